@@ -2,13 +2,16 @@ import readline from 'readline';
 import { blockchainInstance, Transaction } from './src/blockchain.js'; // Adjust based on your exports
 import { createNewWallet, loadWallet, ec } from './src/wallet.js';
 import { MerkleTree, MerkleProofPath, Node } from './src/merkleTree.js';
-import { db } from './src/db.js';
+import { storage } from './src/db.js';
+import { migrate, assertSchemaIsCurrent } from './src/storage/migrator.js';
+import { exportChain, importChain } from './src/snapshot.js';
 import crypto from 'crypto';
 import util from 'util';
 import Decimal from 'decimal.js';
-import { initP2PServer, broadcastBlock, broadcastChain, broadcastTransaction } from './src/p2p.js';
+import yargs from 'yargs/yargs';
+import { hideBin } from 'yargs/helpers';
+import { initP2PServer, broadcastBlock, broadcastChainTip, broadcastTransaction } from './src/p2p.js';
 
-//const queryAsync = util.promisify(db.query).bind(db);
 
 const rl = readline.createInterface({
   input: process.stdin,
@@ -26,18 +29,13 @@ async function main() {
 
     console.log("Initializing P2P server...");
     // Initialize the P2P server with the blockchain instance
-    initP2PServer(blockchainInstance);
+    initP2PServer();
     console.log("P2P server initialized.");
 
     console.log("Initializing blockchain...");
     // Initialize the blockchain
-    await blockchainInstance.init();
+    await blockchainInstance.init(); // Also starts the mining scheduler
     console.log("Blockchain initialized.");
-
-    console.log("Starting automatic mining...");
-    // Start automatic mining using the interval defined in the Blockchain class
-    blockchainInstance.startTimeBasedMining(blockchainInstance.miningIntervalInSeconds);
-    console.log("Automatic mining started.");
   } catch (error) {
     console.error("Error during initialization:", error.message);
     rl.close();
@@ -58,7 +56,10 @@ async function main() {
     8. Run transaction test 
     9. Validate blockchain
     10. Verify Merkle proof by transaction hash 
-    11. Exit
+    11. View balance history of address
+    12. View coin supply
+    13. Mining controls
+    14. Exit
     `);
 
     const choice = await askQuestion("Select an option: ");
@@ -95,6 +96,15 @@ async function main() {
         await verifyMerkleProofByTransactionHash();
         break;
       case "11":
+        await viewBalanceHistory();
+        break;
+      case "12":
+        await viewSupply();
+        break;
+      case "13":
+        await miningControls();
+        break;
+      case "14":
         console.log("Exiting...");
         rl.close();
         return;
@@ -107,13 +117,6 @@ async function main() {
 async function sendTransaction() {
   try {
     console.log("Starting transaction submission process...");
-    if (!(await blockchainInstance.isChainValid())) {
-      console.log("Blockchain is invalid. Transaction cannot proceed.");
-      return;
-    }
-
-    console.log("Blockchain is valid. Proceeding with transaction.");
-
     const fromAddress = await askQuestion("Enter your wallet address: ");
 
     if (!fromAddress || fromAddress.length < 24 || fromAddress.length > 30) {
@@ -167,11 +170,28 @@ async function sendTransaction() {
       return;
     }
 
+    const estimates = [];
+    for (const targetBlocks of [1, 3, 6]) {
+      estimates.push(await blockchainInstance.estimateFee(targetBlocks));
+    }
+    console.log("Suggested fees:");
+    estimates.forEach(estimate => {
+      console.log(`  Within ${estimate.targetBlocks} block(s): ${estimate.fee} (${estimate.feeRatePerKb} per kB)`);
+    });
+
+    const feeInput = await askQuestion(`Enter the fee to pay the miner (leave empty for ${estimates[0].fee}): `);
+    const fee = feeInput.trim() === "" ? Number(estimates[0].fee) : parseFloat(feeInput);
+
+    if (isNaN(fee) || fee < 0) {
+      console.log("Invalid fee.");
+      return;
+    }
+
     console.log("Fetching sender's balance...");
     const senderBalance = await blockchainInstance.getBalanceOfAddress(fromAddress);
     console.log(`Sender's balance: ${senderBalance}`);
 
-    if (new Decimal(senderBalance).lessThan(amount)) {
+    if (new Decimal(senderBalance).lessThan(new Decimal(amount).plus(fee))) {
       console.log("Insufficient funds in the wallet.");
       return;
     }
@@ -180,7 +200,7 @@ async function sendTransaction() {
     const originTransactionHash = latestTransaction ? latestTransaction.hash : null;
 
     console.log("Creating new transaction...");
-    const tx = new Transaction(fromAddress, toAddress, amount, Date.now(), null, "", originTransactionHash);
+    const tx = new Transaction(fromAddress, toAddress, amount, Date.now(), null, "", originTransactionHash, "", null, fee, blockchainInstance.getChainId());
     await tx.signWithAddress(fromAddress); // This now includes publicKey
     console.log("Transaction signed successfully.");
 
@@ -193,16 +213,10 @@ async function sendTransaction() {
 }
 
 async function viewBlockchain() {
-  if (!blockchainInstance.isChainValid()) {
-    console.log("Blockchain is invalid. Cannot view blockchain.");
-    return;
-  }
-
-  const blocks = blockchainInstance.chain;
-  console.log(`Total blocks: ${blocks.length}`);
-  blocks.forEach((block) => {
+  console.log(`Total blocks: ${blockchainInstance.getChainLength()}`);
+  for await (const block of blockchainInstance.iterateBlocks()) {
     console.log(`Block ${block.index}: ${block.transactions.length} transactions, Hash: ${block.hash}`);
-  });
+  }
 }
 
 async function checkBalance() {
@@ -213,16 +227,44 @@ async function checkBalance() {
     return;
   }
 
-  if (!blockchainInstance.isChainValid()) {
-    console.log("Blockchain is invalid. Cannot fetch balance.");
+  const heightAnswer = await askQuestion("Enter a block height (leave empty for the current balance): ");
+
+  try {
+    if (heightAnswer.trim() === "") {
+      const balance = await blockchainInstance.getBalanceOfAddress(address);
+      console.log(`Balance of address ${address}: ${balance}`);
+    } else {
+      const height = Number(heightAnswer);
+      const balance = await blockchainInstance.getBalanceAt(address, height);
+      console.log(`Balance of address ${address} at block ${height}: ${balance}`);
+    }
+  } catch (error) {
+    console.error("Error fetching balance:", error.message);
+  }
+}
+
+async function viewBalanceHistory() {
+  const address = await askQuestion("Enter the address to view balance history: ");
+
+  if (!address || address.length < 24 || address.length > 30) {
+    console.log("Invalid wallet address.");
     return;
   }
 
   try {
-    const balance = await blockchainInstance.getBalanceOfAddress(address);
-    console.log(`Balance of address ${address}: ${balance}`);
+    const history = await blockchainInstance.getBalanceHistory(address);
+    if (history.length === 0) {
+      console.log(`No balance changes found for address ${address}`);
+      return;
+    }
+
+    console.log(`Balance history for address ${address}:`);
+    history.forEach((entry) => {
+      const sign = entry.change.startsWith('-') ? '' : '+';
+      console.log(`Block ${entry.height} (${entry.blockHash}): ${sign}${entry.change} -> ${entry.balance}`);
+    });
   } catch (error) {
-    console.error("Error fetching balance:", error);
+    console.error("Error fetching balance history:", error);
   }
 }
 
@@ -234,36 +276,100 @@ async function viewTransactionsForAddress() {
     return;
   }
 
-  if (!blockchainInstance.isChainValid()) {
-    console.log("Blockchain is invalid. Cannot view transactions.");
-    return;
-  }
-
-  const allTransactions = [];
+  try {
+    let page = await blockchainInstance.getAddressHistory(address, { limit: 20 });
 
-  blockchainInstance.chain.forEach((block) => {
-    block.transactions.forEach((transaction) => {
-      if (transaction.fromAddress === address || transaction.toAddress === address) {
-        allTransactions.push(transaction);
-      }
-    });
-  });
+    if (page.entries.length === 0 && page.pending.length === 0) {
+      console.log(`No transactions found for address ${address}`);
+      return;
+    }
 
-  if (allTransactions.length === 0) {
-    console.log(`No transactions found for address ${address}`);
-  } else {
     console.log(`Transactions for address ${address}:`);
-    allTransactions.forEach((transaction, index) => {
-      console.log(`
-        Transaction ${index + 1}:
-        From: ${transaction.fromAddress}
-        To: ${transaction.toAddress}
-        Amount: ${transaction.amount}
-        Timestamp: ${new Date(transaction.timestamp).toLocaleString()}
-        Hash: ${transaction.hash}
-        Public Key: ${transaction.publicKey}
+    page.pending.forEach(printHistoryEntry);
+
+    while (true) {
+      page.entries.forEach(printHistoryEntry);
+      if (!page.nextCursor) break;
+
+      const more = await askQuestion("Show older transactions? (y/n): ");
+      if (more.trim().toLowerCase() !== "y") break;
+      page = await blockchainInstance.getAddressHistory(address, { limit: 20, cursor: page.nextCursor });
+    }
+  } catch (error) {
+    console.error("Error fetching transactions:", error);
+  }
+}
+
+function printHistoryEntry(entry) {
+  const location = entry.status === "pending"
+    ? "Pending"
+    : `Block ${entry.blockHeight} (${entry.confirmations} confirmations)`;
+  console.log(`
+        ${location}:
+        From: ${entry.fromAddress}
+        To: ${entry.toAddress}
+        Amount: ${entry.amount}
+        Fee: ${entry.fee}
+        Timestamp: ${new Date(entry.timestamp).toLocaleString()}
+        Hash: ${entry.hash}
       `);
-    });
+}
+
+async function viewSupply() {
+  try {
+    const supply = await blockchainInstance.getSupplyInfo();
+    console.log(`
+      Height: ${supply.height}
+      Issued: ${supply.issued}
+      Circulating: ${supply.circulating}
+      Scheduled by emission: ${supply.scheduled}
+      Supply limit: ${supply.limit === null ? "none" : supply.limit}
+      Remaining: ${supply.remaining === null ? "unlimited" : supply.remaining}
+      Next block subsidy: ${supply.nextSubsidy}
+      Next halving at block: ${supply.nextHalvingHeight === null ? "never" : supply.nextHalvingHeight}
+    `);
+  } catch (error) {
+    console.error("Error fetching supply information:", error);
+  }
+}
+
+async function miningControls() {
+  const scheduler = blockchainInstance.miningScheduler;
+  const status = scheduler.status();
+  console.log(`
+      Policy: ${status.policy}
+      Automatic mining: ${status.active ? "running" : "stopped"}
+      Mining a block now: ${status.mining ? "yes" : "no"}
+      Reward address: ${status.rewardAddress || "not set"}
+      Pending transactions: ${status.pendingTransactions}
+      Blocks mined: ${status.blocksMined}
+      Last block mined: ${status.lastBlockAt === null ? "never" : new Date(status.lastBlockAt).toLocaleString()}
+  `);
+
+  const action = await askQuestion("Enter 'start', 'stop', 'mine', 'address' or nothing to go back: ");
+  try {
+    switch (action.trim()) {
+      case "start":
+        scheduler.start();
+        break;
+      case "stop":
+        scheduler.stop();
+        console.log("Automatic mining stopped.");
+        break;
+      case "mine": {
+        const block = await scheduler.mineNow();
+        console.log(block ? `Mined block ${block.index} (${block.hash}).` : "No block was mined.");
+        break;
+      }
+      case "address":
+        scheduler.setRewardAddress((await askQuestion("Enter the reward address: ")).trim());
+        console.log("Reward address updated.");
+        break;
+      default:
+        break;
+    }
+  } catch (error) {
+    console.error("Error controlling mining:", error.message);
   }
 }
 
@@ -275,24 +381,11 @@ async function traceTransaction() {
     return;
   }
 
-  if (!blockchainInstance.isChainValid()) {
-    console.log("Blockchain is invalid. Cannot trace transaction.");
-    return;
-  }
-
-  let foundTransaction = null;
-  let blockIndex = null;
-
-  blockchainInstance.chain.forEach((block, index) => {
-    block.transactions.forEach((transaction) => {
-      if (transaction.hash === transactionHash) {
-        foundTransaction = transaction;
-        blockIndex = index;
-      }
-    });
-  });
+  const found = await blockchainInstance.findTransaction(transactionHash);
 
-  if (foundTransaction) {
+  if (found) {
+    const foundTransaction = found.transaction;
+    const blockIndex = found.block.index;
     console.log(`
       Transaction found in Block ${blockIndex}:
       From: ${foundTransaction.fromAddress}
@@ -316,52 +409,23 @@ async function traceFundMovement() {
     return;
   }
 
-  if (!blockchainInstance.isChainValid()) {
-    console.log("Blockchain is invalid. Cannot trace fund movement.");
-    return;
-  }
-
-  let currentTransaction = null;
-  let blockIndex = null;
+  let found = await blockchainInstance.findTransaction(transactionHash);
 
-  for (let i = 0; i < blockchainInstance.chain.length; i++) {
-    const block = blockchainInstance.chain[i];
-    for (let j = 0; j < block.transactions.length; j++) {
-      if (block.transactions[j].hash === transactionHash) {
-        currentTransaction = block.transactions[j];
-        blockIndex = i;
-        break;
-      }
-    }
-    if (currentTransaction) break;
-  }
-
-  if (!currentTransaction) {
+  if (!found) {
     console.log(`Transaction with hash ${transactionHash} not found.`);
     return;
   }
 
-  console.log(`Tracing fund movement starting from transaction in Block ${blockIndex}...`);
-  displayTransactionDetails(currentTransaction, blockIndex);
-
-  while (currentTransaction.originTransactionHash) {
-    const originHash = currentTransaction.originTransactionHash;
-    currentTransaction = null;
+  console.log(`Tracing fund movement starting from transaction in Block ${found.block.index}...`);
+  displayTransactionDetails(found.transaction, found.block.index);
 
-    for (let i = 0; i < blockchainInstance.chain.length; i++) {
-      const block = blockchainInstance.chain[i];
-      for (let j = 0; j < block.transactions.length; j++) {
-        if (block.transactions[j].hash === originHash) {
-          currentTransaction = block.transactions[j];
-          blockIndex = i;
-          break;
-        }
-      }
-      if (currentTransaction) break;
-    }
+  while (found.transaction.originTransactionHash) {
+    const originHash = found.transaction.originTransactionHash;
+    const origin = await blockchainInstance.findTransaction(originHash);
 
-    if (currentTransaction) {
-      displayTransactionDetails(currentTransaction, blockIndex);
+    if (origin) {
+      found = origin;
+      displayTransactionDetails(found.transaction, found.block.index);
     } else {
       console.log(`Reached the end of the transaction chain. No further origin transactions found.`);
       break;
@@ -385,11 +449,6 @@ function displayTransactionDetails(transaction, blockIndex) {
 async function runTransactionAndMiningTest() {
   console.log("Running transaction test...");
 
-  if (!blockchainInstance.isChainValid()) {
-    console.log("Blockchain is invalid. Cannot run the test.");
-    return;
-  }
-
   let previousTransactionHash = null;
   const wallets = [];
   let transactionCount = 0;
@@ -400,7 +459,7 @@ async function runTransactionAndMiningTest() {
     wallets.push(wallet);
   }
 
-  const genesisRewardAddress = blockchainInstance.genesisAddress;
+  const genesisRewardAddress = blockchainInstance.genesisSpec.allocations[0].address; // Funded by the genesis block
 
   for (let i = 0; i < 12; i++) {
     const toWallet = wallets[i % 2];
@@ -409,7 +468,7 @@ async function runTransactionAndMiningTest() {
     const amount = 10;
     const timestamp = Date.now();
 
-    const tx = new Transaction(fromAddress, toAddress, amount, timestamp, null, '', previousTransactionHash);
+    const tx = new Transaction(fromAddress, toAddress, amount, timestamp, null, '', previousTransactionHash, '', null, 0, blockchainInstance.getChainId());
     await tx.signWithAddress(fromAddress); // This now includes publicKey
     await blockchainInstance.addPendingTransaction(tx);
     previousTransactionHash = tx.hash;
@@ -425,19 +484,19 @@ async function validateBlockchain() {
   let validatedTransactionsCount = 0;
 
   try {
-    for (let i = 0; i < blockchainInstance.chain.length; i++) {
-      const block = blockchainInstance.chain[i];
+    // Replay the whole chain from genesis
+    const result = await blockchainInstance.validateLocalChain();
+    if (!result.valid) {
+      console.log(`Blockchain is invalid: block ${result.error.height} (${result.error.hash}) ${result.error.reason}.`);
+      return;
+    }
 
-      // Validate block integrity
-      const isBlockValid = await blockchainInstance.constructor.isValidChain(blockchainInstance.chain.slice(0, i + 1).map(b => b.toJSON()));
-      if (!isBlockValid) {
-        console.log(`Block ${i} is invalid.`);
-        return;
-      }
+    for await (const block of blockchainInstance.iterateBlocks()) {
+      const i = block.index;
 
       for (const transaction of block.transactions) {
         const isGenesisBlock = i === GENESIS_BLOCK_INDEX;
-        const isValid = await validateTransaction(transaction, i, isGenesisBlock);
+        const isValid = await validateTransaction(transaction, block, isGenesisBlock);
 
         if (!isValid) {
           console.log(`Invalid transaction ${transaction.hash} in block ${i}.`);
@@ -450,7 +509,7 @@ async function validateBlockchain() {
       validatedBlocksCount++;
     }
 
-    const allAddresses = getAllAddressesFromBlockchain();
+    const allAddresses = await getAllAddressesFromBlockchain();
 
     for (const address of allAddresses) {
       const balance = await blockchainInstance.getBalanceOfAddress(address);
@@ -472,11 +531,11 @@ function isHexString(str) {
   return typeof str === 'string' && /^[0-9a-fA-F]+$/.test(str);
 }
 
-async function validateTransaction(transaction, blockIndex, isGenesis = false) {
-  const GENESIS_ADDRESS = '6c7f05cca415fd2073de8ea8853834';
-  const isMiningReward = transaction === blockchainInstance.chain[blockIndex].transactions[blockchainInstance.chain[blockIndex].transactions.length - 1];
+async function validateTransaction(transaction, block, isGenesis = false) {
+  const genesisAddresses = blockchainInstance.genesisSpec.allocations.map(allocation => allocation.address);
+  const isMiningReward = transaction === block.transactions[block.transactions.length - 1];
 
-  if (isGenesis || transaction.fromAddress === GENESIS_ADDRESS || isMiningReward) {
+  if (isGenesis || genesisAddresses.includes(transaction.fromAddress) || isMiningReward) {
     return true;
   }
 
@@ -527,14 +586,14 @@ async function validateTransaction(transaction, blockIndex, isGenesis = false) {
   return true;
 }
 
-function getAllAddressesFromBlockchain() {
+async function getAllAddressesFromBlockchain() {
   const addresses = new Set();
-  blockchainInstance.chain.forEach(block => {
+  for await (const block of blockchainInstance.iterateBlocks()) {
     block.transactions.forEach(transaction => {
       if (transaction.fromAddress) addresses.add(transaction.fromAddress);
       if (transaction.toAddress) addresses.add(transaction.toAddress);
     });
-  });
+  }
   return Array.from(addresses);
 }
 
@@ -543,15 +602,14 @@ async function verifyMerkleProofByTransactionHash() {
 
   try {
     // Retrieve the block hash associated with the transaction hash
-    const blockHashQuery = "SELECT block_hash FROM transactions WHERE hash = ?";
-    const [result] = await db.query(blockHashQuery, [transactionHash]);
+    const transactionRow = await storage.getTransactionByHash(transactionHash);
 
-    if (result.length === 0) {
+    if (!transactionRow) {
       console.log("Transaction not found in the blockchain.");
       return;
     }
 
-    const blockHash = result[0].block_hash;
+    const blockHash = transactionRow.block_hash;
     console.log(`Block hash containing the transaction: ${blockHash}`);
 
     // Retrieve the Merkle proof path
@@ -563,15 +621,14 @@ async function verifyMerkleProofByTransactionHash() {
     }
 
     // Retrieve the Merkle root from the block
-    const blockQuery = "SELECT merkle_root FROM blocks WHERE hash = ?";
-    const [blockResult] = await db.query(blockQuery, [blockHash]);
+    const blockRow = await storage.getBlockByHash(blockHash);
 
-    if (blockResult.length === 0) {
+    if (!blockRow) {
       console.log("Block not found in the blockchain.");
       return;
     }
 
-    const merkleRoot = blockResult[0].merkle_root;
+    const merkleRoot = blockRow.merkle_root;
 
     // Initialize the hash with the transaction hash
     let currentHash = transactionHash;
@@ -613,5 +670,125 @@ async function verifyMerkleProofByTransactionHash() {
 }
 
 
+async function runMigrations() {
+  try {
+    const result = await migrate(storage);
+    if (result.applied.length === 0) {
+      console.log(`Database schema is up to date (version ${result.to}).`);
+    } else {
+      console.log(`Database schema migrated from version ${result.from} to ${result.to}.`);
+    }
+  } catch (err) {
+    console.error("Migration failed:", err);
+    process.exitCode = 1;
+  } finally {
+    rl.close();
+    await storage.close();
+  }
+}
+
+async function runReindex(dryRun) {
+  try {
+    const reported = {}; // Last percentage printed per phase
+    const report = await blockchainInstance.reindex({
+      dryRun,
+      onProgress: ({ phase, height, total }) => {
+        const percent = Math.floor(((height + 1) / total) * 100);
+        if (reported[phase] === undefined || percent >= reported[phase] + 10 || height + 1 === total) {
+          console.log(`[${phase}] ${height + 1}/${total} blocks (${percent}%)`);
+          reported[phase] = percent;
+        }
+      }
+    });
+
+    console.log(`Replayed ${report.blocks} blocks and ${report.transactions} transactions.`);
+    if (report.balanceDifferences.length === 0) {
+      console.log("All address balances match the chain.");
+    } else {
+      console.log(`${report.balanceDifferences.length} address balance(s) ${dryRun ? "differ" : "were corrected"}:`);
+      report.balanceDifferences.forEach(({ address, stored, expected }) => {
+        console.log(`  ${address}: stored ${stored}, expected ${expected}`);
+      });
+    }
+    if (dryRun) {
+      console.log(`${report.blocksWithMissingMerkleData.length} block(s) have missing or misplaced Merkle proofs.`);
+      console.log("Dry run: no changes were written.");
+    } else {
+      console.log("Derived tables rebuilt.");
+    }
+  } catch (err) {
+    console.error("Reindex failed:", err);
+    process.exitCode = 1;
+  } finally {
+    rl.close();
+    await storage.close();
+  }
+}
+
+async function runExport(filePath, fromHeight, toHeight) {
+  try {
+    if (!filePath) {
+      throw new Error("Usage: export <file> [--from <height>] [--to <height>]");
+    }
+    await assertSchemaIsCurrent(storage);
+    await blockchainInstance.loadChainFromDatabase();
+
+    const header = await exportChain(blockchainInstance, filePath, { fromHeight, toHeight });
+    console.log(`Exported blocks ${header.fromHeight}..${header.toHeight} (${header.blockCount} blocks) to ${filePath}.`);
+  } catch (err) {
+    console.error("Export failed:", err.message);
+    process.exitCode = 1;
+  } finally {
+    rl.close();
+    await storage.close();
+  }
+}
+
+async function runImport(filePath) {
+  try {
+    if (!filePath) {
+      throw new Error("Usage: import <file>");
+    }
+    await assertSchemaIsCurrent(storage);
+    await blockchainInstance.loadChainFromDatabase();
+
+    const result = await importChain(blockchainInstance, filePath, {
+      onProgress: ({ height, imported }) => {
+        if (imported > 0 && imported % 1000 === 0) {
+          console.log(`Imported ${imported} blocks (height ${height})...`);
+        }
+      }
+    });
+    console.log(`Imported ${result.imported} new block(s), ${result.skipped} already present. Chain height is now ${blockchainInstance.getHeight()}.`);
+  } catch (err) {
+    console.error("Import failed:", err.message);
+    process.exitCode = 1;
+  } finally {
+    rl.close();
+    await storage.close();
+  }
+}
+
+function optionalHeight(value) {
+  return value === undefined ? undefined : Number(value);
+}
 
-main().catch(console.error);
+const args = yargs(hideBin(process.argv)).help(false).version(false).parse();
+const [command] = args._;
+
+switch (command) {
+  case "migrate":
+    runMigrations();
+    break;
+  case "reindex":
+    runReindex(Boolean(args["dry-run"]));
+    break;
+  case "export":
+    runExport(args._[1], optionalHeight(args.from), optionalHeight(args.to));
+    break;
+  case "import":
+    runImport(args._[1]);
+    break;
+  default:
+    main().catch(console.error);
+}
@@ -1,14 +1,15 @@
 "use strict";
 
 import crypto from "crypto";
-import { db } from './db.js';
+import { storage } from './db.js';
 import { Transaction } from './transaction.js';
 import { MerkleTree, MerkleProofPath } from './merkleTree.js';
 import Decimal from 'decimal.js';
+import { meetsDifficulty } from './difficulty.js';
 
 
 class Block {
-  constructor(index, previousHash, timestamp, transactions, difficulty) {
+  constructor(index, previousHash, timestamp, transactions, difficulty, chainId = null) {
     this.index = index;
     this.previousHash = previousHash;
     this.timestamp = timestamp;
@@ -17,6 +18,8 @@ class Block {
     this.merkleRoot = this.calculateMerkleRoot();
     this.nonce = 0;
     this.originTransactionHash = this.calculateLastOriginTransactionHash(); 
+    this.extraData = null; // Free-form data committed to by the hash, only set on genesis blocks
+    this.chainId = chainId; // Network the block was mined for, taken from its genesis block
     this.hash = this.calculateHash();
   }
 
@@ -30,6 +33,8 @@ class Block {
       merkle_root: this.merkleRoot,
       hash: this.hash,
       origin_transaction_hash: this.originTransactionHash,
+      extra_data: this.extraData,
+      chain_id: this.chainId,
       transactions: this.transactions.map(tx => tx.toJSON())
     };
   }
@@ -40,15 +45,43 @@ class Block {
       data.previous_hash,
       data.timestamp,
       data.transactions.map(txData => Transaction.fromJSON(txData)),
-      data.difficulty
+      data.difficulty,
+      data.chain_id !== undefined ? data.chain_id : null
     );
     block.hash = data.hash;
     block.nonce = data.nonce;
     block.merkleRoot = data.merkle_root;
     block.originTransactionHash = data.origin_transaction_hash;
+    block.extraData = data.extra_data !== undefined ? data.extra_data : null;
     return block;
   }
 
+  // Fields kept in memory for every block of the chain; bodies are loaded on demand
+  getHeader() {
+    return {
+      index: this.index,
+      hash: this.hash,
+      previousHash: this.previousHash,
+      merkleRoot: this.merkleRoot,
+      difficulty: this.difficulty,
+      timestamp: this.timestamp,
+      chainId: this.chainId
+    };
+  }
+
+  // Header of a stored block, built from its blocks table row
+  static headerFromRow(row) {
+    return {
+      index: row.index,
+      hash: row.hash,
+      previousHash: row.previous_hash,
+      merkleRoot: row.merkle_root,
+      difficulty: Number(row.difficulty),
+      timestamp: Number(row.timestamp),
+      chainId: row.chain_id !== undefined ? row.chain_id : null
+    };
+  }
+
   // Calculate the Merkle root for the transactions in the block
   calculateMerkleRoot() {
     if (this.transactions.length === 0) {
@@ -62,9 +95,9 @@ class Block {
   calculateLastOriginTransactionHash() {
     if (this.transactions.length === 0) return null;
     
-    // Handle the case where the last transaction might be a mining reward with a null originTransactionHash
+    // Handle the case where the last transaction is a mining reward, whose originTransactionHash is null or the block height
     const lastTransaction = this.transactions[this.transactions.length - 1];
-    if (lastTransaction.originTransactionHash) {
+    if (lastTransaction.originTransactionHash && lastTransaction.fromAddress !== null) {
       return lastTransaction.originTransactionHash;
     }
     
@@ -75,6 +108,15 @@ class Block {
 
   // Calculate the hash of the block
   calculateHash() {
+    const { prefix, suffix } = this.hashParts();
+    return crypto
+      .createHash("sha256")
+      .update(prefix + this.nonce + suffix)
+      .digest("hex");
+  }
+
+  // Data covered by the block hash, split around the nonce so miners can try nonces without rebuilding it
+  hashParts() {
     const transactionsData = JSON.stringify(
         this.transactions.map((tx) => {
           return {
@@ -90,39 +132,31 @@ class Block {
         })
     );
   
-    const dataToHash =
-      (this.previousHash || '0') +
-      this.timestamp +
-      this.merkleRoot +
-      this.nonce +
-      (this.originTransactionHash || '') +
-      transactionsData;
-
-    const hash = crypto
-      .createHash("sha256")
-      .update(dataToHash)
-      .digest("hex");
-  
-    return hash;
+    return {
+      prefix: (this.previousHash || '0') + this.timestamp + this.merkleRoot,
+      suffix:
+        (this.originTransactionHash || '') +
+        transactionsData +
+        (this.extraData || '') + // Blocks without extra data or a chain ID keep the hash they always had
+        (this.chainId ? `@${this.chainId}` : '')
+    };
   }
 
   // Mine the block by finding a hash that meets the difficulty requirements
   mineBlock(difficulty) {
-    while (
-      this.hash.substring(0, difficulty) !== Array(difficulty + 1).join("0")
-    ) {
+    while (!meetsDifficulty(this.hash, difficulty)) {
       this.nonce++; // Increment the nonce
       this.hash = this.calculateHash(); // Recalculate the block hash
     }
   }
 
-  // Check if all transactions in the block are valid
-  async hasValidTransactions() {
+  // Check if all transactions in the block are valid, and made for `chainId` when it is given
+  async hasValidTransactions(chainId) {
     for (const tx of this.transactions) {
 
       tx.verifyTransaction();
 
-      if (!tx.isValid()) {
+      if (!tx.isValid(chainId)) {
         console.error(`Invalid transaction: ${tx.hash}`); // Log invalid transactions
         return false;
       }
@@ -130,61 +164,94 @@ class Block {
     return true; // All transactions are valid
   }
 
+  // Storage row for the blocks table
+  toRow() {
+    return {
+      hash: this.hash,
+      previous_hash: this.previousHash,
+      timestamp: this.timestamp,
+      nonce: this.nonce,
+      difficulty: this.difficulty,
+      merkle_root: this.merkleRoot,
+      index: this.index,
+      origin_transaction_hash: this.originTransactionHash,
+      extra_data: this.extraData,
+      chain_id: this.chainId
+    };
+  }
+
+  // Persist the block atomically: either everything is stored or nothing is
   async save() {
-    const query = "INSERT INTO blocks (hash, previous_hash, timestamp, nonce, difficulty, merkle_root, `index`, origin_transaction_hash) VALUES (?, ?, ?, ?, ?, ?, ?, ?)";
-    const values = [
-      this.hash,
-      this.previousHash,
-      this.timestamp,
-      this.nonce,
-      this.difficulty,
-      this.merkleRoot,
-      this.index,
-      this.originTransactionHash
-    ];
-    
     try {
-      const [results] = await db.query(query, values);
-
-      
-      for (let i = 0; i < this.transactions.length; i++) {
-        const tx = this.transactions[i];
-        tx.blockHash = this.hash;
-        tx.index_in_block = i; // Assign the transaction's index
-        await tx.save();
-            }
-      await this.updateBalances(); // Update balances after saving transactions
-
-      const merkleTree = new MerkleTree(
-        this.transactions.map((tx) => tx.hash)
-      );
-  
-      await merkleTree.saveNodesToDatabase(this.hash);
-
-      // Store Merkle proofs
-      for (const tx of this.transactions) {
-        const proof = merkleTree.getProof(tx.hash);
-        await this.saveMerkleProof(tx.hash, proof);
-      }
-  
-        return results;
+      return await storage.withTransaction(store => this.connect(store));
     } catch (err) {
-        if (err.code === 'ER_DUP_ENTRY') {
-        } else {
-            console.error(`Error saving block ${this.index}:`, err);
-            throw err;
-        }
+      console.error(`Error saving block ${this.index}:`, err);
+      throw err;
+    }
+  }
+
+  /**
+   * Writes the block, its transactions, balance updates and Merkle data
+   * through the given storage handle. Re-applying a block that is already
+   * stored leaves the database untouched.
+   * @param {StorageAdapter} store - Transactional storage handle
+   * @returns {Promise<boolean>} - False if the block was already stored
+   */
+  async connect(store) {
+    if (await store.getBlockByHash(this.hash)) {
+      return false;
+    }
+
+    await store.saveBlock(this.toRow());
+
+    const chainId = this.index === 0 ? undefined : this.chainId || null; // Genesis allocations carry no chain ID, their block does
+    for (let i = 0; i < this.transactions.length; i++) {
+      const tx = this.transactions[i];
+      tx.blockHash = this.hash;
+      tx.index_in_block = i; // Assign the transaction's index
+      await tx.save(store, chainId);
+    }
+    await this.updateBalances(store); // Update balances after saving transactions
+    await this.saveMerkleData(store);
+    return true;
+  }
+
+  // Store the Merkle tree nodes and the proof path of every transaction
+  async saveMerkleData(store = storage) {
+    const merkleTree = new MerkleTree(
+      this.transactions.map((tx) => tx.hash)
+    );
+
+    await merkleTree.saveNodesToDatabase(this.hash, store);
+
+    for (const tx of this.transactions) {
+      const proof = merkleTree.getProof(tx.hash);
+      await this.saveMerkleProof(tx.hash, proof, store);
     }
   }
   
 
-  async saveMerkleProof(transactionHash, proof) {
-    const query =
-      "INSERT INTO merkle_proof_paths (block_hash, transaction_hash, proof_path) VALUES (?, ?, ?)";
-    const values = [this.hash, transactionHash, JSON.stringify(proof)];
+  /**
+   * Reverses `connect`: undoes the block's balance changes and removes its
+   * Merkle data, transaction rows and block row.
+   * @param {StorageAdapter} store - Transactional storage handle
+   */
+  async disconnect(store) {
+    await this.updateBalances(store, -1);
+    await store.deleteMerkleData(this.hash);
+    await store.deleteTransactionsByBlockHash(this.hash);
+    await store.deleteBlock(this.hash);
+  }
+
+  async saveMerkleProof(transactionHash, proof, store = storage) {
+    const row = {
+      block_hash: this.hash,
+      transaction_hash: transactionHash,
+      proof_path: JSON.stringify(proof)
+    };
 
     try {
-      await db.query(query, values);
+      await store.saveMerkleProof(row);
     } catch (err) {
         console.error(`Error saving proof path for transaction ${transactionHash}:`, err);
       throw err;
@@ -193,80 +260,136 @@ class Block {
 
   // Load a block from the database
   static async load(hash) {
-    const query = "SELECT * FROM blocks WHERE hash = ?";
     try {
-      const [results] = await db.query(query, [hash]);
-      if (results.length === 0) {
+      const result = await storage.getBlockByHash(hash);
+      if (!result) {
         return null;
       }
   
-      const result = results[0];
-      const block = new Block(
-        result.index,
-        result.previous_hash, // null for genesis block
-        result.timestamp,
-        [],
-        result.difficulty
-      );
-      block.hash = result.hash;
-      block.nonce = result.nonce;
-      block.merkleRoot = result.merkle_root;
-      block.originTransactionHash = result.origin_transaction_hash;
-  
       // Load transactions in the correct order
-      const txQuery = "SELECT hash FROM transactions WHERE block_hash = ? ORDER BY index_in_block ASC";
-      const [txResults] = await db.query(txQuery, [block.hash]);
-  
-      for (const tx of txResults) {
-        const transaction = await Transaction.load(tx.hash);
-        if (transaction) {
-          if (!transaction.isValid()) {
-            console.error(`Invalid transaction in block ${block.index}: ${tx.hash}`);
-            throw new Error(`Invalid transaction in block ${block.index}`);
-          }
-          block.transactions.push(transaction);
-        }
-      }
+      const txRows = await storage.getTransactionsByBlockHash(result.hash);
+      return Block.fromRows(result, txRows);
+    } catch (err) {
+      console.error("Error loading block:", err);
+      throw err;
+    }
+  }
+
+  /**
+   * Load the blocks between two heights with two queries: one for the block
+   * rows and one for all of their transactions.
+   * @param {number} fromIndex - First height, inclusive
+   * @param {number} toIndex - Last height, inclusive
+   * @returns {Promise<Block[]>} - Blocks ordered by height
+   */
+  static async loadRange(fromIndex, toIndex, store = storage) {
+    try {
+      const blockRows = await store.getBlocksByHeightRange(fromIndex, toIndex);
+      const txRows = await store.getTransactionsByBlockHashes(blockRows.map(row => row.hash));
 
-      if (block.index !== 0) { // Skip genesis block hash verification
-        const recalculatedHash = block.calculateHash();
-        if (block.hash !== recalculatedHash) {
-            console.error(`Invalid block hash for block ${block.index}:`);
-            console.error(`Stored Hash: ${block.hash}`);
-            console.error(`Recalculated Hash: ${recalculatedHash}`);
-            throw new Error(`Invalid block hash for block ${block.index}`);
-        }
+      const txRowsByBlock = new Map(blockRows.map(row => [row.hash, []]));
+      for (const txRow of txRows) {
+        txRowsByBlock.get(txRow.block_hash).push(txRow);
       }
-  
-      return block;
+
+      return blockRows.map(row => Block.fromRows(row, txRowsByBlock.get(row.hash)));
     } catch (err) {
-      console.error("Error loading block:", err);
+      console.error(`Error loading blocks ${fromIndex}..${toIndex}:`, err);
       throw err;
     }
   }
+
+  // Build and verify a block from its row and its transaction rows (ordered by index_in_block)
+  static fromRows(result, txRows) {
+    const block = new Block(
+      result.index,
+      result.previous_hash, // null for genesis block
+      Number(result.timestamp),
+      [],
+      Number(result.difficulty),
+      result.chain_id !== undefined ? result.chain_id : null
+    );
+    block.hash = result.hash;
+    block.nonce = result.nonce;
+    block.merkleRoot = result.merkle_root;
+    block.originTransactionHash = result.origin_transaction_hash;
+    block.extraData = result.extra_data !== undefined ? result.extra_data : null;
+
+    for (const row of txRows) {
+      const transaction = Transaction.fromRow(row);
+      if (!transaction.isValid()) {
+        console.error(`Invalid transaction in block ${block.index}: ${row.hash}`);
+        throw new Error(`Invalid transaction in block ${block.index}`);
+      }
+      block.transactions.push(transaction);
+    }
+
+    if (block.index !== 0) { // Skip genesis block hash verification
+      const recalculatedHash = block.calculateHash();
+      if (block.hash !== recalculatedHash) {
+          console.error(`Invalid block hash for block ${block.index}:`);
+          console.error(`Stored Hash: ${block.hash}`);
+          console.error(`Recalculated Hash: ${recalculatedHash}`);
+          throw new Error(`Invalid block hash for block ${block.index}`);
+      }
+    }
+
+    return block;
+  }
   
 
-  async updateBalances() {
+  // Net balance change per address caused by this block's transactions
+  getBalanceChanges() {
+    const changes = new Map();
+    const add = (address, amount) => changes.set(address, (changes.get(address) || new Decimal(0)).plus(amount));
+
     for (const tx of this.transactions) {
       if (tx.fromAddress) {
-        await this.updateAddressBalance(tx.fromAddress, -tx.amount);
+        add(tx.fromAddress, new Decimal(tx.amount).plus(tx.fee || 0).negated()); // The sender also pays the fee
       }
       if (tx.toAddress) {
-        await this.updateAddressBalance(tx.toAddress, tx.amount);
+        add(tx.toAddress, tx.amount);
       }
     }
+    return changes;
+  }
+
+  // Sum of the fees paid by this block's transactions, which the coinbase may collect
+  getTotalFees() {
+    return this.transactions
+      .filter(tx => tx.fromAddress !== null)
+      .reduce((total, tx) => total.plus(tx.fee || 0), new Decimal(0));
+  }
+
+  /**
+   * Apply (direction 1) or revert (direction -1) the balance changes of this
+   * block, recording or removing its rows in the balance history.
+   */
+  async updateBalances(store = storage, direction = 1) {
+    for (const [address, delta] of this.getBalanceChanges()) {
+      await this.updateAddressBalance(address, delta.times(direction).toFixed(8), store);
+
+      if (direction > 0) {
+        await store.saveBalanceDelta({
+          address,
+          block_index: this.index,
+          block_hash: this.hash,
+          delta: delta.toFixed(8),
+          balance: new Decimal(await store.getBalance(address)).toFixed(8)
+        });
+      }
+    }
+
+    if (direction < 0) {
+      await store.deleteBalanceDeltasByBlockHash(this.hash);
+    }
   }
 
   
 
-  async updateAddressBalance(address, amount) {
-    const query = `
-      INSERT INTO address_balances (address, balance)
-      VALUES (?, ?)
-      ON DUPLICATE KEY UPDATE balance = balance + ?
-    `;
+  async updateAddressBalance(address, amount, store = storage) {
     try {
-      await db.query(query, [address, amount, amount]);
+      await store.updateBalance(address, amount);
     } catch (err) {
       console.error(`Error updating balance for address ${address}:`, err);
       throw err;
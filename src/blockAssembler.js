@@ -0,0 +1,58 @@
+"use strict";
+
+// Orders two transactions by fee rate, highest first; older and then lower-hash transactions win ties
+function compareByFeeRate(a, b) {
+  return b.getFeeRate().comparedTo(a.getFeeRate()) || a.timestamp - b.timestamp || a.hash.localeCompare(b.hash);
+}
+
+/**
+ * Chooses which pending transactions go into a new block and in what order.
+ * Transactions are taken highest fee rate first while they fit in `maxBytes`
+ * and `maxCount`, but each sender's transactions stay in timestamp order: a
+ * sender's next transaction only competes once the previous one has been
+ * taken, and once one of them does not fit the rest of that sender's queue
+ * waits for a later block.
+ * @param {Transaction[]} candidates - Valid pending transactions, unique by hash
+ * @param {Object} options
+ * @param {number} options.maxBytes - Total serialized size allowed for the selected transactions
+ * @param {number} [options.maxCount=Infinity] - Number of transactions allowed
+ * @returns {Transaction[]} - Selected transactions in block order
+ */
+function selectTransactions(candidates, { maxBytes, maxCount = Infinity }) {
+  const queues = new Map(); // sender -> transactions in timestamp order
+  for (const tx of candidates) {
+    if (!queues.has(tx.fromAddress)) queues.set(tx.fromAddress, []);
+    queues.get(tx.fromAddress).push(tx);
+  }
+  for (const queue of queues.values()) {
+    queue.sort((a, b) => a.timestamp - b.timestamp || a.hash.localeCompare(b.hash));
+  }
+
+  const selected = [];
+  let remainingBytes = maxBytes;
+
+  while (queues.size > 0 && selected.length < maxCount) {
+    let best = null;
+    for (const [sender, queue] of queues) {
+      if (!best || compareByFeeRate(queue[0], best.tx) < 0) {
+        best = { sender, tx: queue[0] };
+      }
+    }
+
+    const queue = queues.get(best.sender);
+    const size = best.tx.getSize();
+    if (size > remainingBytes) {
+      queues.delete(best.sender);
+      continue;
+    }
+
+    selected.push(best.tx);
+    remainingBytes -= size;
+    queue.shift();
+    if (queue.length === 0) queues.delete(best.sender);
+  }
+
+  return selected;
+}
+
+export { selectTransactions };
@@ -0,0 +1,35 @@
+"use strict";
+
+/**
+ * Size of a block for the consensus limit: the serialized size of its
+ * transactions, coinbase included. The header is small and fixed, so it is
+ * left out.
+ * @param {Transaction[]} transactions
+ * @returns {number} - Bytes
+ */
+function blockSize(transactions) {
+  return transactions.reduce((total, tx) => total + tx.getSize(), 0);
+}
+
+/**
+ * Checks a non-genesis block against the maximum transaction count and
+ * maximum size.
+ * @param {Transaction[]} transactions - Block transactions, coinbase included
+ * @param {Object} params - Chain parameters
+ * @param {number} params.maxBlockBytes
+ * @param {number} params.maxBlockTransactions
+ * @returns {string|null} - Why the block is too large, or null if it fits
+ */
+function checkBlockLimits(transactions, { maxBlockBytes, maxBlockTransactions }) {
+  if (transactions.length > maxBlockTransactions) {
+    return `has ${transactions.length} transactions but at most ${maxBlockTransactions} are allowed`;
+  }
+
+  const size = blockSize(transactions);
+  if (size > maxBlockBytes) {
+    return `is ${size} bytes but at most ${maxBlockBytes} are allowed`;
+  }
+  return null;
+}
+
+export { blockSize, checkBlockLimits };
@@ -0,0 +1,50 @@
+"use strict";
+
+// Number of preceding blocks whose median timestamp a new block must exceed
+const MEDIAN_TIME_SPAN = 11;
+
+/**
+ * Median timestamp of the (up to) 11 blocks below `height`.
+ * @param {number} height - Height of the block being checked (>= 1)
+ * @param {Function} headerAt - Returns the header ({ timestamp }) at a lower height
+ * @returns {number}
+ */
+function medianTimePast(height, headerAt) {
+  const timestamps = [];
+  for (let index = Math.max(height - MEDIAN_TIME_SPAN, 0); index < height; index++) {
+    timestamps.push(Number(headerAt(index).timestamp));
+  }
+  timestamps.sort((a, b) => a - b);
+  return timestamps[Math.floor(timestamps.length / 2)];
+}
+
+/**
+ * Checks a block timestamp against the median-time-past and maximum future
+ * drift rules. The genesis block is exempt.
+ * @param {number} timestamp - Block timestamp in ms
+ * @param {number} height - Block height
+ * @param {Function} headerAt - Returns the header at a lower height
+ * @param {Object} params - Chain parameters
+ * @param {number} params.maxFutureDriftSeconds - How far ahead of `now` a timestamp may be
+ * @param {number} now - Network-adjusted current time in ms
+ * @returns {string|null} - Why the timestamp is invalid, or null if it is valid
+ */
+function checkBlockTime(timestamp, height, headerAt, { maxFutureDriftSeconds }, now) {
+  if (!Number.isSafeInteger(timestamp)) {
+    return `timestamp ${timestamp} is not an integer`;
+  }
+  if (height === 0) {
+    return null;
+  }
+
+  const median = medianTimePast(height, headerAt);
+  if (timestamp <= median) {
+    return `timestamp ${timestamp} is not after the median time of the previous blocks (${median})`;
+  }
+  if (timestamp > now + maxFutureDriftSeconds * 1000) {
+    return `timestamp ${timestamp} is more than ${maxFutureDriftSeconds}s in the future`;
+  }
+  return null;
+}
+
+export { MEDIAN_TIME_SPAN, medianTimePast, checkBlockTime };
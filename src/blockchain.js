@@ -1,28 +1,51 @@
 "use strict";
 
-import { db } from './db.js';
+import path from 'path';
+import { storage } from './db.js';
+import { config as nodeConfig } from './config.js';
+import { assertSchemaIsCurrent } from './storage/migrator.js';
 import { Transaction } from './transaction.js';
 import { Block } from './block.js';
-import { broadcastBlock, broadcastChain, broadcastTransaction } from './p2p.js';
-import { acquireLock, releaseLock } from './lock.js';
+import { broadcastBlock, broadcastChainTip, broadcastTransaction } from './p2p.js';
+import { acquireLock, releaseLock, withLock } from './lock.js';
 import { MerkleTree, MerkleProofPath } from './merkleTree.js';
 import { createNewWallet, loadWallet } from './wallet.js';
+import { LRUCache } from './lruCache.js';
+import { isValidDifficulty, meetsDifficulty, nextDifficulty, blockWork, chainWork, compareChainTips } from './difficulty.js';
+import { medianTimePast, checkBlockTime } from './blockTime.js';
+import { networkTime } from './networkTime.js';
+import { checkCoinbase } from './coinbase.js';
+import { blockSubsidy, scheduledSupplyAt, supplyLimit, nextHalvingHeight } from './emission.js';
+import { selectTransactions } from './blockAssembler.js';
+import { checkBlockLimits } from './blockLimits.js';
+import { checkTransfer, applyTransfers, affordableTransactions } from './transfers.js';
+import { ChainValidator } from './chainValidator.js';
+import { loadGenesisSpec, buildGenesisBlock, genesisAllocationTotal } from './genesis.js';
+import { checkCheckpoint, checkReorgDepth } from './finality.js';
+import { estimateFee, TYPICAL_TRANSACTION_SIZE } from './feeEstimator.js';
+import { Miner } from './miner.js';
+import { MiningScheduler } from './miningScheduler.js';
 
 
 import Decimal from'decimal.js';
 
+const BLOCK_BATCH_SIZE = 500; // Blocks fetched per storage round trip when loading ranges
+const MAX_HISTORY_PAGE_SIZE = 500;
+
 class Blockchain {
-  constructor() {
+  constructor(config = nodeConfig) {
     if (Blockchain.instance) {
       return Blockchain.instance;
     }
-    this.chain = [];
-    this.difficulty = 0; // Set a realistic difficulty
+    this.config = config;
+    this.headers = []; // Header of every main-chain block, indexed by height
+    this.heightsByHash = new Map();
+    this.blockCache = new LRUCache(config.cache.blockCacheSize); // Recently used block bodies by height
     this.pendingTransactions = [];
-    this.miningReward = 100;
-    this.minerAddress = "59a8277a36bffda17f9a997e5f7c23";
-    this.genesisAddress = "6c7f05cca415fd2073de8ea8853834";
-    this.miningIntervalInSeconds = 30;
+    this.genesisSpec = null; // Loaded from chain.genesisFile by init()
+    this.genesisSupply = null; // Coins allocated by the genesis specification, a Decimal
+    this.miner = new Miner({ threads: config.mining.threads });
+    this.miningScheduler = new MiningScheduler(this, config.mining);
     this.transactionPool = new Set();
 
     this.connectedPeers = [];
@@ -35,40 +58,39 @@ class Blockchain {
   }
 
   async init() {
+    await assertSchemaIsCurrent(storage);
     await this.initializeGenesisBlock();
     await this.loadChainFromDatabase();
-    this.startTimeBasedMining(this.miningIntervalInSeconds);
-
-    setInterval(async () => {
-      const pendingTxCount = await this.countPendingTransactions();
-      if (pendingTxCount > 0) {
-        await this.minePendingTransactions(this.getMinerAddress());
-      }
-    }, 10000); // Check every 10 seconds (adjustable)
+    this.miningScheduler.start();
   }
 
 
+  /**
+   * Makes sure the database holds the genesis block derived from the genesis
+   * specification, storing it on first start. A node whose stored genesis
+   * block differs belongs to another chain and refuses to start.
+   */
   async initializeGenesisBlock() {
     console.log("Checking for existing genesis block...");
-    const query = "SELECT * FROM blocks WHERE `index` = 0";
-    
     try {
-        const [rows] = await db.query(query);
-        
-        if (rows.length > 0) {
-            const genesisBlock = await Block.load(rows[0].hash);
-            if (this.chain.length === 0) {
-                this.chain.push(genesisBlock); // Only add to memory if chain is empty
+        const expectedGenesis = this.loadGenesisBlock();
+        const genesisRow = await storage.getBlockByIndex(0);
+
+        if (genesisRow) {
+            if (genesisRow.hash !== expectedGenesis.hash) {
+                throw new Error(
+                  `Stored genesis block ${genesisRow.hash} does not match ${this.config.chain.genesisFile} (${expectedGenesis.hash}). ` +
+                  "Use the genesis specification this database was created with, or start from an empty database."
+                );
             }
-        } else {
-            const genesisBlock = await this.fetchGenesisBlockFromPeers();
-  
-            if (genesisBlock) {
-                this.chain.push(genesisBlock);
-                await genesisBlock.save();
-            } else {
-                await this.createGenesisBlockWithReward(this.genesisAddress, 1000000);
+            const genesisBlock = await Block.load(genesisRow.hash);
+            if (this.getChainLength() === 0) {
+                this.appendBlock(genesisBlock); // Only add to memory if chain is empty
             }
+        } else {
+            await expectedGenesis.save();
+            this.appendBlock(expectedGenesis);
+            console.log(`Created genesis block ${expectedGenesis.hash} for chain ${this.genesisSpec.chainId}.`);
         }
     } catch (err) {
         console.error("Error initializing genesis block:", err);
@@ -76,121 +98,247 @@ class Blockchain {
     }
   }
 
+  // Builds the genesis block from `chain.genesisFile`, whose allocations may not exceed `chain.maxSupply`
+  loadGenesisBlock() {
+    const spec = loadGenesisSpec(path.resolve(this.config.chain.genesisFile));
+    const allocated = genesisAllocationTotal(spec);
+    const { maxSupply } = this.config.chain;
+    if (maxSupply > 0 && allocated.greaterThan(maxSupply)) {
+      throw new Error(`${this.config.chain.genesisFile} allocates ${allocated.toFixed(8)} coins, more than chain.maxSupply (${maxSupply}).`);
+    }
+    this.genesisSpec = spec;
+    this.genesisSupply = allocated;
+    return buildGenesisBlock(spec);
+  }
 
+  // Consensus parameters: `config.chain` plus the supply allocated by the genesis block
+  getChainParams() {
+    return { ...this.config.chain, genesisSupply: this.genesisSupply };
+  }
 
+  /**
+   * Network this node belongs to: the chain ID its genesis block was built
+   * for. Every later block and every transaction must carry the same ID.
+   * @returns {string|null} - Null for chains whose genesis has no chain ID
+   */
+  getChainId() {
+    const genesis = this.headers[0];
+    return genesis ? genesis.chainId : null;
+  }
 
-  async fetchGenesisBlockFromPeers() {
-    if (!this.connectedPeers || this.connectedPeers.length === 0) {
-        return null;
-    }
+  // Number of blocks in the main chain
+  getChainLength() {
+    return this.headers.length;
+  }
 
-    // Create a promise that waits for the genesis block to be received
-    return new Promise((resolve, reject) => {
-        let genesisBlockReceived = false;
+  // Height of the chain tip, -1 while there is no genesis block
+  getHeight() {
+    return this.headers.length - 1;
+  }
 
-        // Set up a timeout in case the peers do not respond in time
-        const timeout = setTimeout(() => {
-            if (!genesisBlockReceived) {
-                reject(new Error("Timeout: No genesis block received from peers."));
-            }
-        }, 5000); // 5-second timeout, adjust as needed
-
-        // Send a request to all connected peers
-        this.connectedPeers.forEach((peer) => {
-            peer.send(JSON.stringify({
-                type: 'REQUEST_GENESIS_BLOCK'
-            }));
-
-            // Listen for incoming messages
-            peer.on('message', (data) => {
-                const message = JSON.parse(data);
-                if (message.type === 'GENESIS_BLOCK' && !genesisBlockReceived) {
-                    genesisBlockReceived = true;
-                    clearTimeout(timeout); // Clear the timeout when a response is received
-                    const genesisBlock = Block.fromJSON(message.data);
-                    resolve(genesisBlock); // Resolve with the genesis block
-                }
-            });
-        });
-    });
-}
+  getHeader(height) {
+    return this.headers[height];
+  }
 
+  getLatestHeader() {
+    return this.headers[this.headers.length - 1];
+  }
 
+  getHeaderByHash(hash) {
+    const height = this.heightsByHash.get(hash);
+    return height === undefined ? undefined : this.headers[height];
+  }
 
-  // Create the genesis block with a reward transaction
-  async createGenesisBlockWithReward(genesisAddress, initialReward) {
-    const rewardTx = new Transaction(null, genesisAddress, initialReward); // Reward transaction
-    rewardTx.hash = rewardTx.calculateHash();
-    rewardTx.signature = null; // Reward transactions don't need a signature
-  
-    console.log("Creating genesis block...");
-    const genesisBlock = new Block(
-      0,
-      null, 
-      Date.now(),
-      [rewardTx],
-      this.difficulty
-    );
-  
-    console.log("Mining genesis block...");
-    genesisBlock.mineBlock(this.difficulty);
-    console.log("Genesis block mined with hash:", genesisBlock.hash);
-  
-    this.chain.push(genesisBlock);
-  
-    try {
-      console.log("Saving genesis block to the database...");
-      await genesisBlock.save();
-      console.log(
-        console.log(`Genesis block created with initial balance of ${initialReward} to address ${genesisAddress}`)
-      );
-    } catch (err) {
-      console.error("Error saving genesis block:", err);
-      throw err;
+  // Whether the block is part of the main chain
+  hasBlock(hash) {
+    return this.heightsByHash.has(hash);
+  }
+
+  /**
+   * Main-chain block at the given height, served from the block cache or
+   * loaded from storage.
+   * @param {number} height
+   * @returns {Promise<Block|null>}
+   */
+  async getBlock(height) {
+    const header = this.headers[height];
+    if (!header) {
+      return null;
+    }
+
+    const cached = this.blockCache.get(height);
+    if (cached) {
+      return cached;
     }
+
+    const block = await Block.load(header.hash);
+    if (!block) {
+      throw new Error(`Block ${height} (${header.hash}) is missing from storage.`);
+    }
+    this.blockCache.set(height, block);
+    return block;
+  }
+
+  async getBlockByHash(hash) {
+    const height = this.heightsByHash.get(hash);
+    return height === undefined ? null : await this.getBlock(height);
   }
 
   // Get the latest block in the blockchain
-  getLatestBlock() {
-    return this.chain[this.chain.length - 1];
+  async getLatestBlock() {
+    return await this.getBlock(this.getHeight());
   }
 
-  // Start the time-based mining process
-  startTimeBasedMining(intervalInSeconds) {
-    setInterval(async () => {
-      if (this.pendingTransactions.length > 0) {
-        await this.minePendingTransactions(this.getMinerAddress());
+  /**
+   * Blocks from `fromHeight` to `toHeight`, both inclusive. Blocks missing
+   * from the cache are fetched with one batched range load, which bypasses
+   * the cache so that long scans do not evict recently used blocks.
+   * @returns {Promise<Block[]>}
+   */
+  async getBlocks(fromHeight = 0, toHeight = this.getHeight()) {
+    const first = Math.max(fromHeight, 0);
+    const last = Math.min(toHeight, this.getHeight());
+    if (first > last) {
+      return [];
+    }
+
+    const missing = [];
+    for (let height = first; height <= last; height++) {
+      if (!this.blockCache.has(height)) missing.push(height);
+    }
+
+    const loaded = new Map();
+    if (missing.length > 0) {
+      const blocks = await Block.loadRange(missing[0], missing[missing.length - 1]);
+      blocks.forEach(block => loaded.set(block.index, block));
+    }
+
+    const blocks = [];
+    for (let height = first; height <= last; height++) {
+      const block = loaded.get(height) || this.blockCache.get(height);
+      if (!block || block.hash !== this.headers[height].hash) {
+        throw new Error(`Block ${height} (${this.headers[height].hash}) is missing from storage.`);
       }
-    }, intervalInSeconds * 1000);
+      blocks.push(block);
+    }
+    return blocks;
+  }
+
+  // Walk the main chain in batches without holding all block bodies in memory
+  async *iterateBlocks(fromHeight = 0, toHeight = this.getHeight()) {
+    const last = Math.min(toHeight, this.getHeight());
+    for (let height = Math.max(fromHeight, 0); height <= last; height += BLOCK_BATCH_SIZE) {
+      yield* await this.getBlocks(height, Math.min(height + BLOCK_BATCH_SIZE - 1, last));
+    }
+  }
+
+  // Like `iterateBlocks`, with blocks in `Block.toJSON()` form
+  async *iterateBlockData(fromHeight = 0, toHeight = this.getHeight()) {
+    for await (const block of this.iterateBlocks(fromHeight, toHeight)) {
+      yield block.toJSON();
+    }
+  }
+
+  /**
+   * Finds a confirmed transaction through the transaction index instead of
+   * scanning blocks.
+   * @param {string} hash - Transaction hash
+   * @returns {Promise<{transaction: Transaction, block: Block}|null>}
+   */
+  async findTransaction(hash) {
+    const row = await storage.getTransactionByHash(hash);
+    if (!row || !this.hasBlock(row.block_hash)) {
+      return null;
+    }
+    const block = await this.getBlockByHash(row.block_hash);
+    const transaction = block.transactions.find(tx => tx.hash === hash);
+    return transaction ? { transaction, block } : null;
   }
 
-  // Mine pending transactions and add a new block to the blockchain
+  // Make `block` the new tip of the in-memory chain
+  appendBlock(block) {
+    if (block.index !== this.headers.length) {
+      throw new Error(`Cannot append block ${block.index} at height ${this.headers.length}.`);
+    }
+    this.pushHeader(block.getHeader());
+    this.blockCache.set(block.index, block);
+  }
+
+  // Record a header as the new tip, together with the chain's accumulated work up to it
+  pushHeader(header) {
+    const previous = this.getLatestHeader();
+    header.chainWork = (previous ? previous.chainWork : 0n) + blockWork(header.difficulty);
+    this.headers.push(header);
+    this.heightsByHash.set(header.hash, header.index);
+  }
+
+  // Tip of the main chain in the form used for fork choice
+  getChainTip() {
+    const tip = this.getLatestHeader();
+    return tip ? { height: tip.index, hash: tip.hash, work: tip.chainWork } : { height: -1, hash: null, work: 0n };
+  }
+
+  // Drop every block above `height` from memory
+  truncateChain(height) {
+    while (this.headers.length - 1 > height) {
+      const header = this.headers.pop();
+      this.heightsByHash.delete(header.hash);
+      this.blockCache.delete(header.index);
+    }
+  }
+
+  clearChain() {
+    this.truncateChain(-1);
+    this.blockCache.clear();
+  }
+
+  // Difficulty a block at `height` must meet, computed from the headers below it
+  getDifficultyAt(height) {
+    return nextDifficulty(height, index => this.headers[index], this.config.chain);
+  }
+
+  // Difficulty the next block must meet
+  getNextDifficulty() {
+    return this.getDifficultyAt(this.getChainLength());
+  }
+
+  /**
+   * Mine pending transactions and add a new block to the blockchain. Usually
+   * called by `miningScheduler`, which decides when to mine.
+   * @param {string} miningRewardAddress - Receives the block subsidy and fees
+   * @returns {Promise<Block|null>} - The mined block, null if none was mined
+   */
   async minePendingTransactions(miningRewardAddress) {
     // Attempt to acquire a lock before starting mining to prevent concurrent mining
     const lockAcquired = await acquireLock("miningLock");
     if (!lockAcquired) {
       
-      return;
+      return null;
     }
 
     try {
       if (this.pendingTransactions.length === 0) {
-        return;
+        return null;
       }
 
       console.log("Starting to mine a new block...");
 
+      if (!miningRewardAddress) {
+        throw new Error("A mining reward address is required: every block must contain a coinbase transaction.");
+      }
+
       // **Step 1: Filter Out Transactions Already in the Chain**
-      const filteredTransactions = this.pendingTransactions.filter(tx => 
-        !this.chain.some(block => 
-          block.transactions.some(existingTx => existingTx.hash === tx.hash)
-        )
-      );
+      const filteredTransactions = [];
+      for (const tx of this.pendingTransactions) {
+        if (tx.fromAddress !== null && !await storage.getTransactionByHash(tx.hash)) {
+          filteredTransactions.push(tx);
+        }
+      }
 
       if (filteredTransactions.length === 0) {
         this.pendingTransactions = [];
         await this.clearPendingTransactions();
-        return;
+        return null;
       }
 
       // **Step 2: Ensure Uniqueness of Transactions by Their Hash**
@@ -200,56 +348,95 @@ class Blockchain {
           uniqueTransactionsMap.set(tx.hash, tx);
         }
       });
-      const uniqueTransactions = Array.from(uniqueTransactionsMap.values());
+      const uniqueTransactions = affordableTransactions(
+        Array.from(uniqueTransactionsMap.values()),
+        await this.senderBalances(uniqueTransactionsMap.values(), this.getHeight())
+      ); // Senders must be able to pay for what they send, at the new block's place in the chain
 
       if (uniqueTransactions.length === 0) {
         this.pendingTransactions = [];
         await this.clearPendingTransactions();
-        return;
+        return null;
       }
 
-      // **Step 3: Prepare Transactions for the New Block**
-      const blockTransactions = [...uniqueTransactions];
-
-      // **Step 4: Add Mining Reward Transaction**
-      if (miningRewardAddress) {
-        const rewardTx = new Transaction(
-          null, // No sender for mining rewards
-          miningRewardAddress,
-          this.miningReward
-        );
-        rewardTx.hash = rewardTx.calculateHash();
-        rewardTx.signature = null; // Reward transactions don't need a signature
-        blockTransactions.push(rewardTx);
+      // **Step 3: Pick the Best-Paying Transactions That Fit in the Block**
+      // Room is kept for a coinbase collecting every candidate's fee, the largest it can get;
+      // whatever does not fit stays pending for the next blocks
+      const height = this.getChainLength();
+      const coinbaseTimestamp = Date.now(); // Shared with the coinbase mined, so the room kept is its exact size
+      const subsidy = blockSubsidy(height, this.getChainParams());
+      const allFees = uniqueTransactions.reduce((total, tx) => total.plus(tx.fee || 0), new Decimal(0));
+      const largestCoinbase = new Transaction(null, miningRewardAddress, subsidy.plus(allFees).toFixed(8), coinbaseTimestamp, null, "", String(height));
+      largestCoinbase.chainId = this.getChainId();
+      const blockTransactions = selectTransactions(uniqueTransactions, {
+        maxBytes: Math.min(this.config.mining.maxBlockBytes, this.config.chain.maxBlockBytes - largestCoinbase.getSize()),
+        maxCount: this.config.chain.maxBlockTransactions - 1
+      });
+      if (blockTransactions.length === 0) {
+        return null;
       }
 
+      // **Step 4: Add Mining Reward Transaction (the coinbase always comes last)**
+      const fees = blockTransactions.reduce((total, tx) => total.plus(tx.fee || 0), new Decimal(0));
+      const rewardTx = new Transaction(
+        null, // No sender for mining rewards
+        miningRewardAddress,
+        subsidy.plus(fees).toFixed(8),
+        coinbaseTimestamp,
+        null,
+        "",
+        String(height) // The block height, which keeps the coinbase hash unique
+      );
+      rewardTx.chainId = this.getChainId();
+      rewardTx.hash = rewardTx.calculateHash();
+      rewardTx.signature = null; // Reward transactions don't need a signature
+      blockTransactions.push(rewardTx);
+
       // **Step 5: Create a New Block with the Collected Transactions**
+      const previousBlock = await this.getLatestBlock();
+      const difficulty = this.getNextDifficulty();
       const newBlock = new Block(
-        this.chain.length,
-        this.getLatestBlock().hash,
-        Date.now(),
+        height,
+        previousBlock.hash,
+        Math.max(networkTime.now(), medianTimePast(height, index => this.headers[index]) + 1),
         blockTransactions,
-        this.difficulty
+        difficulty,
+        this.getChainId()
       );
 
       // **Step 6: Validate Origin Transaction Hash**
-      const previousBlock = this.getLatestBlock();
       const expectedOriginTransactionHash = previousBlock.calculateLastOriginTransactionHash();
 
       if (previousBlock.originTransactionHash !== expectedOriginTransactionHash) {
         throw new Error('Previous block has an invalid origin transaction hash');
       }
 
-      // **Step 7: Mine the New Block**
-      newBlock.mineBlock(this.difficulty);
+      // **Step 7: Mine the New Block in worker threads; a block received meanwhile cancels it**
+      const mined = await this.miner.mine(newBlock, difficulty, {
+        onProgress: ({ hashes, hashrate }) => console.log(`Mining block ${height}: ${hashes} hashes tried, ${Math.round(hashrate)} H/s.`)
+      });
+      if (!mined) {
+        console.log(`Mining of block ${height} was cancelled.`);
+        return null;
+      }
+
+      // **Step 8: Save the New Block and Add It to the Chain, unless a received block took its place**
+      const appended = await withLock("chainLock", async () => {
+        if (this.getLatestHeader().hash !== previousBlock.hash) {
+          console.log(`Mined block ${height} is stale: the chain tip changed while mining.`);
+          return false;
+        }
+        await newBlock.save();
+        this.appendBlock(newBlock);
+        return true;
+      });
+      if (!appended) {
+        return null;
+      }
 
       console.log(`Mined block successfully with index: ${newBlock.index}`);
       console.log(`Number of transactions mined in block ${newBlock.index}: ${newBlock.transactions.length}`);
 
-      // **Step 8: Add the New Block to the Chain and Save It**
-      this.chain.push(newBlock);
-      await newBlock.save();
-
       // **Step 9: Clear Mined Transactions from Pending and the Transaction Pool**
       await this.clearMinedTransactions(newBlock.transactions);
       newBlock.transactions.forEach(tx => {
@@ -263,8 +450,10 @@ class Blockchain {
       this.pendingTransactions = this.pendingTransactions.filter(tx => 
         !newBlock.transactions.some(newTx => newTx.hash === tx.hash)
       );
+      return newBlock;
     } catch (error) {
       console.error("Error during mining process:", error);
+      return null;
     } finally {
       // Release the lock regardless of whether mining was successful or not
       await releaseLock("miningLock");
@@ -280,16 +469,101 @@ class Blockchain {
     await this.addPendingTransaction(tx);
   }
 
-  async addBlock(newBlock) {
-    const previousBlock = this.getLatestBlock();
+  /**
+   * Adds a block on top of the main chain if it is valid.
+   * @param {Block} newBlock
+   * @param {Object} [options]
+   * @param {boolean} [options.broadcast=true] - Announce the block to peers; block sync turns this off
+   * @returns {Promise<boolean>}
+   */
+  async addBlock(newBlock, { broadcast = true } = {}) {
+    // Checked and appended as one step, so no other block takes the tip in between
+    return withLock("chainLock", async () => {
+      if (!await this.isValidNextBlock(newBlock, this.getLatestHeader())) {
+        return false;
+      }
+
+      try {
+        await newBlock.save();
+        this.appendBlock(newBlock);
+
+        await this.clearMinedTransactions(newBlock.transactions);
+
+        this.pendingTransactions = this.pendingTransactions.filter(tx => 
+          !newBlock.transactions.some(newTx => newTx.hash === tx.hash)
+        );
+
+        if (broadcast) {
+          broadcastBlock(newBlock);
+        }
+        return true;
+      } catch (err) {
+        console.error("Error adding block:", err);
+        return false;
+      }
+    });
+  }
 
+  // Checks a block against the main-chain block it claims to extend
+  async isValidNextBlock(newBlock, previousBlock) {
     if (newBlock.previousHash !== previousBlock.hash) {
       console.log("Previous hash mismatch. Block rejected.");
       return false;
     }
 
-    if (!await newBlock.hasValidTransactions()) {
-      console.log("Block has invalid transactions. Block rejected.");
+    if (newBlock.index !== previousBlock.index + 1) {
+      console.log("Block index does not follow the previous block. Block rejected.");
+      return false;
+    }
+
+    const timeError = checkBlockTime(newBlock.timestamp, newBlock.index, index => this.headers[index], this.config.chain, networkTime.now());
+    if (timeError) {
+      console.log(`Block ${timeError}. Block rejected.`);
+      return false;
+    }
+
+    if ((newBlock.chainId || null) !== this.getChainId()) {
+      console.log(`Block was mined for chain ${newBlock.chainId || '(none)'}, not ${this.getChainId() || '(none)'}. Block rejected.`);
+      return false;
+    }
+
+    const limitError = checkBlockLimits(newBlock.transactions, this.config.chain);
+    if (limitError) {
+      console.log(`Block ${limitError}. Block rejected.`);
+      return false;
+    }
+
+    if (newBlock.merkleRoot !== newBlock.calculateMerkleRoot()) {
+      console.log("Block has an invalid Merkle root. Block rejected.");
+      return false;
+    }
+
+    try {
+      if (!await newBlock.hasValidTransactions(this.getChainId())) {
+        console.log("Block has invalid transactions. Block rejected.");
+        return false;
+      }
+    } catch (err) {
+      console.log(`Block has an invalid transaction: ${err.message} Block rejected.`);
+      return false;
+    }
+
+    const coinbaseError = checkCoinbase(newBlock.transactions, blockSubsidy(newBlock.index, this.getChainParams()).plus(newBlock.getTotalFees()), newBlock.index);
+    if (coinbaseError) {
+      console.log(`Block ${coinbaseError}. Block rejected.`);
+      return false;
+    }
+
+    for (const tx of newBlock.transactions) {
+      if (await this.isConfirmedBy(tx.hash, previousBlock.index)) {
+        console.log(`Block repeats transaction ${tx.hash}, already in the chain. Block rejected.`);
+        return false;
+      }
+    }
+
+    const transferError = applyTransfers(newBlock.transactions, await this.senderBalances(newBlock.transactions, previousBlock.index));
+    if (transferError) {
+      console.log(`Block ${transferError}. Block rejected.`);
       return false;
     }
 
@@ -298,35 +572,99 @@ class Blockchain {
       return false;
     }
 
-    if (newBlock.hash.substring(0, this.difficulty) !== Array(this.difficulty + 1).join("0")) {
+    const checkpointError = checkCheckpoint(newBlock.index, newBlock.hash, this.config.chain.checkpoints);
+    if (checkpointError) {
+      console.log(`Block ${checkpointError}. Block rejected.`);
+      return false;
+    }
+
+    if (newBlock.difficulty !== this.getDifficultyAt(newBlock.index)) {
+      console.log("Block has an unexpected difficulty. Block rejected.");
+      return false;
+    }
+
+    if (!meetsDifficulty(newBlock.hash, newBlock.difficulty)) {
       console.log("Block does not meet difficulty requirements. Block rejected.");
       return false;
     }
 
-    this.chain.push(newBlock);
-    try {
-      await newBlock.save();
+    return true;
+  }
 
-      await this.clearMinedTransactions(newBlock.transactions);
+  /**
+   * Handles a block that builds on a main-chain block other than the tip.
+   * If the branch ending in it wins the fork choice (more work, or equal work
+   * and a lower tip hash) the chain is reorganized onto it.
+   * @param {Block} block
+   * @returns {Promise<boolean>} - True if the block became the new tip
+   */
+  async acceptForkBlock(block) {
+    // Under the lock addBlock holds, so the tip cannot move while the fork is checked
+    return withLock("chainLock", async () => {
+      const parent = this.getHeaderByHash(block.previousHash);
+      if (!parent || parent.index === this.getHeight()) {
+        return false;
+      }
 
-      this.pendingTransactions = this.pendingTransactions.filter(tx => 
-        !newBlock.transactions.some(newTx => newTx.hash === tx.hash)
-      );
+      if (!isValidDifficulty(block.difficulty)) {
+        console.log(`Fork block has an invalid difficulty ${block.difficulty}. Block rejected.`);
+        return false;
+      }
 
-      broadcastBlock(newBlock);
+      const candidate = { hash: block.hash, work: parent.chainWork + blockWork(block.difficulty) };
+      if (compareChainTips(candidate, this.getChainTip()) <= 0) {
+        return false;
+      }
+
+      if (!this.isReorgAllowed(parent.index, block.hash)) {
+        return false;
+      }
+
+      if (!await this.isValidNextBlock(block, parent)) {
+        return false;
+      }
+
+      await this.reorganize(parent.index, [block]);
       return true;
-    } catch (err) {
-      console.error("Error adding block:", err);
-      return false;
+    });
+  }
+
+  /**
+   * Main-chain balances of the senders of `transactions` right after the block at `height`.
+   * @param {Iterable<Transaction>} transactions
+   * @param {number} height
+   * @returns {Promise<Map<string, Decimal>>} - sender -> balance
+   */
+  async senderBalances(transactions, height) {
+    const balances = new Map();
+    for (const tx of transactions) {
+      if (tx.fromAddress && !balances.has(tx.fromAddress)) {
+        balances.set(tx.fromAddress, new Decimal(await this.getBalanceAt(tx.fromAddress, height)));
+      }
     }
+    return balances;
+  }
+
+  // Whether a transaction is in a main-chain block at or below `height`
+  async isConfirmedBy(hash, height) {
+    const row = await storage.getTransactionByHash(hash);
+    const block = row && this.getHeaderByHash(row.block_hash);
+    return Boolean(block) && block.index <= height;
   }
 
-  getMinerAddress() {
-    return this.minerAddress;
+  // Abandons the block being mined, e.g. because a received block already extends the tip it builds on
+  cancelMining(reason) {
+    if (this.miner.cancel()) {
+      console.log(`Mining cancelled: ${reason}.`);
+    }
   }
 
   async addPendingTransaction(transaction) {
-    if (!transaction.isValid()) {
+    if (transaction.fromAddress === null) {
+      throw new Error("Coinbase transactions cannot be submitted to the mempool.");
+    }
+
+    if (!transaction.isValid(this.getChainId())) {
       throw new Error("Invalid transaction.");
     }
     
@@ -335,125 +673,405 @@ class Blockchain {
       return;
     }
 
+    if (await this.isConfirmedBy(transaction.hash, this.getHeight())) {
+      throw new Error("Transaction is already in the chain.");
+    }
+
+    // The sender must cover this transaction on top of its other pending ones
+    const pendingSpent = this.pendingTransactions
+      .filter(tx => tx.fromAddress === transaction.fromAddress)
+      .reduce((total, tx) => total.plus(tx.amount).plus(tx.fee || 0), new Decimal(0));
+    const available = new Decimal(await this.getBalanceOfAddress(transaction.fromAddress)).minus(pendingSpent);
+    const transferError = checkTransfer(transaction, available);
+    if (transferError) {
+      throw new Error(`Transaction ${transferError}.`);
+    }
+
     this.pendingTransactions.push(transaction);
     this.transactionPool.add(transaction.hash);
     await transaction.savePending();
     broadcastTransaction(transaction);
   }
 
-  calculateCumulativeDifficulty(chainData) {
-    return chainData.reduce((total, block) => total + block.difficulty, 0);
-  }
-
-  // Replace the current chain with a new chain
+  /**
+   * Replace the current chain with a received branch if it wins the fork
+   * choice (see `compareChainTips`), reorganizing from the fork point. The
+   * branch is validated after the local blocks up to the fork point, which
+   * are streamed from storage.
+   * @param {Object[]} newChainData - Consecutive blocks in `Block.toJSON()` form: a whole chain from genesis,
+   *   or a branch whose first block extends a main-chain block
+   * @returns {Promise<boolean>} - True if the chain was replaced
+   */
   async replaceChain(newChainData) {
-    if (newChainData.length <= this.chain.length) {
-      
-      return;
-    }
-  
-    const isValid = await Blockchain.isValidChain(newChainData);
-    if (!isValid) {
-      console.log("Received chain is invalid.");
-      return;
-    }
-  
-    const localCumulativeDifficulty = this.calculateCumulativeDifficulty(this.chain.map(block => block.toJSON()));
-    const receivedCumulativeDifficulty = this.calculateCumulativeDifficulty(newChainData);
-  
-    if (receivedCumulativeDifficulty > localCumulativeDifficulty) {
+    // Under the lock addBlock holds, so the tip cannot move while the branch is checked
+    return withLock("chainLock", async () => {
+      const candidate = this.describeBranch(newChainData);
+      if (!candidate || compareChainTips(candidate.tip, this.getChainTip()) <= 0) {
+        return false;
+      }
+      const { forkIndex, branch, tip } = candidate;
+
+      if (!this.isReorgAllowed(forkIndex, tip.hash)) {
+        return false;
+      }
+
+      const isValid = await Blockchain.isValidChain(concatBlocks(this.iterateBlockData(0, forkIndex), branch), this.config.chain);
+      if (!isValid) {
+        console.log("Received chain is invalid.");
+        return false;
+      }
+
       try {
-        this.chain = [];
-        for (const blockData of newChainData) {
-          const loadedBlock = await Block.load(blockData.hash);
-          if (loadedBlock) {
-            
-            this.chain.push(loadedBlock);
-          } else {
-            
-            const newBlock = Block.fromJSON(blockData);
-            await newBlock.save();
-            this.chain.push(newBlock);
-          }
-        }
-        
-        broadcastChain();
+        await this.reorganize(forkIndex, branch.map(blockData => Block.fromJSON(blockData)));
+        broadcastChainTip();
+        return true;
       } catch (err) {
         console.error("Error replacing chain:", err);
+        return false;
       }
-    } else {
+    });
+  }
+
+  /**
+   * Where received blocks leave the main chain and the tip they lead to, for
+   * the fork choice. Nothing is validated beyond what computing the work needs.
+   * @param {Object[]} newChainData - See `replaceChain`
+   * @returns {{forkIndex: number, branch: Object[], tip: {hash: string, work: bigint}}|null} - `branch` holds the
+   *   blocks after the fork point, possibly none; null if the blocks do not connect to the main chain or carry a
+   *   malformed difficulty
+   */
+  describeBranch(newChainData) {
+    if (newChainData.length === 0) {
+      return null;
+    }
 
+    const badBlock = newChainData.find(blockData => !isValidDifficulty(blockData.difficulty));
+    if (badBlock) {
+      console.log(`Received chain has an invalid difficulty ${badBlock.difficulty} at block ${badBlock.index}.`);
+      return null;
     }
+
+    const forkIndex = this.findForkPoint(newChainData);
+    if (forkIndex < 0) {
+      console.log("Received chain does not share our genesis block.");
+      return null;
+    }
+
+    const branch = newChainData.slice(forkIndex + 1 - newChainData[0].index);
+    const tip = branch.length > 0
+      ? { hash: branch[branch.length - 1].hash, work: this.headers[forkIndex].chainWork + chainWork(branch) }
+      : { hash: this.headers[forkIndex].hash, work: this.headers[forkIndex].chainWork };
+    return { forkIndex, branch, tip };
   }
 
-  async getBalanceOfAddress(address) {
-    const query = `
-      SELECT balance 
-      FROM address_balances 
-      WHERE address = ?
-    `;
+  // Refuses, and logs, switching to a branch that forks off deeper than chain.maxReorgDepth
+  isReorgAllowed(forkIndex, branchTipHash) {
+    const depthError = checkReorgDepth(this.getHeight(), forkIndex, this.config.chain.maxReorgDepth);
+    if (depthError) {
+      console.log(`Rejected branch ending in ${branchTipHash} (fork at block ${forkIndex}): it ${depthError}.`);
+      return false;
+    }
+    return true;
+  }
 
+  /**
+   * Index of the last block shared by the local chain and `chainData`.
+   * @param {Object[]} chainData - Consecutive blocks in `Block.toJSON()` form, see `replaceChain`
+   * @returns {number} - -1 if the blocks share nothing with the local chain
+   */
+  findForkPoint(chainData) {
+    const first = chainData[0];
+    if (!Number.isInteger(first.index) || first.index < 0) {
+      return -1;
+    }
 
-    try {
-      const [rows, fields] = await db.query(query, [address]);
-      if (rows.length === 0) {
-        return new Decimal(0).toFixed(8);
-      } else {
-        const balance = rows[0].balance || 0;
-        return new Decimal(balance).toFixed(8);
+    let forkIndex = first.index - 1;
+    if (forkIndex >= 0 && (!this.headers[forkIndex] || this.headers[forkIndex].hash !== first.previous_hash)) {
+      return -1;
+    }
+    for (const blockData of chainData) {
+      const header = this.headers[blockData.index];
+      if (!header || header.hash !== blockData.hash) {
+        break;
       }
-    } catch (err) {
-      throw err;
+      forkIndex = blockData.index;
     }
+    return forkIndex;
   }
 
-  // Static method to validate an entire chain
-  static async isValidChain(chainData) {
-    if (chainData.length === 0) return false;
-  
-    const firstBlock = chainData[0];
-    // Update the condition to accept 'null' for the genesis block's previous_hash
-    if (firstBlock.index !== 0 || firstBlock.previous_hash !== null) {
-      console.log("Invalid genesis block.");
-      return false;
+  /**
+   * Hashes that tell a peer which blocks we have: the last ten main-chain
+   * blocks, then exponentially sparser ones down to genesis.
+   * @returns {string[]} - Newest first
+   */
+  getBlockLocator() {
+    const hashes = [];
+    let step = 1;
+    for (let height = this.getHeight(); height > 0; height -= step) {
+      hashes.push(this.headers[height].hash);
+      if (hashes.length >= 10) {
+        step *= 2;
+      }
     }
-
-    if (firstBlock.index === 0) {
-      return true;
+    if (this.getChainLength() > 0) {
+      hashes.push(this.headers[0].hash);
     }
+    return hashes;
+  }
 
-    const tempBalances = {};
-  
-    // Continue with the rest of the validation
-    for (let i = 1; i < chainData.length; i++) {
-      const currentBlock = chainData[i];
-      const previousBlock = chainData[i - 1];
-  
-      if (currentBlock.previous_hash !== previousBlock.hash) {
-        console.log(`Block ${currentBlock.index} has invalid previous hash.`);
-        return false;
+  /**
+   * Height of the highest main-chain block named in a peer's locator.
+   * @param {string[]} locator - See `getBlockLocator`
+   * @returns {number} - -1 if none of the hashes is on the main chain
+   */
+  findLocatorFork(locator) {
+    for (const hash of locator) {
+      const header = this.getHeaderByHash(hash);
+      if (header) {
+        return header.index;
       }
-  
-      const tempBlock = Block.fromJSON(currentBlock);
-      if (currentBlock.hash !== tempBlock.hash()) {
-        console.log(`Block ${currentBlock.index} has invalid hash.`);
-        return false;
+    }
+    return -1;
+  }
+
+  /**
+   * Disconnects every local block after `forkIndex` and connects `newBlocks`
+   * in their place, all in one storage transaction. Transactions of the
+   * abandoned blocks that are not part of the new branch go back to the
+   * mempool if they are still valid.
+   * @param {number} forkIndex - Index of the last block both branches share
+   * @param {Block[]} newBlocks - Blocks of the new branch, starting at forkIndex + 1
+   */
+  async reorganize(forkIndex, newBlocks) {
+    const orphanedBlocks = await this.getBlocks(forkIndex + 1);
+
+    if (orphanedBlocks.length > 0) {
+      console.log(`Reorganizing chain: disconnecting ${orphanedBlocks.length} block(s) after block ${forkIndex}, connecting ${newBlocks.length}.`);
+    }
+
+    await storage.withTransaction(async (store) => {
+      for (const block of [...orphanedBlocks].reverse()) {
+        await block.disconnect(store);
       }
-  
-      if (currentBlock.hash.substring(0, currentBlock.difficulty) !== Array(currentBlock.difficulty + 1).join("0")) {
-        console.log(`Block ${currentBlock.index} does not meet difficulty requirements.`);
-        return false;
+      for (const block of newBlocks) {
+        await block.connect(store);
       }
-  
-      const isValidTransactions = await tempBlock.hasValidTransactions();
-      if (!isValidTransactions) {
-        console.log(`Block ${currentBlock.index} contains invalid transactions.`);
-        return false;
+    });
+
+    this.truncateChain(forkIndex);
+    newBlocks.forEach(block => this.appendBlock(block));
+
+    const confirmedTransactions = newBlocks.flatMap(block => block.transactions);
+    await this.clearMinedTransactions(confirmedTransactions);
+    const confirmedHashes = new Set(confirmedTransactions.map(tx => tx.hash));
+    this.pendingTransactions = this.pendingTransactions.filter(tx => !confirmedHashes.has(tx.hash));
+    confirmedHashes.forEach(hash => this.transactionPool.delete(hash));
+
+    await this.returnTransactionsToMempool(orphanedBlocks, confirmedHashes);
+  }
+
+  // Put the still-valid transactions of abandoned blocks back into pending
+  async returnTransactionsToMempool(orphanedBlocks, confirmedHashes) {
+    const spent = {}; // Amount already committed per sender by returned transactions
+
+    for (const block of orphanedBlocks) {
+      for (const orphanedTx of block.transactions) {
+        if (!orphanedTx.fromAddress || confirmedHashes.has(orphanedTx.hash) || this.transactionPool.has(orphanedTx.hash)) {
+          continue; // Mining rewards die with their block
+        }
+
+        const tx = Transaction.fromJSON({ ...orphanedTx.toJSON(), blockHash: null, index_in_block: null });
+        try {
+          if (!tx.isValid(this.getChainId())) continue;
+        } catch (err) {
+          continue;
+        }
+
+        const balance = new Decimal(await this.getBalanceOfAddress(tx.fromAddress));
+        const committed = (spent[tx.fromAddress] || new Decimal(0)).plus(tx.amount).plus(tx.fee || 0);
+        if (balance.lessThan(committed)) {
+          console.log(`Dropping orphaned transaction ${tx.hash}: insufficient balance after reorganization.`);
+          continue;
+        }
+        spent[tx.fromAddress] = committed;
+
+        this.pendingTransactions.push(tx);
+        this.transactionPool.add(tx.hash);
+        await tx.savePending();
       }
+    }
+  }
 
+  async getBalanceOfAddress(address) {
+    try {
+      const balance = await storage.getBalance(address);
+      return new Decimal(balance || 0).toFixed(8);
+    } catch (err) {
+      throw err;
     }
-  
-    return true;
+  }
+
+  /**
+   * Coin supply of the current chain next to what the emission schedule allows.
+   * Issued is every coin the chain has created (the sum of all balances).
+   * Circulating leaves out the genesis allocations that have not moved: for
+   * each allocation of the genesis block, what its address still holds of it.
+   * @returns {Promise<Object>} - { height, issued, circulating, scheduled, remaining, limit, nextSubsidy, nextHalvingHeight };
+   *   amounts are strings with 8 decimals, `remaining` and `limit` are null if the supply has no limit
+   */
+  async getSupplyInfo() {
+    const height = this.getHeight();
+    const params = this.getChainParams();
+    const balances = await storage.getAllBalances();
+    const issued = balances.reduce((total, row) => total.plus(row.balance), new Decimal(0));
+    let genesisHeld = new Decimal(0);
+    for (const allocation of (await this.getBlock(0)).transactions) {
+      genesisHeld = genesisHeld.plus(Decimal.min(new Decimal(await storage.getBalance(allocation.toAddress) || 0), allocation.amount));
+    }
+    const limit = supplyLimit(params);
+
+    return {
+      height,
+      issued: issued.toFixed(8),
+      circulating: issued.minus(genesisHeld).toFixed(8),
+      scheduled: scheduledSupplyAt(height, params).toFixed(8),
+      remaining: limit ? Decimal.max(limit.minus(issued), 0).toFixed(8) : null,
+      limit: limit ? limit.toFixed(8) : null,
+      nextSubsidy: blockSubsidy(height + 1, params).toFixed(8),
+      nextHalvingHeight: nextHalvingHeight(height, params)
+    };
+  }
+
+  /**
+   * Suggests the fee for a transaction that should be mined within
+   * `targetBlocks` blocks, based on the fee rates of the last
+   * `fees.sampleBlocks` blocks and the current mempool.
+   * @param {number} [targetBlocks=1] - Acceptable wait in blocks
+   * @param {number} [size] - Serialized size of the transaction in bytes, defaults to a typical transfer
+   * @returns {Promise<Object>} - { targetBlocks, feeRatePerKb, fee, mempoolBlocks, sampleSize }, see `estimateFee`
+   */
+  async estimateFee(targetBlocks = 1, size = TYPICAL_TRANSACTION_SIZE) {
+    const tip = this.getHeight();
+    const recentBlocks = await this.getBlocks(Math.max(tip - this.config.fees.sampleBlocks + 1, 1), tip);
+
+    return estimateFee({
+      recentBlocks,
+      pending: this.pendingTransactions,
+      targetBlocks,
+      maxBlockBytes: this.config.mining.maxBlockBytes,
+      size
+    });
+  }
+
+  /**
+   * Balance of an address right after the block at `height` was connected.
+   * @param {string} address
+   * @param {number} height - Main-chain height, 0 for genesis
+   * @returns {Promise<string>} - Balance with 8 decimals
+   */
+  async getBalanceAt(address, height) {
+    if (!Number.isInteger(height) || height < 0 || height > this.getHeight()) {
+      throw new Error(`Height ${height} is outside the chain (0..${this.getHeight()}).`);
+    }
+
+    const row = await storage.getBalanceDeltaAt(address, height);
+    return new Decimal(row ? row.balance : 0).toFixed(8);
+  }
+
+  /**
+   * Transactions sent or received by an address, read through the address
+   * indexes and paginated with an opaque cursor.
+   * @param {string} address
+   * @param {Object} [options]
+   * @param {string} [options.cursor] - `nextCursor` of the previous page
+   * @param {number} [options.limit=50] - Confirmed entries per page, at most 500
+   * @param {string} [options.direction='desc'] - 'desc' for newest first, 'asc' for oldest first
+   * @param {number} [options.fromHeight=0] - Lowest block height, inclusive
+   * @param {number} [options.toHeight] - Highest block height, inclusive; defaults to the tip
+   * @returns {Promise<Object>} - { entries, pending, nextCursor }; `pending` is only filled on the first page
+   */
+  async getAddressHistory(address, { cursor = null, limit = 50, direction = 'desc', fromHeight = 0, toHeight = this.getHeight() } = {}) {
+    if (!Number.isInteger(limit) || limit < 1 || limit > MAX_HISTORY_PAGE_SIZE) {
+      throw new Error(`limit must be an integer between 1 and ${MAX_HISTORY_PAGE_SIZE}.`);
+    }
+    if (direction !== 'asc' && direction !== 'desc') {
+      throw new Error("direction must be 'asc' or 'desc'.");
+    }
+    if (!Number.isInteger(fromHeight) || !Number.isInteger(toHeight)) {
+      throw new Error("fromHeight and toHeight must be integers.");
+    }
+
+    const tipHeight = this.getHeight();
+    const rows = await storage.getTransactionsByAddress(address, {
+      fromHeight,
+      toHeight,
+      after: cursor ? decodeHistoryCursor(cursor) : null,
+      descending: direction === 'desc',
+      limit
+    });
+
+    const entries = rows.map(row => ({
+      ...historyEntry(Transaction.fromRow(row)),
+      status: 'confirmed',
+      blockHeight: Number(row.block_index),
+      blockHash: row.block_hash,
+      indexInBlock: Number(row.index_in_block),
+      confirmations: tipHeight - Number(row.block_index) + 1
+    }));
+
+    const pending = cursor ? [] : (await storage.getPendingTransactionsByAddress(address)).map(row => ({
+      ...historyEntry(Transaction.fromRow(row)),
+      status: 'pending',
+      blockHeight: null,
+      blockHash: null,
+      indexInBlock: null,
+      confirmations: 0
+    }));
+
+    const last = entries[entries.length - 1];
+    return {
+      entries,
+      pending,
+      nextCursor: entries.length === limit ? `${last.blockHeight}:${last.indexInBlock}` : null
+    };
+  }
+
+  /**
+   * Every block that changed the address's balance, oldest first.
+   * @param {string} address
+   * @returns {Promise<Object[]>} - Entries of { height, blockHash, change, balance }
+   */
+  async getBalanceHistory(address) {
+    const rows = await storage.getBalanceDeltas(address);
+    return rows.map(row => ({
+      height: Number(row.block_index),
+      blockHash: row.block_hash,
+      change: new Decimal(row.delta).toFixed(8),
+      balance: new Decimal(row.balance).toFixed(8)
+    }));
+  }
+
+  /**
+   * Validates an entire chain given in `Block.toJSON()` form by replaying it
+   * from genesis (see `ChainValidator`).
+   * @param {Iterable<Object>|AsyncIterable<Object>} chainData - Blocks starting at genesis
+   * @param {Object} [chainParams] - Consensus parameters (`config.chain`)
+   * @returns {Promise<boolean>}
+   */
+  static async isValidChain(chainData, chainParams = nodeConfig.chain) {
+    const result = await Blockchain.validateChain(chainData, chainParams);
+    if (!result.valid) {
+      console.log(`Block ${result.error.height} ${result.error.reason}.`);
+    }
+    return result.valid;
+  }
+
+  /**
+   * Like `isValidChain`, but reports the first failing block and why.
+   * @returns {Promise<{valid: boolean, checkedBlocks: number, error: ({height: number, hash: string, reason: string}|null)}>}
+   */
+  static async validateChain(chainData, chainParams = nodeConfig.chain) {
+    return new ChainValidator(chainParams, networkTime.now()).validate(chainData);
   }
 
   /**
@@ -464,7 +1082,7 @@ class Blockchain {
    */
   async verifyTransactionInBlock(transactionHash, blockHash) {
     try {
-      const block = this.chain.find(b => b.hash === blockHash);
+      const block = this.getHeaderByHash(blockHash);
       if (!block) {
         console.log("Block not found in local blockchain.");
         return false;
@@ -486,23 +1104,25 @@ class Blockchain {
 
   // Load the blockchain from the database
   async loadChainFromDatabase() {
-    const query = "SELECT * FROM blocks ORDER BY `index` ASC";
     try {
       // Clear the existing chain to prevent duplication
-      this.chain = [];
-      
-      const [rows, fields] = await db.query(query);
-      for (const result of rows) {
-        const block = await Block.load(result.hash);
-        if (block) {
-          this.chain.push(block);
+      this.clearChain();
+
+      // Only headers are kept; block bodies are loaded on demand
+      const rows = await storage.getAllBlocks();
+      for (const row of rows) {
+        const header = Block.headerFromRow(row);
+        const previousHeader = this.getLatestHeader();
+        if (header.index !== this.headers.length || (previousHeader && header.previousHash !== previousHeader.hash)) {
+          throw new Error(`Blockchain is invalid after loading from database: block ${header.index} does not link to the previous block.`);
         }
+        this.pushHeader(header);
       }
-  
-      if (!await this.isChainValid()) {
+
+      if (this.getChainLength() > 0 && !await this.getLatestBlock()) {
         throw new Error("Blockchain is invalid after loading from database.");
       } else {
-        console.log("Blockchain loaded and validated successfully.");
+        console.log(`Blockchain loaded successfully: ${this.getChainLength()} block(s).`);
       }
     } catch (err) {
       console.error("Error loading blockchain from database:", err);
@@ -511,10 +1131,8 @@ class Blockchain {
   }
 
   async countPendingTransactions() {
-    const query = "SELECT COUNT(*) AS count FROM pending_transactions";
     try {
-      const [rows, fields] = await db.query(query);
-      return rows[0].count;
+      return await storage.countPendingTransactions();
     } catch (err) {
       throw err;
     }
@@ -522,9 +1140,8 @@ class Blockchain {
 
   // Clear pending transactions from the database
   async clearPendingTransactions() {
-    const query = "DELETE FROM pending_transactions";
     try {
-      await db.query(query);
+      await storage.clearPendingTransactions();
   
     } catch (err) {
       console.error("Error clearing pending transactions:", err);
@@ -536,9 +1153,8 @@ class Blockchain {
     const transactionHashes = minedTransactions.map(tx => tx.hash);
     if (transactionHashes.length === 0) return;
 
-    const query = `DELETE FROM pending_transactions WHERE hash IN (${transactionHashes.map(() => '?').join(', ')})`;
     try {
-        await db.query(query, transactionHashes);
+        await storage.deletePendingTransactions(transactionHashes);
        
     } catch (err) {
         console.error("Error clearing mined transactions from the database:", err);
@@ -556,7 +1172,7 @@ class Blockchain {
     for (const tx of transactions) {
       if (tx.fromAddress) {
         if (!calculatedBalances[tx.fromAddress]) calculatedBalances[tx.fromAddress] = new Decimal(0);
-        calculatedBalances[tx.fromAddress] = calculatedBalances[tx.fromAddress].minus(tx.amount);
+        calculatedBalances[tx.fromAddress] = calculatedBalances[tx.fromAddress].minus(tx.amount).minus(tx.fee || 0);
       }
 
       if (tx.toAddress) {
@@ -595,6 +1211,7 @@ class Blockchain {
             From: ${tx.fromAddress}
             To: ${tx.toAddress}
             Amount: ${tx.amount}
+            Fee: ${tx.fee}
             Timestamp: ${new Date(tx.timestamp).toLocaleString()}
             Hash: ${tx.hash}
             Origin Transaction Hash: ${tx.originTransactionHash}`
@@ -608,20 +1225,111 @@ class Blockchain {
     return true;
   }
 
+  /**
+   * Rebuilds every derived table (address balances, Merkle nodes and proof
+   * paths) by replaying the stored blocks from genesis.
+   * @param {Object} [options]
+   * @param {boolean} [options.dryRun=false] - Only report what would change
+   * @param {Function} [options.onProgress] - Called with { phase, height, total } after each block
+   * @returns {Promise<Object>} - Report with block/transaction counts, balance differences and blocks with missing Merkle data
+   */
+  async reindex({ dryRun = false, onProgress = () => {} } = {}) {
+    const total = (await storage.getAllBlocks()).length;
+    const expectedBalances = new Map();
+    const missingMerkleData = [];
+    let transactionCount = 0;
+    let blockCount = 0;
+    let previousBlock = null;
+
+    // Replay the chain in memory first so a corrupt block aborts before anything is touched
+    for await (const block of this.loadStoredBlocks(total)) {
+      if (block.index !== blockCount || (previousBlock && block.previousHash !== previousBlock.hash)) {
+        throw new Error(`Cannot reindex: block ${block.index} (${block.hash}) does not link to the previous block.`);
+      }
+      if (block.calculateMerkleRoot() !== block.merkleRoot) {
+        throw new Error(`Cannot reindex: block ${block.index} has an invalid Merkle root.`);
+      }
+
+      for (const tx of block.transactions) {
+        if (tx.fromAddress) {
+          expectedBalances.set(tx.fromAddress, (expectedBalances.get(tx.fromAddress) || new Decimal(0)).minus(tx.amount).minus(tx.fee || 0));
+        }
+        if (tx.toAddress) {
+          expectedBalances.set(tx.toAddress, (expectedBalances.get(tx.toAddress) || new Decimal(0)).plus(tx.amount));
+        }
+        if (dryRun) {
+          const proof = await storage.getMerkleProof(tx.hash);
+          if (!proof || proof.block_hash !== block.hash) {
+            missingMerkleData.push(block.hash);
+          }
+        }
+      }
+
+      transactionCount += block.transactions.length;
+      blockCount++;
+      previousBlock = block;
+      onProgress({ phase: 'scan', height: block.index, total });
+    }
+
+    if (blockCount !== total) {
+      throw new Error(`Cannot reindex: expected ${total} blocks but found ${blockCount} consecutive blocks from genesis.`);
+    }
+
+    const balanceDifferences = await this.diffBalances(expectedBalances);
+    const report = {
+      dryRun,
+      blocks: blockCount,
+      transactions: transactionCount,
+      balanceDifferences,
+      blocksWithMissingMerkleData: [...new Set(missingMerkleData)]
+    };
+
+    if (dryRun) {
+      return report;
+    }
+
+    await storage.withTransaction(async (store) => {
+      await store.clearDerivedData();
+      for await (const block of this.loadStoredBlocks(total, store)) {
+        await block.updateBalances(store);
+        await block.saveMerkleData(store);
+        onProgress({ phase: 'rebuild', height: block.index, total });
+      }
+    });
+    await storage.rebuildIndexes();
+
+    return report;
+  }
+
+  // Stored blocks from genesis, loaded in batches regardless of the in-memory headers
+  async *loadStoredBlocks(count, store = storage) {
+    for (let height = 0; height < count; height += BLOCK_BATCH_SIZE) {
+      yield* await Block.loadRange(height, Math.min(height + BLOCK_BATCH_SIZE, count) - 1, store);
+    }
+  }
+
+  // Compare replayed balances with address_balances; missing rows count as zero
+  async diffBalances(expectedBalances) {
+    const storedBalances = new Map(
+      (await storage.getAllBalances()).map(row => [row.address, new Decimal(row.balance)])
+    );
+    const addresses = new Set([...expectedBalances.keys(), ...storedBalances.keys()]);
+    const differences = [];
+
+    for (const address of addresses) {
+      const expected = expectedBalances.get(address) || new Decimal(0);
+      const stored = storedBalances.get(address) || new Decimal(0);
+      if (!expected.equals(stored)) {
+        differences.push({ address, stored: stored.toFixed(8), expected: expected.toFixed(8) });
+      }
+    }
+    return differences;
+  }
+
   async getAllTransactions() {
-    const query = "SELECT * FROM transactions";
     try {
-      const [rows, fields] = await db.query(query);
-      return rows.map(result => new Transaction(
-        result.from_address,
-        result.to_address,
-        result.amount,
-        result.timestamp,
-        result.signature,
-        result.block_hash,
-        result.origin_transaction_hash,
-        result.public_key
-      ));
+      const rows = await storage.getAllTransactions();
+      return rows.map(row => Transaction.fromRow(row));
     } catch (err) {
       console.error("Error fetching all transactions:", err);
       throw err;
@@ -629,9 +1337,44 @@ class Blockchain {
   }
 
   async isChainValid() {
-    const chainData = this.chain.map(block => block.toJSON());
-    return await Blockchain.isValidChain(chainData);
+    return (await this.validateLocalChain()).valid;
+  }
+
+  // Replays the local main chain from genesis, streaming it from storage in batches; see `Blockchain.validateChain`
+  async validateLocalChain() {
+    const result = await Blockchain.validateChain(this.iterateBlockData(), this.config.chain);
+    if (!result.valid) {
+      console.log(`Block ${result.error.height} ${result.error.reason}.`);
+    }
+    return result;
+  }
+}
+
+// Yields the blocks of each source in turn; sources may be arrays or async generators
+async function* concatBlocks(...sources) {
+  for (const source of sources) {
+    yield* source;
+  }
+}
+
+function historyEntry(tx) {
+  return {
+    hash: tx.hash,
+    fromAddress: tx.fromAddress,
+    toAddress: tx.toAddress,
+    amount: tx.amount,
+    fee: new Decimal(tx.fee || 0).toFixed(8),
+    timestamp: tx.timestamp
+  };
+}
+
+// History cursors are "<block height>:<index in block>" of the last entry returned
+function decodeHistoryCursor(cursor) {
+  const match = /^(\d+):(\d+)$/.exec(cursor);
+  if (!match) {
+    throw new Error(`Invalid history cursor: ${cursor}`);
   }
+  return { blockIndex: Number(match[1]), indexInBlock: Number(match[2]) };
 }
 
 const blockchainInstance = new Blockchain();
@@ -0,0 +1,161 @@
+"use strict";
+
+import Decimal from 'decimal.js';
+import { Block } from './block.js';
+import { meetsDifficulty, nextDifficulty } from './difficulty.js';
+import { checkBlockTime } from './blockTime.js';
+import { checkCoinbase } from './coinbase.js';
+import { blockSubsidy } from './emission.js';
+import { checkBlockLimits } from './blockLimits.js';
+import { checkCheckpoint } from './finality.js';
+import { applyTransfers } from './transfers.js';
+
+/**
+ * Validates a whole chain by replaying it from genesis: every block is
+ * checked for linkage, hash, checkpoints, chain ID, Merkle root, timestamp,
+ * difficulty and proof of work, size limits, transaction hashes and
+ * signatures (made with the sender's key) and coinbase rules, and no
+ * transaction may appear twice, while balances are rebuilt transaction by
+ * transaction so that no account can spend more than it holds at that point
+ * of the chain. The chain ID every block and transaction must carry is the
+ * one of the chain's genesis block. Blocks are read one at a time, so a
+ * chain can be streamed from storage in batches; only headers, balances and
+ * transaction hashes are kept.
+ */
+class ChainValidator {
+  /**
+   * @param {Object} chainParams - Consensus parameters (`config.chain`); the genesis supply is read from the genesis block
+   * @param {number} now - Network-adjusted current time in ms, for the future drift rule
+   */
+  constructor(chainParams, now) {
+    this.chainParams = chainParams;
+    this.now = now;
+    this.balances = new Map(); // address -> Decimal, as of the last replayed transaction
+    this.chainId = null; // Chain ID of the genesis block being validated
+    this.genesisSupply = null; // Coins allocated by that genesis block, a Decimal
+    this.seenHashes = new Set(); // Hashes of the transactions replayed so far
+    this.headers = []; // { index, hash, timestamp, difficulty } of the blocks that passed
+  }
+
+  /**
+   * @param {Iterable<Object>|AsyncIterable<Object>} blocks - Blocks in `Block.toJSON()` form, in order from genesis
+   * @returns {Promise<{valid: boolean, checkedBlocks: number, error: ({height: number, hash: string, reason: string}|null)}>}
+   *   `checkedBlocks` counts the blocks that passed; `error` describes the first one that did not
+   */
+  async validate(blocks) {
+    this.balances = new Map();
+    this.seenHashes = new Set();
+    this.headers = [];
+
+    for await (const data of blocks) {
+      const height = this.headers.length;
+      if (height === 0) {
+        this.chainId = data.chain_id || null;
+      }
+      const reason = await this.checkBlock(data, height);
+      if (reason) {
+        return { valid: false, checkedBlocks: height, error: { height, hash: data.hash, reason } };
+      }
+      this.headers.push({ index: data.index, hash: data.hash, timestamp: data.timestamp, difficulty: data.difficulty });
+    }
+
+    if (this.headers.length === 0) {
+      return { valid: false, checkedBlocks: 0, error: { height: 0, hash: null, reason: 'is missing: the chain is empty' } };
+    }
+    return { valid: true, checkedBlocks: this.headers.length, error: null };
+  }
+
+  // Checks the block at `height` and replays its transactions; returns why it is invalid, or null
+  async checkBlock(data, height) {
+    const params = this.chainParams;
+
+    if (data.index !== height) {
+      return `has index ${data.index}`;
+    }
+    if (height === 0 ? data.previous_hash !== null : data.previous_hash !== this.headers[height - 1].hash) {
+      return 'does not link to the previous block';
+    }
+    if (height > 0 && data.transactions.length > params.maxBlockTransactions) {
+      return 'has too many transactions'; // Checked first: the Merkle tree cannot be built for them
+    }
+
+    let block;
+    try {
+      block = Block.fromJSON(data);
+    } catch (err) {
+      return `cannot be decoded: ${err.message}`;
+    }
+
+    if (block.calculateMerkleRoot() !== data.merkle_root) {
+      return 'has an invalid Merkle root';
+    }
+    if (block.calculateHash() !== data.hash) {
+      return 'has an invalid hash';
+    }
+    const checkpointError = checkCheckpoint(height, data.hash, params.checkpoints);
+    if (checkpointError) {
+      return checkpointError;
+    }
+    if (height > 0 && (block.chainId || null) !== this.chainId) {
+      return `was mined for chain ${block.chainId || '(none)'}, not ${this.chainId || '(none)'}`;
+    }
+
+    const chainId = height === 0 ? undefined : this.chainId; // Genesis allocations carry no chain ID, their block does
+    for (const tx of block.transactions) {
+      if (this.seenHashes.has(tx.hash)) {
+        return `repeats transaction ${tx.hash}`;
+      }
+      this.seenHashes.add(tx.hash);
+      try {
+        tx.verifyTransaction();
+        if (!tx.isValid(chainId)) {
+          return `has an invalid signature on transaction ${tx.hash}`;
+        }
+      } catch (err) {
+        return `has an invalid transaction ${tx.hash}: ${err.message}`;
+      }
+    }
+
+    const ruleError = height === 0 ? this.checkGenesisRules(block) : this.checkBlockRules(block);
+    if (ruleError) {
+      return ruleError;
+    }
+    if (height === 0) {
+      this.genesisSupply = block.transactions.reduce((total, tx) => total.plus(tx.amount), new Decimal(0));
+    }
+
+    return this.replayTransactions(block);
+  }
+
+  checkGenesisRules(block) {
+    if (block.transactions.length === 0 || block.transactions.some(tx => tx.fromAddress !== null)) {
+      return 'must contain only genesis allocations, and at least one';
+    }
+    return null;
+  }
+
+  checkBlockRules(block) {
+    const params = { ...this.chainParams, genesisSupply: this.genesisSupply };
+    const headerAt = height => this.headers[height];
+
+    const timeError = checkBlockTime(block.timestamp, block.index, headerAt, params, this.now);
+    if (timeError) {
+      return timeError;
+    }
+    if (block.difficulty !== nextDifficulty(block.index, headerAt, params)) {
+      return 'has an unexpected difficulty';
+    }
+    if (!meetsDifficulty(block.hash, block.difficulty)) {
+      return 'does not meet its difficulty';
+    }
+    return checkBlockLimits(block.transactions, params) ||
+      checkCoinbase(block.transactions, blockSubsidy(block.index, params).plus(block.getTotalFees()), block.index);
+  }
+
+  // Applies the block's transactions in order, failing as soon as a sender would go negative
+  replayTransactions(block) {
+    return applyTransfers(block.transactions, this.balances);
+  }
+}
+
+export { ChainValidator };
@@ -0,0 +1,49 @@
+"use strict";
+
+import Decimal from 'decimal.js';
+
+/**
+ * Checks the coinbase rules of a non-genesis block: exactly one transaction
+ * without a sender, placed last, carrying the block height as its origin
+ * transaction hash, paying no fee and an amount of at most `maxReward`
+ * (subsidy plus the block's fees). The height keeps every coinbase hash
+ * unique, even for blocks mined by the same address in the same millisecond.
+ * The amount may be zero once the subsidy has run out and the block collects
+ * no fees.
+ * @param {Transaction[]} transactions - Block transactions in order
+ * @param {Decimal|number|string} maxReward
+ * @param {number} height - Index of the block
+ * @returns {string|null} - Why the block breaks the rules, or null if it follows them
+ */
+function checkCoinbase(transactions, maxReward, height) {
+  const coinbaseCount = transactions.filter(tx => tx.fromAddress === null).length;
+  if (coinbaseCount !== 1) {
+    return `must contain exactly one coinbase transaction, found ${coinbaseCount}`;
+  }
+
+  const coinbase = transactions[transactions.length - 1];
+  if (coinbase.fromAddress !== null) {
+    return 'coinbase transaction must be the last transaction';
+  }
+  if (!coinbase.toAddress) {
+    return 'coinbase transaction has no recipient';
+  }
+  if (coinbase.originTransactionHash !== String(height)) {
+    return `coinbase transaction must carry the block height ${height}`;
+  }
+
+  if (!new Decimal(coinbase.fee || 0).isZero()) {
+    return 'coinbase transaction must not pay a fee';
+  }
+
+  const amount = new Decimal(coinbase.amount);
+  if (amount.isNegative()) {
+    return 'coinbase amount must not be negative';
+  }
+  if (amount.greaterThan(maxReward)) {
+    return `coinbase pays ${amount.toFixed(8)} but at most ${new Decimal(maxReward).toFixed(8)} is allowed`;
+  }
+  return null;
+}
+
+export { checkCoinbase };
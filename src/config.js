@@ -0,0 +1,339 @@
+import fs from 'fs';
+import path from 'path';
+import yargs from 'yargs/yargs';
+import { hideBin } from 'yargs/helpers';
+import { ConfigError } from './errors.js';
+
+const DEFAULT_CONFIG = {
+  database: {
+    backend: 'mysql', // 'mysql' | 'sqlite' | 'memory'
+    host: 'localhost',
+    port: 3306,
+    user: 'root',
+    password: '',
+    name: 'blockchain',
+    connectionLimit: 10,
+    sqliteFile: null // Defaults to `<name>.sqlite`
+  },
+  p2p: {
+    port: 6001,
+    peers: [],
+    heartbeatIntervalSeconds: 30
+  },
+  mining: {
+    enabled: true, // false is the same as the 'disabled' policy
+    policy: 'interval', // When the scheduler mines, see MINING_POLICIES
+    intervalSeconds: 30, // 'interval': time between blocks while transactions are pending
+    pendingCheckIntervalSeconds: 10, // 'threshold' and 'latency': how often the mempool is checked
+    minPendingTransactions: 10, // 'threshold': pending transactions that trigger a block
+    maxLatencySeconds: 60, // 'latency': longest a pending transaction waits before a block is mined
+    maxBlockBytes: 1000000, // Serialized size of the transactions the miner packs into one block
+    threads: 1, // Worker threads searching the nonce space in parallel
+    rewardAddress: null // Receives block rewards and fees; the scheduler does not mine without one
+  },
+  cache: {
+    blockCacheSize: 256 // Block bodies kept in memory; headers are always in memory
+  },
+  fees: {
+    sampleBlocks: 20 // Recent blocks whose confirmed fee rates feed fee estimates
+  },
+  chain: {
+    retargetInterval: 10, // Blocks between difficulty adjustments, 0 keeps the difficulty fixed
+    targetBlockTimeSeconds: 30,
+    maxFutureDriftSeconds: 600, // How far ahead of network-adjusted time a block may be dated
+    miningReward: 100, // Block subsidy of the first era
+    halvingInterval: 210000, // Blocks between subsidy halvings, 0 never halves
+    tailEmission: 0, // Minimum subsidy once halvings bring it lower
+    maxSupply: 0, // Hard cap on all coins ever created, genesis allocation included; 0 for none
+    maxBlockBytes: 1000000, // Serialized size of a block's transactions, coinbase included
+    maxBlockTransactions: 1000, // Coinbase included; at most MAX_BLOCK_TRANSACTIONS
+    checkpoints: {}, // Block height -> hash every accepted chain must contain
+    maxReorgDepth: 100, // Most blocks a reorganization may disconnect, 0 for no limit
+    genesisFile: 'genesis.json' // Genesis specification: chain ID, difficulty and allocations; relative paths start at the working directory
+  }
+};
+
+// Every setting that can be overridden from the environment or the command line.
+// [config path, environment variable(s), CLI flag, type]
+const OVERRIDES = [
+  ['database.backend', ['STORAGE_BACKEND'], 'db-backend', 'string'],
+  ['database.host', ['DATABASE_HOST'], 'db-host', 'string'],
+  ['database.port', ['DATABASE_PORT'], 'db-port', 'integer'],
+  ['database.user', ['DATABASE_USER'], 'db-user', 'string'],
+  ['database.password', ['DATABASE_PASSWORD'], 'db-password', 'string'],
+  ['database.name', ['DATABASE_NAME'], 'db-name', 'string'],
+  ['database.connectionLimit', ['DATABASE_CONNECTION_LIMIT'], 'db-connection-limit', 'integer'],
+  ['database.sqliteFile', ['SQLITE_FILE'], 'sqlite-file', 'string'],
+  ['p2p.port', ['P2P_PORT'], 'p2p-port', 'integer'],
+  ['p2p.peers', ['PEERS'], 'peers', 'list'],
+  ['p2p.heartbeatIntervalSeconds', ['P2P_HEARTBEAT_INTERVAL'], 'p2p-heartbeat-interval', 'number'],
+  ['mining.enabled', ['MINING_ENABLED'], 'mining-enabled', 'boolean'],
+  ['mining.policy', ['MINING_POLICY'], 'mining-policy', 'string'],
+  ['mining.intervalSeconds', ['MINING_INTERVAL'], 'mining-interval', 'number'],
+  ['mining.pendingCheckIntervalSeconds', ['MINING_PENDING_CHECK_INTERVAL'], 'mining-pending-check-interval', 'number'],
+  ['mining.minPendingTransactions', ['MINING_MIN_PENDING'], 'mining-min-pending', 'integer'],
+  ['mining.maxLatencySeconds', ['MINING_MAX_LATENCY'], 'mining-max-latency', 'number'],
+  ['mining.maxBlockBytes', ['MINING_MAX_BLOCK_BYTES'], 'mining-max-block-bytes', 'integer'],
+  ['mining.threads', ['MINING_THREADS'], 'mining-threads', 'integer'],
+  ['mining.rewardAddress', ['MINING_REWARD_ADDRESS', 'MINER_ADDRESS'], 'reward-address', 'string'],
+  ['cache.blockCacheSize', ['BLOCK_CACHE_SIZE'], 'block-cache-size', 'integer'],
+  ['fees.sampleBlocks', ['FEE_SAMPLE_BLOCKS'], 'fee-sample-blocks', 'integer'],
+  ['chain.retargetInterval', ['RETARGET_INTERVAL'], 'retarget-interval', 'integer'],
+  ['chain.targetBlockTimeSeconds', ['TARGET_BLOCK_TIME'], 'target-block-time', 'number'],
+  ['chain.maxFutureDriftSeconds', ['MAX_FUTURE_DRIFT'], 'max-future-drift', 'number'],
+  ['chain.miningReward', ['MINING_REWARD'], 'mining-reward', 'number'],
+  ['chain.halvingInterval', ['HALVING_INTERVAL'], 'halving-interval', 'integer'],
+  ['chain.tailEmission', ['TAIL_EMISSION'], 'tail-emission', 'number'],
+  ['chain.maxSupply', ['MAX_SUPPLY'], 'max-supply', 'number'],
+  ['chain.maxBlockBytes', ['MAX_BLOCK_BYTES'], 'max-block-bytes', 'integer'],
+  ['chain.maxBlockTransactions', ['MAX_BLOCK_TRANSACTIONS'], 'max-block-transactions', 'integer'],
+  ['chain.checkpoints', ['CHECKPOINTS'], 'checkpoints', 'map'],
+  ['chain.maxReorgDepth', ['MAX_REORG_DEPTH'], 'max-reorg-depth', 'integer'],
+  ['chain.genesisFile', ['GENESIS_FILE'], 'genesis-file', 'string']
+];
+
+const STORAGE_BACKENDS = ['mysql', 'sqlite', 'memory'];
+
+// 'interval': a block every mining.intervalSeconds while transactions are pending
+// 'threshold': a block once mining.minPendingTransactions transactions are pending
+// 'latency': a block once the oldest pending transaction is mining.maxLatencySeconds old
+// 'onDemand': only when asked to, from the CLI
+// 'disabled': never
+const MINING_POLICIES = ['interval', 'threshold', 'latency', 'onDemand', 'disabled'];
+
+// Merkle trees are built at most 10 levels deep, which holds 2^10 transactions
+const MAX_BLOCK_TRANSACTIONS = 1024;
+
+/**
+ * Builds the node configuration from, in increasing order of precedence:
+ * built-in defaults, a JSON config file, environment variables and CLI flags.
+ * @param {Object} [sources]
+ * @param {string[]} [sources.argv=process.argv] - Full process argv
+ * @param {Object} [sources.env=process.env] - Environment variables
+ * @returns {Object} - Validated, frozen configuration
+ * @throws {ConfigError} - If any setting is invalid
+ */
+function loadConfig({ argv = process.argv, env = process.env } = {}) {
+  const flags = yargs(hideBin(argv))
+    .parserConfiguration({ 'camel-case-expansion': false, 'parse-numbers': false, 'parse-positional-numbers': false })
+    .help(false)
+    .version(false)
+    .parse();
+
+  const config = structuredClone(DEFAULT_CONFIG);
+  const problems = [];
+
+  const configFile = flags.config || env.AIBTCC_CONFIG || defaultConfigFile();
+  if (configFile) {
+    try {
+      merge(config, JSON.parse(fs.readFileSync(configFile, 'utf8')));
+    } catch (err) {
+      problems.push(`Cannot read config file ${configFile}: ${err.message}`);
+    }
+  }
+
+  for (const [key, envNames, flag, type] of OVERRIDES) {
+    const envName = envNames.find(name => env[name] !== undefined && env[name] !== '');
+    if (envName) {
+      applyOverride(config, key, env[envName], type, `environment variable ${envName}`, problems);
+    }
+    if (flags[flag] !== undefined) {
+      applyOverride(config, key, flags[flag], type, `flag --${flag}`, problems);
+    }
+  }
+
+  if (!config.database.sqliteFile) {
+    config.database.sqliteFile = `${config.database.name}.sqlite`;
+  }
+
+  problems.push(...validateConfig(config));
+  if (problems.length > 0) {
+    throw new ConfigError(problems);
+  }
+
+  return deepFreeze(config);
+}
+
+function defaultConfigFile() {
+  const file = path.resolve('config.json');
+  return fs.existsSync(file) ? file : null;
+}
+
+function merge(target, source) {
+  for (const [key, value] of Object.entries(source)) {
+    if (value && typeof value === 'object' && !Array.isArray(value) && target[key] && typeof target[key] === 'object') {
+      merge(target[key], value);
+    } else {
+      target[key] = value;
+    }
+  }
+}
+
+function applyOverride(config, key, raw, type, source, problems) {
+  const value = coerce(raw, type);
+  if (value === undefined) {
+    problems.push(`${source} must be a valid ${type}, got "${raw}"`);
+    return;
+  }
+  const parts = key.split('.');
+  const section = parts.slice(0, -1).reduce((obj, part) => obj[part], config);
+  section[parts[parts.length - 1]] = value;
+}
+
+function coerce(raw, type) {
+  const text = String(raw).trim();
+  switch (type) {
+    case 'integer':
+      return /^-?\d+$/.test(text) ? Number(text) : undefined;
+    case 'number':
+      return text !== '' && !isNaN(Number(text)) ? Number(text) : undefined;
+    case 'boolean':
+      if (['true', '1', 'yes'].includes(text.toLowerCase())) return true;
+      if (['false', '0', 'no'].includes(text.toLowerCase())) return false;
+      return undefined;
+    case 'list':
+      return text === '' ? [] : text.split(',').map(item => item.trim()).filter(Boolean);
+    case 'map': { // "key:value,key:value"
+      const entries = coerce(raw, 'list').map(item => item.split(':').map(part => part.trim()));
+      return entries.every(entry => entry.length === 2 && entry[0] !== '') ? Object.fromEntries(entries) : undefined;
+    }
+    default:
+      return text;
+  }
+}
+
+/**
+ * Checks a configuration object and returns a list of human readable problems.
+ * @param {Object} config
+ * @returns {string[]}
+ */
+function validateConfig(config) {
+  const problems = [];
+  const { database, p2p, mining, cache, fees, chain } = config;
+
+  if (!STORAGE_BACKENDS.includes(database.backend)) {
+    problems.push(`database.backend must be one of ${STORAGE_BACKENDS.join(', ')}`);
+  }
+  if (database.backend === 'mysql') {
+    checkPort(database.port, 'database.port', problems);
+    if (!database.host) problems.push('database.host is required for the mysql backend');
+    if (!database.user) problems.push('database.user is required for the mysql backend');
+  }
+  if (!database.name) problems.push('database.name is required');
+  if (!Number.isInteger(database.connectionLimit) || database.connectionLimit < 1) {
+    problems.push('database.connectionLimit must be a positive integer');
+  }
+
+  checkPort(p2p.port, 'p2p.port', problems);
+  if (!Array.isArray(p2p.peers) || p2p.peers.some(peer => !/^wss?:\/\/.+/.test(peer))) {
+    problems.push('p2p.peers must be a list of ws:// or wss:// URLs');
+  }
+  checkPositive(p2p.heartbeatIntervalSeconds, 'p2p.heartbeatIntervalSeconds', problems);
+
+  if (typeof mining.enabled !== 'boolean') problems.push('mining.enabled must be true or false');
+  if (!MINING_POLICIES.includes(mining.policy)) {
+    problems.push(`mining.policy must be one of ${MINING_POLICIES.join(', ')}`);
+  }
+  checkPositive(mining.intervalSeconds, 'mining.intervalSeconds', problems);
+  checkPositive(mining.pendingCheckIntervalSeconds, 'mining.pendingCheckIntervalSeconds', problems);
+  if (!Number.isInteger(mining.minPendingTransactions) || mining.minPendingTransactions < 1) {
+    problems.push('mining.minPendingTransactions must be a positive integer');
+  }
+  checkPositive(mining.maxLatencySeconds, 'mining.maxLatencySeconds', problems);
+  if (!Number.isInteger(mining.maxBlockBytes) || mining.maxBlockBytes < 1) {
+    problems.push('mining.maxBlockBytes must be a positive integer');
+  }
+  if (!Number.isInteger(mining.threads) || mining.threads < 1) {
+    problems.push('mining.threads must be a positive integer');
+  }
+  if (mining.rewardAddress !== null) {
+    checkAddress(mining.rewardAddress, 'mining.rewardAddress', problems);
+  }
+  if (mining.minerAddress !== undefined) {
+    problems.push('mining.minerAddress has been renamed to mining.rewardAddress');
+  }
+
+  if (!Number.isInteger(cache.blockCacheSize) || cache.blockCacheSize < 1) {
+    problems.push('cache.blockCacheSize must be a positive integer');
+  }
+
+  if (!Number.isInteger(fees.sampleBlocks) || fees.sampleBlocks < 1) {
+    problems.push('fees.sampleBlocks must be a positive integer');
+  }
+
+  if (!Number.isInteger(chain.retargetInterval) || chain.retargetInterval < 0) {
+    problems.push('chain.retargetInterval must be a non-negative integer');
+  }
+  checkPositive(chain.targetBlockTimeSeconds, 'chain.targetBlockTimeSeconds', problems);
+  checkPositive(chain.maxFutureDriftSeconds, 'chain.maxFutureDriftSeconds', problems);
+  if (typeof chain.miningReward !== 'number' || !(chain.miningReward >= 0)) {
+    problems.push('chain.miningReward must be a non-negative number');
+  }
+  if (!Number.isInteger(chain.halvingInterval) || chain.halvingInterval < 0) {
+    problems.push('chain.halvingInterval must be a non-negative integer');
+  }
+  if (typeof chain.tailEmission !== 'number' || !(chain.tailEmission >= 0)) {
+    problems.push('chain.tailEmission must be a non-negative number');
+  }
+  if (typeof chain.maxSupply !== 'number' || !(chain.maxSupply >= 0)) {
+    problems.push('chain.maxSupply must be a non-negative number');
+  }
+  if (!Number.isInteger(chain.maxBlockBytes) || chain.maxBlockBytes < 1) {
+    problems.push('chain.maxBlockBytes must be a positive integer');
+  }
+  if (!Number.isInteger(chain.maxBlockTransactions) || chain.maxBlockTransactions < 1 || chain.maxBlockTransactions > MAX_BLOCK_TRANSACTIONS) {
+    problems.push(`chain.maxBlockTransactions must be an integer between 1 and ${MAX_BLOCK_TRANSACTIONS}`);
+  }
+  if (!chain.checkpoints || typeof chain.checkpoints !== 'object' || Array.isArray(chain.checkpoints) ||
+      Object.entries(chain.checkpoints).some(([height, hash]) => !/^\d+$/.test(height) || !/^[0-9a-f]{64}$/.test(hash))) {
+    problems.push('chain.checkpoints must map block heights to 64-character lowercase hex block hashes');
+  }
+  if (!Number.isInteger(chain.maxReorgDepth) || chain.maxReorgDepth < 0) {
+    problems.push('chain.maxReorgDepth must be a non-negative integer');
+  }
+  if (typeof chain.genesisFile !== 'string' || chain.genesisFile.trim() === '') {
+    problems.push('chain.genesisFile is required');
+  }
+  for (const name of ['difficulty', 'genesisAddress', 'genesisReward']) {
+    if (chain[name] !== undefined) {
+      problems.push(`chain.${name} has been removed: the genesis specification in chain.genesisFile sets it`);
+    }
+  }
+
+  return problems;
+}
+
+function checkPort(value, name, problems) {
+  if (!Number.isInteger(value) || value < 1 || value > 65535) {
+    problems.push(`${name} must be an integer between 1 and 65535`);
+  }
+}
+
+function checkPositive(value, name, problems) {
+  if (typeof value !== 'number' || !(value > 0)) {
+    problems.push(`${name} must be a positive number`);
+  }
+}
+
+function checkAddress(value, name, problems) {
+  if (typeof value !== 'string' || value.length < 24 || value.length > 30) {
+    problems.push(`${name} must be a wallet address of 24 to 30 characters`);
+  }
+}
+
+function deepFreeze(obj) {
+  Object.values(obj).forEach(value => {
+    if (value && typeof value === 'object') deepFreeze(value);
+  });
+  return Object.freeze(obj);
+}
+
+let config;
+try {
+  config = loadConfig();
+} catch (err) {
+  console.error(err.message);
+  process.exit(1); // Refuse to start with an invalid configuration
+}
+
+export { config, loadConfig, validateConfig, DEFAULT_CONFIG, MINING_POLICIES };
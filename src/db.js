@@ -1,29 +1,25 @@
-import mysql from 'mysql2/promise';
+import { createStorage } from './storage/index.js';
+import { config } from './config.js';
 
-// Fetch the database name from environment variables or default to 'blockchain'
-const databaseName = process.env.DATABASE_NAME || 'blockchain';
+const { database } = config;
 
-const db = mysql.createPool({
-  host: 'localhost', 
-  port: 3306,        
-  user: 'root',
-  password: 'g46',
-  database: databaseName, // Dynamic database name
-  waitForConnections: true,
-  connectionLimit: 10,
-  queueLimit: 0
+const storage = await createStorage({
+  backend: database.backend,
+  host: database.host,
+  port: database.port,
+  user: database.user,
+  password: database.password,
+  database: database.name,
+  connectionLimit: database.connectionLimit,
+  filename: database.sqliteFile
 });
 
-db.getConnection()
-  .then(connection => {
-    console.log(`Connected to database: ${databaseName}`);;
-    connection.release();
-  })
-  .catch(err => {
-    console.error('Database connection failed:', err);
-    process.exit(1); // Exit the process if the database connection fails
-  });
-
-export {db};
-
+try {
+  await storage.connect();
+  console.log(`Connected to ${database.backend} storage: ${database.name}`);
+} catch (err) {
+  console.error('Database connection failed:', err);
+  process.exit(1); // Exit the process if the database connection fails
+}
 
+export { storage };
@@ -0,0 +1,115 @@
+"use strict";
+
+// Highest possible SHA-256 value; a block at difficulty d needs hash <= MAX_TARGET / d
+const MAX_TARGET = (1n << 256n) - 1n;
+
+// A single retarget may move difficulty by at most this factor in either direction
+const MAX_ADJUSTMENT_FACTOR = 4;
+
+/**
+ * Whether a value can be a block difficulty: a non-negative safe integer.
+ * Difficulties from peers must pass this before any target or work is
+ * computed from them.
+ * @param {*} difficulty
+ * @returns {boolean}
+ */
+function isValidDifficulty(difficulty) {
+  return Number.isSafeInteger(difficulty) && difficulty >= 0;
+}
+
+/**
+ * Hash target for a difficulty. Difficulty is the expected number of hashes
+ * needed to find a valid block; 0 and 1 both accept any hash.
+ * @param {number} difficulty
+ * @returns {bigint}
+ */
+function targetForDifficulty(difficulty) {
+  if (difficulty <= 1) {
+    return MAX_TARGET;
+  }
+  return MAX_TARGET / BigInt(difficulty);
+}
+
+/**
+ * @param {string} hash - Block hash as 64 hex characters
+ * @param {number} difficulty
+ * @returns {boolean} - True if the hash is at or below the difficulty's target
+ */
+function meetsDifficulty(hash, difficulty) {
+  if (typeof hash !== 'string' || !/^[0-9a-f]{64}$/.test(hash)) {
+    return false;
+  }
+  return BigInt('0x' + hash) <= targetForDifficulty(difficulty);
+}
+
+/**
+ * Difficulty required for the block at `height`, derived only from the
+ * headers before it so every node computes the same value. Difficulty
+ * changes every `retargetInterval` blocks, scaling the previous value by
+ * how far the last interval's block time was from the target.
+ * @param {number} height - Height of the block being mined or validated (>= 1)
+ * @param {Function} headerAt - Returns the header ({ timestamp, difficulty }) at a lower height
+ * @param {Object} params - Chain parameters
+ * @param {number} params.retargetInterval - Blocks between adjustments; 0 keeps the difficulty fixed
+ * @param {number} params.targetBlockTimeSeconds - Desired average time between blocks
+ * @returns {number}
+ */
+function nextDifficulty(height, headerAt, { retargetInterval, targetBlockTimeSeconds }) {
+  const previous = headerAt(height - 1);
+  const previousDifficulty = Number(previous.difficulty);
+
+  if (retargetInterval === 0 || height % retargetInterval !== 0) {
+    return previousDifficulty;
+  }
+
+  const first = headerAt(Math.max(height - 1 - retargetInterval, 0));
+  const blockGaps = (height - 1) - Number(first.index);
+  if (blockGaps <= 0) {
+    return previousDifficulty;
+  }
+
+  const expectedTimespan = blockGaps * targetBlockTimeSeconds * 1000;
+  const actualTimespan = Math.min(
+    Math.max(Number(previous.timestamp) - Number(first.timestamp), expectedTimespan / MAX_ADJUSTMENT_FACTOR),
+    expectedTimespan * MAX_ADJUSTMENT_FACTOR
+  );
+
+  return Math.max(1, Math.round(Math.max(previousDifficulty, 1) * expectedTimespan / actualTimespan));
+}
+
+/**
+ * Expected number of hashes needed to find a block at this difficulty.
+ * @param {number} difficulty
+ * @returns {bigint}
+ */
+function blockWork(difficulty) {
+  return (MAX_TARGET + 1n) / (targetForDifficulty(difficulty) + 1n);
+}
+
+/**
+ * Total work of a sequence of blocks or headers.
+ * @param {Object[]} blocks - Anything with a `difficulty` field
+ * @returns {bigint}
+ */
+function chainWork(blocks) {
+  return blocks.reduce((total, block) => total + blockWork(Number(block.difficulty)), 0n);
+}
+
+/**
+ * Fork choice: the tip with more accumulated work wins; on equal work the
+ * numerically lower tip hash wins, so every node picks the same tip.
+ * @param {{work: bigint, hash: string}} a
+ * @param {{work: bigint, hash: string}} b
+ * @returns {number} - Positive if `a` is preferred, negative if `b` is, 0 if they are the same tip
+ */
+function compareChainTips(a, b) {
+  if (a.work !== b.work) {
+    return a.work > b.work ? 1 : -1;
+  }
+  if (a.hash === b.hash) {
+    return 0;
+  }
+  return a.hash < b.hash ? 1 : -1;
+}
+
+export { MAX_TARGET, isValidDifficulty, targetForDifficulty, meetsDifficulty, nextDifficulty, blockWork, chainWork, compareChainTips };
@@ -0,0 +1,106 @@
+"use strict";
+
+import Decimal from 'decimal.js';
+
+/**
+ * Emission schedule. Every function here is a pure function of block height
+ * and the chain parameters in `config.chain`, plus the genesis allocation:
+ *   miningReward     - subsidy of the first era
+ *   halvingInterval  - blocks per era, the subsidy halves at every multiple (0 never halves)
+ *   tailEmission     - the subsidy never drops below this (0 lets it reach zero)
+ *   maxSupply        - hard cap on all coins ever created, genesis allocation included (0 for no cap)
+ *   genesisSupply    - coins allocated by the genesis block; not a setting, it is the sum of the
+ *                      allocations of the genesis specification (see `Blockchain.getChainParams`)
+ */
+
+// After this many halvings any realistic subsidy has rounded down to zero
+const MAX_HALVINGS = 64;
+
+// Subsidy of era `era` before the supply cap is applied
+function eraSubsidy(era, params) {
+  const halved = era >= MAX_HALVINGS
+    ? new Decimal(0)
+    : new Decimal(params.miningReward).div(new Decimal(2).pow(era)).toDecimalPlaces(8, Decimal.ROUND_DOWN);
+  return Decimal.max(halved, params.tailEmission);
+}
+
+function eraOf(height, params) {
+  return params.halvingInterval === 0 ? 0 : Math.floor(height / params.halvingInterval);
+}
+
+// Sum of the uncapped subsidies of blocks 1..height, one step per era
+function uncappedIssuance(height, params) {
+  let total = new Decimal(0);
+  for (let era = 0, start = 1; start <= height; era++) {
+    const subsidy = eraSubsidy(era, params);
+    const lastOfEra = params.halvingInterval === 0 ? height : Math.min((era + 1) * params.halvingInterval - 1, height);
+    const constantFromHere = params.halvingInterval === 0 || subsidy.equals(eraSubsidy(era + 1, params));
+    const end = constantFromHere ? height : lastOfEra;
+
+    total = total.plus(subsidy.times(end - start + 1));
+    start = end + 1;
+  }
+  return total;
+}
+
+/**
+ * Coins created by blocks 0..height, genesis allocation included.
+ * @param {number} height
+ * @param {Object} params - `config.chain` and `genesisSupply`
+ * @returns {Decimal}
+ */
+function scheduledSupplyAt(height, params) {
+  if (height < 0) {
+    return new Decimal(0);
+  }
+  const total = uncappedIssuance(height, params).plus(params.genesisSupply);
+  return params.maxSupply > 0 ? Decimal.min(total, params.maxSupply) : total;
+}
+
+/**
+ * New coins the coinbase of the block at `height` may create.
+ * The genesis allocation is not a subsidy, so height 0 returns 0.
+ * @param {number} height
+ * @param {Object} params - `config.chain` and `genesisSupply`
+ * @returns {Decimal}
+ */
+function blockSubsidy(height, params) {
+  if (height <= 0) {
+    return new Decimal(0);
+  }
+  return scheduledSupplyAt(height, params).minus(scheduledSupplyAt(height - 1, params));
+}
+
+/**
+ * Total supply the schedule converges to, or null if it grows forever.
+ * @param {Object} params - `config.chain` and `genesisSupply`
+ * @returns {Decimal|null}
+ */
+function supplyLimit(params) {
+  if (params.maxSupply > 0) {
+    return new Decimal(params.maxSupply);
+  }
+  if (params.tailEmission > 0) {
+    return null;
+  }
+  if (new Decimal(params.miningReward).isZero()) {
+    return new Decimal(params.genesisSupply);
+  }
+  if (params.halvingInterval === 0) {
+    return null;
+  }
+
+  let era = 0;
+  while (!eraSubsidy(era, params).isZero()) era++;
+  return scheduledSupplyAt(era * params.halvingInterval - 1, params);
+}
+
+// First height after `height` at which the era changes, or null if the subsidy never halves
+function nextHalvingHeight(height, params) {
+  if (params.halvingInterval === 0) {
+    return null;
+  }
+  return (eraOf(height, params) + 1) * params.halvingInterval;
+}
+
+export { blockSubsidy, scheduledSupplyAt, supplyLimit, nextHalvingHeight };
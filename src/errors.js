@@ -1,3 +1,4 @@
+
 class InvalidAddressError extends Error {
     constructor(message) {
       super(message);
@@ -11,11 +12,20 @@ class InvalidAddressError extends Error {
       this.name = 'InvalidPrivateKeyError';
     }
   }
+
+  class ConfigError extends Error {
+    constructor(problems) {
+      super(`Invalid configuration:\n  - ${problems.join('\n  - ')}`);
+      this.name = 'ConfigError';
+      this.problems = problems;
+    }
+  }
   
   // Add other custom errors as needed
   
-  module.exports = {
+  export {
     InvalidAddressError,
     InvalidPrivateKeyError,
+    ConfigError,
     // Export other errors as needed
-  };
\ No newline at end of file
+  };
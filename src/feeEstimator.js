@@ -0,0 +1,91 @@
+"use strict";
+
+import Decimal from 'decimal.js';
+
+// Fee rates are quoted per 1000 bytes of serialized transaction
+const BYTES_PER_KB = 1000;
+// Approximate size of a signed transfer, used when the caller does not know the exact size
+const TYPICAL_TRANSACTION_SIZE = 700;
+// Smallest fee rate step: one unit of the 8th decimal per kB
+const MIN_FEE_RATE_STEP = new Decimal('0.00000001');
+
+function feeRatePerKb(tx) {
+  return tx.getFeeRate().times(BYTES_PER_KB);
+}
+
+/**
+ * Fee rate paid by recently confirmed transactions. The shorter the target,
+ * the higher the percentile: 1 block uses the median, 3 blocks the 25th
+ * percentile, 9 blocks the 10th.
+ */
+function historicalFeeRate(recentBlocks, targetBlocks) {
+  const rates = recentBlocks
+    .flatMap(block => block.transactions.filter(tx => tx.fromAddress !== null))
+    .map(feeRatePerKb)
+    .sort((a, b) => a.comparedTo(b));
+
+  if (rates.length === 0) {
+    return new Decimal(0);
+  }
+  return rates[Math.floor((rates.length - 1) / (targetBlocks + 1))];
+}
+
+/**
+ * Fee rate needed to be mined within `targetBlocks` given the current
+ * mempool: block assembly takes the highest fee rates first, so the new
+ * transaction must outbid whatever would otherwise fill those blocks.
+ */
+function mempoolFeeRate(pending, targetBlocks, maxBlockBytes, size) {
+  const byFeeRate = pending
+    .filter(tx => tx.fromAddress !== null)
+    .map(tx => ({ rate: feeRatePerKb(tx), size: tx.getSize() }))
+    .sort((a, b) => b.rate.comparedTo(a.rate));
+
+  const capacity = targetBlocks * maxBlockBytes - size;
+  let filled = 0;
+  for (const entry of byFeeRate) {
+    filled += entry.size;
+    if (filled > capacity) {
+      return entry.rate.plus(MIN_FEE_RATE_STEP);
+    }
+  }
+  return new Decimal(0);
+}
+
+/**
+ * Suggests a fee for a transaction that should be mined within
+ * `targetBlocks` blocks, from the fee rates confirmed in recent blocks and
+ * the depth of the mempool, whichever asks for more.
+ * @param {Object} input
+ * @param {Block[]} input.recentBlocks - The most recent main-chain blocks
+ * @param {Transaction[]} input.pending - Transactions waiting in the mempool
+ * @param {number} input.targetBlocks - Number of blocks the transaction may wait, at least 1
+ * @param {number} input.maxBlockBytes - Transaction bytes a miner packs into one block
+ * @param {number} input.size - Serialized size of the transaction to price in bytes
+ * @returns {{targetBlocks: number, feeRatePerKb: string, fee: string, mempoolBlocks: number, sampleSize: number}}
+ *   Fee amounts are strings with 8 decimals; `mempoolBlocks` is how many blocks the current mempool fills
+ */
+function estimateFee({ recentBlocks, pending, targetBlocks, maxBlockBytes, size }) {
+  if (!Number.isInteger(targetBlocks) || targetBlocks < 1) {
+    throw new Error("The confirmation target must be a positive number of blocks.");
+  }
+  if (!Number.isInteger(size) || size < 1) {
+    throw new Error("The transaction size must be a positive number of bytes.");
+  }
+
+  const rate = Decimal.max(
+    historicalFeeRate(recentBlocks, targetBlocks),
+    mempoolFeeRate(pending, targetBlocks, maxBlockBytes, size)
+  );
+  const mempoolBytes = pending.reduce((total, tx) => total + tx.getSize(), 0);
+
+  return {
+    targetBlocks,
+    feeRatePerKb: rate.toFixed(8, Decimal.ROUND_UP),
+    fee: rate.times(size).dividedBy(BYTES_PER_KB).toFixed(8, Decimal.ROUND_UP),
+    mempoolBlocks: Math.ceil(mempoolBytes / maxBlockBytes),
+    sampleSize: recentBlocks.reduce((total, block) => total + block.transactions.filter(tx => tx.fromAddress !== null).length, 0)
+  };
+}
+
+export { estimateFee, BYTES_PER_KB, TYPICAL_TRANSACTION_SIZE };
@@ -0,0 +1,34 @@
+"use strict";
+
+/**
+ * Checks a main-chain block against the hard checkpoints, which pin the
+ * block hash at chosen heights.
+ * @param {number} height - Block height
+ * @param {string} hash - Block hash
+ * @param {Object} checkpoints - Height (as an object key) -> block hash, see `config.chain.checkpoints`
+ * @returns {string|null} - Why the block is rejected, or null if it passes
+ */
+function checkCheckpoint(height, hash, checkpoints) {
+  const expected = checkpoints[height];
+  if (expected !== undefined && expected !== hash) {
+    return `does not match the checkpoint at height ${height} (${expected})`;
+  }
+  return null;
+}
+
+/**
+ * Checks how many main-chain blocks a reorganization would disconnect.
+ * @param {number} tipHeight - Height of the current tip
+ * @param {number} forkIndex - Height of the last block both branches share
+ * @param {number} maxReorgDepth - Most blocks a reorganization may disconnect, 0 for no limit
+ * @returns {string|null} - Why the reorganization is refused, or null if it is allowed
+ */
+function checkReorgDepth(tipHeight, forkIndex, maxReorgDepth) {
+  const depth = tipHeight - forkIndex;
+  if (maxReorgDepth > 0 && depth > maxReorgDepth) {
+    return `would disconnect ${depth} blocks, more than the maximum reorganization depth of ${maxReorgDepth}`;
+  }
+  return null;
+}
+
+export { checkCheckpoint, checkReorgDepth };
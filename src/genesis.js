@@ -0,0 +1,118 @@
+"use strict";
+
+import fs from 'fs';
+import Decimal from 'decimal.js';
+import { Transaction } from './transaction.js';
+import { Block } from './block.js';
+
+/**
+ * A genesis specification (genesis.json) fixes everything the genesis block
+ * is made of, so every node derives the same block and hash from it:
+ *
+ *   chainId     - Name of the network, carried by the genesis block and every later block and transaction
+ *   timestamp   - Genesis time in ms, used for the block and its allocations
+ *   difficulty  - Genesis difficulty (expected hashes per block, 0 or 1 accepts any hash)
+ *   extraData   - Optional free-form string
+ *   allocations - [{ address, amount }] initial balances, one transaction each, in file order
+ */
+
+/**
+ * Reads and checks a genesis specification.
+ * @param {string} file - Path of the JSON file
+ * @returns {Object} - The specification
+ * @throws {Error} - If the file cannot be read or the specification is invalid
+ */
+function loadGenesisSpec(file) {
+  let spec;
+  try {
+    spec = JSON.parse(fs.readFileSync(file, 'utf8'));
+  } catch (err) {
+    throw new Error(`Cannot read genesis specification ${file}: ${err.message}`);
+  }
+
+  const problems = validateGenesisSpec(spec);
+  if (problems.length > 0) {
+    throw new Error(`Invalid genesis specification ${file}:\n  - ${problems.join('\n  - ')}`);
+  }
+  return spec;
+}
+
+/**
+ * @param {Object} spec
+ * @returns {string[]} - Human readable problems, empty if the specification is valid
+ */
+function validateGenesisSpec(spec) {
+  const problems = [];
+  if (!spec || typeof spec !== 'object') {
+    return ['must be a JSON object'];
+  }
+
+  if (typeof spec.chainId !== 'string' || spec.chainId.trim() === '') {
+    problems.push('chainId must be a non-empty string');
+  }
+  if (!Number.isInteger(spec.timestamp) || spec.timestamp < 0) {
+    problems.push('timestamp must be a non-negative integer (ms since the epoch)');
+  }
+  if (!Number.isInteger(spec.difficulty) || spec.difficulty < 0) {
+    problems.push('difficulty must be a non-negative integer');
+  }
+  if (spec.extraData !== undefined && typeof spec.extraData !== 'string') {
+    problems.push('extraData must be a string');
+  }
+
+  if (!Array.isArray(spec.allocations) || spec.allocations.length === 0) {
+    problems.push('allocations must be a non-empty list');
+    return problems;
+  }
+  const seen = new Set();
+  spec.allocations.forEach((allocation, i) => {
+    const { address, amount } = allocation || {};
+    if (typeof address !== 'string' || address.length < 24 || address.length > 30) {
+      problems.push(`allocations[${i}].address must be a wallet address of 24 to 30 characters`);
+    } else if (seen.has(address)) {
+      problems.push(`allocations[${i}].address ${address} is allocated twice`);
+    }
+    seen.add(address);
+    if (!isPositiveAmount(amount)) {
+      problems.push(`allocations[${i}].amount must be a positive amount with at most 8 decimals`);
+    }
+  });
+  return problems;
+}
+
+function isPositiveAmount(amount) {
+  if (typeof amount !== 'string' && typeof amount !== 'number') {
+    return false;
+  }
+  try {
+    const value = new Decimal(amount);
+    return value.isFinite() && value.greaterThan(0) && value.decimalPlaces() <= 8;
+  } catch (err) {
+    return false;
+  }
+}
+
+/**
+ * Builds the genesis block described by a specification. The result only
+ * depends on the specification: the same file yields a byte-identical block.
+ * @param {Object} spec - A valid specification
+ * @returns {Block}
+ */
+function buildGenesisBlock(spec) {
+  const allocations = spec.allocations.map(({ address, amount }) =>
+    new Transaction(null, address, new Decimal(amount).toFixed(8), spec.timestamp)
+  );
+
+  const block = new Block(0, null, spec.timestamp, allocations, spec.difficulty, spec.chainId);
+  block.extraData = JSON.stringify({ chainId: spec.chainId, extraData: spec.extraData || '' });
+  block.hash = block.calculateHash();
+  block.mineBlock(spec.difficulty); // Starts from nonce 0, so it always finds the same nonce
+  return block;
+}
+
+// Sum of all initial allocations
+function genesisAllocationTotal(spec) {
+  return spec.allocations.reduce((total, { amount }) => total.plus(amount), new Decimal(0));
+}
+
+export { loadGenesisSpec, validateGenesisSpec, buildGenesisBlock, genesisAllocationTotal };
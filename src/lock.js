@@ -1,4 +1,5 @@
 const locks = {};
+const queues = {}; // Resource -> settled promise of the last task queued by withLock
 
 // Acquire a lock for a specific resource
 async function acquireLock(resource) {
@@ -14,4 +15,18 @@ async function releaseLock(resource) {
   delete locks[resource];
 }
 
-export { acquireLock, releaseLock };
+// Run a task once every task queued before it for the same resource has finished
+async function withLock(resource, task) {
+  const run = (queues[resource] || Promise.resolve()).then(() => task());
+  const settled = run.catch(() => {});
+  queues[resource] = settled;
+  try {
+    return await run;
+  } finally {
+    if (queues[resource] === settled) {
+      delete queues[resource];
+    }
+  }
+}
+
+export { acquireLock, releaseLock, withLock };
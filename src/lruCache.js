@@ -0,0 +1,56 @@
+/**
+ * Fixed-capacity cache that evicts the least recently used entry.
+ * Relies on Map preserving insertion order: the first key is the oldest.
+ */
+class LRUCache {
+  /**
+   * @param {number} capacity - Maximum number of entries kept
+   */
+  constructor(capacity) {
+    if (!Number.isInteger(capacity) || capacity < 1) {
+      throw new Error("LRU cache capacity must be a positive integer.");
+    }
+    this.capacity = capacity;
+    this.entries = new Map();
+  }
+
+  get size() {
+    return this.entries.size;
+  }
+
+  has(key) {
+    return this.entries.has(key);
+  }
+
+  /**
+   * Returns the cached value and marks it as most recently used.
+   * @returns {*} - The value, or undefined if not cached
+   */
+  get(key) {
+    if (!this.entries.has(key)) {
+      return undefined;
+    }
+    const value = this.entries.get(key);
+    this.entries.delete(key);
+    this.entries.set(key, value);
+    return value;
+  }
+
+  set(key, value) {
+    this.entries.delete(key);
+    this.entries.set(key, value);
+    if (this.entries.size > this.capacity) {
+      this.entries.delete(this.entries.keys().next().value);
+    }
+  }
+
+  delete(key) {
+    return this.entries.delete(key);
+  }
+
+  clear() {
+    this.entries.clear();
+  }
+}
+
+export { LRUCache };
@@ -1,5 +1,5 @@
 import crypto from 'crypto'; // Import the crypto module for hashing
-import { db } from './db.js'; // Import the database connection module
+import { storage } from './db.js'; // Import the storage backend
 
 class Node {
   /**
@@ -50,18 +50,18 @@ class MerkleTree {
   /**
    * Saves all nodes of the Merkle Tree to the database.
    * @param {string} blockHash - Hash of the block associated with the Merkle Tree
+   * @param {StorageAdapter} [store=storage] - Storage handle, e.g. the one of an open transaction
    * @param {Node} [node=this.root] - Current node being processed
    * @param {number} [level=0] - Current level in the tree
    * @param {number} [index=0] - Index of the current node
    * @returns {Promise<void>}
    */
-  async saveNodesToDatabase(blockHash, node = this.root, level = 0, index = 0) {
+  async saveNodesToDatabase(blockHash, store = storage, node = this.root, level = 0, index = 0) {
     if (node !== null) {
-      const query = "INSERT INTO merkle_nodes (block_hash, node_level, node_index, node_value) VALUES (?, ?, ?, ?)";
-      const values = [blockHash, level, index, node.value];
+      const row = { block_hash: blockHash, node_level: level, node_index: index, node_value: node.value };
       
       try {
-        await db.query(query, values);
+        await store.saveMerkleNode(row);
         //console.log(`Saved node at level ${level}, index ${index}: ${node.value}`);
       } catch (err) {
         console.error("Database query error:", err);
@@ -69,8 +69,8 @@ class MerkleTree {
       }
   
       if (node.left !== null) {
-        await this.saveNodesToDatabase(blockHash, node.left, level + 1, index * 2);
-        await this.saveNodesToDatabase(blockHash, node.right, level + 1, index * 2 + 1);
+        await this.saveNodesToDatabase(blockHash, store, node.left, level + 1, index * 2);
+        await this.saveNodesToDatabase(blockHash, store, node.right, level + 1, index * 2 + 1);
       }
     }
   }
@@ -287,20 +287,19 @@ class MerkleTree {
 // New class added to the file
 class MerkleProofPath {
   static async getProofPath(transactionHash) {
-    const query = "SELECT proof_path FROM merkle_proof_paths WHERE transaction_hash = ?";
     console.log("Retrieving proof path for transaction:", transactionHash);
 
     try {
-      const [rows] = await db.query(query, [transactionHash]);
+      const row = await storage.getMerkleProof(transactionHash);
 
-      if (rows.length === 0) {
+      if (!row) {
         // No proof path found for the given transaction hash
         console.log("No proof path found for transaction:", transactionHash);
         return null;
       }
 
-      // Assuming only one result is returned per transaction_hash
-      const proofPath = JSON.parse(rows[0].proof_path);
+      // Only one proof path is stored per transaction_hash
+      const proofPath = JSON.parse(row.proof_path);
       console.log("Verified proof path:", proofPath);
       return proofPath;
     } catch (err) {
@@ -0,0 +1,115 @@
+"use strict";
+
+import { Worker } from 'worker_threads';
+import { meetsDifficulty } from './difficulty.js';
+
+const WORKER_URL = new URL('./minerWorker.js', import.meta.url);
+
+/**
+ * Proof of work search off the main thread, so P2P handling, the CLI and
+ * timers keep running while a block is mined. The nonce space is split
+ * between `threads` workers: worker i tries nonce + i, nonce + i + threads, ...
+ * One block is mined at a time; `cancel()` abandons it.
+ */
+class Miner {
+  /**
+   * @param {Object} options
+   * @param {number} options.threads - Worker threads per block
+   * @param {number} [options.progressIntervalMs] - How often each worker reports its hash count
+   */
+  constructor({ threads, progressIntervalMs = 5000 }) {
+    this.threads = threads;
+    this.progressIntervalMs = progressIntervalMs;
+    this.current = null; // Stops the search in progress, if any
+  }
+
+  isMining() {
+    return this.current !== null;
+  }
+
+  /**
+   * Searches for a nonce that makes the block's hash meet `difficulty`,
+   * starting at the block's current nonce. On success the block's nonce and
+   * hash are updated.
+   * @param {Block} block
+   * @param {number} difficulty
+   * @param {Object} [options]
+   * @param {Function} [options.onProgress] - Called with { hashes, hashrate } (hashes tried so far, per second)
+   * @returns {Promise<boolean>} - True if the block was mined, false if mining was cancelled
+   */
+  mine(block, difficulty, { onProgress = () => {} } = {}) {
+    if (this.current) {
+      return Promise.reject(new Error("A block is already being mined."));
+    }
+    if (meetsDifficulty(block.hash, difficulty)) {
+      return Promise.resolve(true);
+    }
+
+    const { prefix, suffix } = block.hashParts();
+    const stopFlag = new SharedArrayBuffer(Int32Array.BYTES_PER_ELEMENT);
+    const startedAt = Date.now();
+    const workers = [];
+    let hashes = 0;
+
+    return new Promise((resolve, reject) => {
+      const finish = (err, mined) => {
+        if (!workers.length) {
+          return; // Already finished
+        }
+        Atomics.store(new Int32Array(stopFlag), 0, 1);
+        workers.splice(0).forEach(worker => worker.terminate());
+        this.current = null;
+        err ? reject(err) : resolve(mined);
+      };
+
+      const onMessage = (message) => {
+        if (message.type === 'progress') {
+          hashes += message.hashes;
+          onProgress({ hashes, hashrate: hashes / Math.max((Date.now() - startedAt) / 1000, 0.001) });
+          return;
+        }
+
+        block.nonce = message.nonce;
+        block.hash = block.calculateHash();
+        if (block.hash !== message.hash || !meetsDifficulty(block.hash, difficulty)) {
+          finish(new Error(`Miner worker returned an invalid nonce ${message.nonce} for block ${block.index}.`));
+          return;
+        }
+        finish(null, true);
+      };
+
+      for (let i = 0; i < this.threads; i++) {
+        const worker = new Worker(WORKER_URL, {
+          workerData: {
+            prefix,
+            suffix,
+            difficulty,
+            startNonce: block.nonce + i,
+            step: this.threads,
+            stopFlag,
+            progressIntervalMs: this.progressIntervalMs
+          }
+        });
+        worker.on('message', onMessage);
+        worker.on('error', err => finish(err));
+        workers.push(worker);
+      }
+
+      this.current = () => finish(null, false);
+    });
+  }
+
+  /**
+   * Abandons the block being mined; its `mine` call resolves to false.
+   * @returns {boolean} - False if nothing was being mined
+   */
+  cancel() {
+    if (!this.current) {
+      return false;
+    }
+    this.current();
+    return true;
+  }
+}
+
+export { Miner };
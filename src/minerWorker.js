@@ -0,0 +1,42 @@
+"use strict";
+
+import crypto from 'crypto';
+import { parentPort, workerData } from 'worker_threads';
+import { targetForDifficulty } from './difficulty.js';
+
+// Hashes tried between checks of the stop flag and the progress clock
+const BATCH_SIZE = 4096;
+
+/**
+ * Worker side of `Miner`: tries the nonces startNonce, startNonce + step, ...
+ * of one block until a hash meets the difficulty or the shared stop flag is
+ * raised. Posts { type: 'progress', hashes } about every progressIntervalMs
+ * and { type: 'found', nonce, hash } when it finds a block.
+ */
+function search({ prefix, suffix, difficulty, startNonce, step, stopFlag, progressIntervalMs }) {
+  const stop = new Int32Array(stopFlag);
+  const target = targetForDifficulty(difficulty);
+  let nonce = startNonce;
+  let hashes = 0;
+  let reportedAt = Date.now();
+
+  while (Atomics.load(stop, 0) === 0) {
+    for (let i = 0; i < BATCH_SIZE; i++) {
+      const hash = crypto.createHash('sha256').update(prefix + nonce + suffix).digest('hex');
+      if (BigInt('0x' + hash) <= target) {
+        parentPort.postMessage({ type: 'found', nonce, hash });
+        return;
+      }
+      nonce += step;
+    }
+
+    hashes += BATCH_SIZE;
+    if (Date.now() - reportedAt >= progressIntervalMs) {
+      parentPort.postMessage({ type: 'progress', hashes });
+      hashes = 0;
+      reportedAt = Date.now();
+    }
+  }
+}
+
+search(workerData);
@@ -0,0 +1,138 @@
+"use strict";
+
+/**
+ * Decides when the node mines, following one of the policies of
+ * `mining.policy` (see MINING_POLICIES in config.js). It owns the only mining
+ * timer and never runs two mining attempts at once; blocks go to
+ * `rewardAddress`, which can be changed while the node runs.
+ */
+class MiningScheduler {
+  /**
+   * @param {Blockchain} blockchain
+   * @param {Object} settings - Mining configuration (`config.mining`)
+   */
+  constructor(blockchain, settings) {
+    this.blockchain = blockchain;
+    this.settings = settings;
+    this.policy = settings.enabled ? settings.policy : 'disabled';
+    this.rewardAddress = settings.rewardAddress;
+    this.timer = null;
+    this.running = null; // Mining attempt in progress
+    this.blocksMined = 0;
+    this.lastBlockAt = null;
+  }
+
+  /**
+   * Starts checking, on the policy's schedule, whether a block should be mined.
+   * @returns {boolean} - False if the policy has no schedule or no reward address is set
+   */
+  start() {
+    if (this.timer) {
+      return true;
+    }
+    if (this.policy === 'disabled' || this.policy === 'onDemand') {
+      console.log(`Mining policy is '${this.policy}': no blocks are mined automatically.`);
+      return false;
+    }
+    if (!this.rewardAddress) {
+      console.log("No mining reward address is configured (mining.rewardAddress): automatic mining is not started.");
+      return false;
+    }
+
+    const seconds = this.policy === 'interval' ? this.settings.intervalSeconds : this.settings.pendingCheckIntervalSeconds;
+    this.timer = setInterval(() => this.tick(), seconds * 1000);
+    console.log(`Automatic mining started with policy '${this.policy}', checking every ${seconds} seconds.`);
+    return true;
+  }
+
+  // Stops automatic mining and abandons the block being mined, if any
+  stop() {
+    if (this.timer) {
+      clearInterval(this.timer);
+      this.timer = null;
+    }
+    this.blockchain.cancelMining('mining was stopped');
+  }
+
+  status() {
+    return {
+      policy: this.policy,
+      active: this.timer !== null,
+      mining: this.running !== null,
+      rewardAddress: this.rewardAddress,
+      pendingTransactions: this.blockchain.pendingTransactions.length,
+      blocksMined: this.blocksMined,
+      lastBlockAt: this.lastBlockAt
+    };
+  }
+
+  setRewardAddress(address) {
+    if (typeof address !== 'string' || address.length < 24 || address.length > 30) {
+      throw new Error("The reward address must be a wallet address of 24 to 30 characters.");
+    }
+    this.rewardAddress = address;
+  }
+
+  /**
+   * Whether the policy calls for a block given the current mempool.
+   * @param {number} [now] - Current time in ms
+   * @returns {boolean}
+   */
+  shouldMine(now = Date.now()) {
+    const pending = this.blockchain.pendingTransactions;
+    if (pending.length === 0) {
+      return false;
+    }
+
+    switch (this.policy) {
+      case 'interval':
+        return true;
+      case 'threshold':
+        return pending.length >= this.settings.minPendingTransactions;
+      case 'latency': {
+        const oldest = Math.min(...pending.map(tx => Number(tx.timestamp)));
+        return now - oldest >= this.settings.maxLatencySeconds * 1000;
+      }
+      default:
+        return false;
+    }
+  }
+
+  async tick() {
+    if (!this.running && this.shouldMine()) {
+      await this.mineNow();
+    }
+  }
+
+  /**
+   * Mines one block from the pending transactions, whatever the policy
+   * (unless mining is disabled). Waits for the attempt in progress instead
+   * of starting a second one.
+   * @returns {Promise<Block|null>} - The mined block, null if none was mined
+   */
+  async mineNow() {
+    if (this.policy === 'disabled') {
+      throw new Error("Mining is disabled by configuration.");
+    }
+    if (!this.rewardAddress) {
+      throw new Error("A mining reward address is required.");
+    }
+
+    if (!this.running) {
+      this.running = this.blockchain.minePendingTransactions(this.rewardAddress)
+        .then(block => {
+          if (block) {
+            this.blocksMined++;
+            this.lastBlockAt = Date.now();
+          }
+          return block || null;
+        })
+        .finally(() => {
+          this.running = null;
+        });
+    }
+    return this.running;
+  }
+}
+
+export { MiningScheduler };
@@ -0,0 +1,54 @@
+"use strict";
+
+// Offsets are only applied once this many peers have reported their clock
+const MIN_SAMPLES = 3;
+
+// Peers can never move our clock by more than this
+const MAX_OFFSET_MS = 5 * 60 * 1000;
+
+/**
+ * Network-adjusted time: the local clock corrected by the median offset
+ * between our clock and the clocks peers report in their handshakes.
+ */
+class NetworkTime {
+  constructor(clock = Date.now) {
+    this.clock = clock;
+    this.offsets = new Map(); // peer -> (peer time - local time) in ms
+  }
+
+  /**
+   * Records the clock a peer reported in its handshake.
+   * @param {*} peer - Any value identifying the connection
+   * @param {number} peerTime - Peer's Date.now() when it sent the handshake
+   */
+  addSample(peer, peerTime) {
+    if (!Number.isFinite(peerTime)) {
+      return;
+    }
+    this.offsets.set(peer, peerTime - this.clock());
+  }
+
+  removePeer(peer) {
+    this.offsets.delete(peer);
+  }
+
+  // Median peer offset in ms, 0 until enough peers have reported
+  getOffset() {
+    if (this.offsets.size < MIN_SAMPLES) {
+      return 0;
+    }
+
+    const sorted = [...this.offsets.values()].sort((a, b) => a - b);
+    const middle = Math.floor(sorted.length / 2);
+    const median = sorted.length % 2 === 1 ? sorted[middle] : Math.round((sorted[middle - 1] + sorted[middle]) / 2);
+    return Math.max(-MAX_OFFSET_MS, Math.min(MAX_OFFSET_MS, median));
+  }
+
+  now() {
+    return this.clock() + this.getOffset();
+  }
+}
+
+const networkTime = new NetworkTime();
+
+export { NetworkTime, networkTime, MIN_SAMPLES, MAX_OFFSET_MS };
@@ -2,11 +2,18 @@ import WebSocket, { WebSocketServer } from 'ws';
 import { Transaction } from './transaction.js';
 import { Block } from './block.js';
 import { blockchainInstance } from './blockchain.js';
+import { config as nodeConfig } from './config.js';
+import { networkTime } from './networkTime.js';
+import { compareChainTips, isValidDifficulty, blockWork } from './difficulty.js';
 
-const P2P_PORT = process.env.P2P_PORT || 6001;
-const peers = process.env.PEERS ? process.env.PEERS.split(',') : [];
 const sockets = [];
 
+// Blocks per BLOCKS message while a peer catches up
+const SYNC_BATCH_SIZE = 500;
+
+// Locator hashes read from a REQUEST_BLOCKS message; a real locator has about 10 + log2(height)
+const MAX_LOCATOR_HASHES = 100;
+
 //let blockchainInstance = null;
 let connectedPeers = [];
 
@@ -16,24 +23,21 @@ function initConnection(ws) {
   blockchainInstance.setConnectedPeers(connectedPeers);
   initMessageHandler(ws);
   initErrorHandler(ws);
-  sendChain(ws); // Send the current chain to the new peer
+  sendHandshake(ws);
+  sendChainTip(ws); // Peers with less work ask for the blocks they miss
 
-  // After connecting, request the genesis block from the peer
-  requestGenesisBlock();
+  // Ask for the peer's genesis block to find out early whether it is on our chain
+  ws.send(JSON.stringify({
+    type: 'REQUEST_GENESIS_BLOCK',
+  }));
 }
 
-let genesisRequestSent = false;
-
-function requestGenesisBlock() {
-    // Only send a request if one hasn't already been sent
-    if (!genesisRequestSent && blockchainInstance.chain.length === 0) {
-      sockets.forEach((socket) => {
-        socket.send(JSON.stringify({
-          type: 'REQUEST_GENESIS_BLOCK',
-        }));
-      });
-      genesisRequestSent = true;  // Mark as sent to prevent multiple requests
-    }
+// Tell the peer our clock so it can derive network-adjusted time
+function sendHandshake(ws) {
+  ws.send(JSON.stringify({
+    type: 'HANDSHAKE',
+    data: { time: Date.now() },
+  }));
 }
 
 function initMessageHandler(ws) {
@@ -42,17 +46,26 @@ function initMessageHandler(ws) {
       const message = JSON.parse(data);
       
       switch (message.type) {
+        case 'HANDSHAKE':
+          networkTime.addSample(ws, Number(message.data && message.data.time));
+          break;
         case 'REQUEST_GENESIS_BLOCK':
           await handleGenesisBlockRequest(ws);
             break;
         case 'GENESIS_BLOCK':
-          await handleReceivedGenesisBlock(message.data);
+          handleReceivedGenesisBlock(message.data, ws);
             break;    
-        case 'CHAIN':
-          await handleReceivedChain(message.data);
+        case 'CHAIN_TIP':
+          handleChainTip(message.data, ws);
+          break;
+        case 'REQUEST_BLOCKS':
+          await sendBlocks(message.data, ws);
+          break;
+        case 'BLOCKS':
+          await handleReceivedBlocks(message.data, ws);
           break;
         case 'NEW_BLOCK':
-          await handleReceivedBlock(message.data);
+          await handleReceivedBlock(message.data, ws);
           break;
         case 'NEW_TRANSACTION':
           await handleReceivedTransaction(message.data);
@@ -69,25 +82,36 @@ function initMessageHandler(ws) {
 function initErrorHandler(ws) {
   const closeConnection = () => {
     console.log('Connection closed');
+    networkTime.removePeer(ws);
     sockets.splice(sockets.indexOf(ws), 1);
   };
   ws.on('close', closeConnection);
   ws.on('error', closeConnection);
 }
 
-function broadcastChain() {
-  const chainData = blockchainInstance.chain.map(block => block.toJSON());
-  sockets.forEach((socket) => socket.send(JSON.stringify({
-    type: 'CHAIN',
-    data: chainData,
-  })));
+function chainTipMessage() {
+  const tip = blockchainInstance.getChainTip();
+  return JSON.stringify({
+    type: 'CHAIN_TIP',
+    data: { height: tip.height, hash: tip.hash, work: tip.work.toString() },
+  });
+}
+
+// Announces our tip to every peer, e.g. after a reorganization
+function broadcastChainTip() {
+  const message = chainTipMessage();
+  sockets.forEach((socket) => socket.send(message));
 }
 
-function sendChain(socket) {
-  const chainData = blockchainInstance.chain.map(block => block.toJSON());
+function sendChainTip(socket) {
+  socket.send(chainTipMessage());
+}
+
+// Asks a peer for the blocks after the last one of `locator` it has on its main chain
+function requestBlocks(socket, locator = blockchainInstance.getBlockLocator()) {
   socket.send(JSON.stringify({
-    type: 'CHAIN',
-    data: chainData,
+    type: 'REQUEST_BLOCKS',
+    data: { locator },
   }));
 }
 
@@ -116,8 +140,8 @@ function broadcastTransaction(transaction, excludeSocket = null) {
 }
 
 async function handleGenesisBlockRequest(ws) {
-    if (blockchainInstance.chain.length > 0) {
-      const genesisBlock = blockchainInstance.chain[0];
+    if (blockchainInstance.getChainLength() > 0) {
+      const genesisBlock = await blockchainInstance.getBlock(0);
       ws.send(JSON.stringify({
         type: 'GENESIS_BLOCK',
         data: genesisBlock.toJSON(),
@@ -127,95 +151,120 @@ async function handleGenesisBlockRequest(ws) {
     }
 }
 
-let processedGenesis = false;
-
-async function handleReceivedGenesisBlock(receivedGenesisBlockData) {
-
-    if (processedGenesis) {
-        
-        return;
+// Every node derives its genesis block from genesis.json, so a peer with another genesis is on another chain
+function handleReceivedGenesisBlock(receivedGenesisBlockData, ws) {
+    if (blockchainInstance.getChainLength() === 0) {
+        return; // Not initialized yet; the chain exchange will reject a foreign chain anyway
     }
 
-    const receivedGenesisBlock = Block.fromJSON(receivedGenesisBlockData);
-
-    // If the local node doesn't have a genesis block, use the received one
-    if (blockchainInstance.chain.length === 0) {
-        
-
-        blockchainInstance.chain.push(receivedGenesisBlock);
-
-        // Check if the block is already in the database before saving
-        const existingBlock = await Block.load(receivedGenesisBlock.hash);
-        if (!existingBlock) {
-            
-            await receivedGenesisBlock.save();
-        }
-
-        processedGenesis = true; // Mark the genesis block as processed
-    } else if (blockchainInstance.chain[0].hash !== receivedGenesisBlock.hash) {
-       
-        throw new Error('Genesis block mismatch');
+    if (blockchainInstance.getHeader(0).hash !== receivedGenesisBlockData.hash) {
+        console.log(`Peer is on another chain (genesis ${receivedGenesisBlockData.hash}). Disconnecting.`);
+        ws.close();
     } else {
         console.log('Genesis block is consistent with the local chain.');
-        processedGenesis = true;
     }
 }
 
-let lastProcessedChainHash = null;
+// Starts catching up with a peer whose tip has more work than ours
+function handleChainTip(tip, ws) {
+    if (!tip || typeof tip.hash !== 'string' || !/^\d+$/.test(String(tip.work))) {
+      return;
+    }
+    if (blockchainInstance.hasBlock(tip.hash) || compareChainTips({ hash: tip.hash, work: BigInt(tip.work) }, blockchainInstance.getChainTip()) <= 0) {
+      return;
+    }
 
+    ws.syncBranch = null;
+    requestBlocks(ws);
+}
 
-async function handleReceivedChain(receivedChain) {
-    try {
+// Answers REQUEST_BLOCKS with the next main-chain blocks, SYNC_BATCH_SIZE at most
+async function sendBlocks(request, ws) {
+    const locator = request && Array.isArray(request.locator) ? request.locator.slice(0, MAX_LOCATOR_HASHES) : [];
+    const from = blockchainInstance.findLocatorFork(locator) + 1;
+    const blocks = await blockchainInstance.getBlocks(from, from + SYNC_BATCH_SIZE - 1);
 
-      if (!receivedChain || receivedChain.length === 0) {
-        
-        return;
-      } 
-      
-      const localChainLength = blockchainInstance.chain.length;
-      const receivedChainLength = receivedChain.length;
+    ws.send(JSON.stringify({
+      type: 'BLOCKS',
+      data: { blocks: blocks.map(block => block.toJSON()), tipHeight: blockchainInstance.getHeight() },
+    }));
+}
 
-      const genesisBlock = receivedChain[0];
-      if (genesisBlock.index !== 0 || genesisBlock.previous_hash !== null) {
-        
-        return;
-      }
-      
-      // First check if the received chain is valid
-      const isValid = await blockchainInstance.constructor.isValidChain(receivedChain);
+// Most blocks collected for a competing branch: one batch past the deepest fork we may switch to, no limit without one
+function maxSyncBranchLength() {
+    const { maxReorgDepth } = blockchainInstance.config.chain;
+    return maxReorgDepth > 0 ? maxReorgDepth + SYNC_BATCH_SIZE : Infinity;
+}
 
-      const lastBlock = receivedChain[receivedChainLength - 1];
-      if (!lastBlock || !lastBlock.hash) {
-        
-        return;
+/**
+ * Catches up with a peer one BLOCKS batch at a time. Blocks extending our
+ * tip are added as they come. Blocks of a competing branch are collected on
+ * the socket, as `{blocks, tip}` with the work of the branch tip added up
+ * block by block, until the branch has more work than our chain, and
+ * `replaceChain` then switches to it. A branch forking off deeper than
+ * `chain.maxReorgDepth`, or growing past `maxSyncBranchLength`, is dropped.
+ * The next batch is requested while the peer has more blocks.
+ */
+async function handleReceivedBlocks(response, ws) {
+    const blocks = response && Array.isArray(response.blocks) ? response.blocks : [];
+    let branch = ws.syncBranch || null;
+    ws.syncBranch = null;
+
+    for (const blockData of blocks) {
+      if (!branch && blockData.previous_hash === blockchainInstance.getLatestHeader().hash) {
+        if (!await blockchainInstance.addBlock(Block.fromJSON(blockData), { broadcast: false })) {
+          console.log(`Stopped syncing: received block ${blockData.index} (${blockData.hash}) is invalid.`);
+          return;
+        }
+        blockchainInstance.cancelMining(`synced block ${blockData.index} (${blockData.hash})`);
+        continue;
       }
 
-      const receivedChainHash = lastBlock.hash;
-
-      if (lastProcessedChainHash === receivedChainHash) {
-        
-        return;
+      if (!branch) {
+        const candidate = blockchainInstance.describeBranch([blockData]);
+        if (!candidate) {
+          return; // Not a branch of our chain
+        }
+        if (candidate.branch.length === 0) {
+          continue; // Already on our chain
+        }
+        if (!blockchainInstance.isReorgAllowed(candidate.forkIndex, blockData.hash)) {
+          return;
+        }
+        branch = { blocks: [blockData], tip: candidate.tip };
+      } else {
+        const previous = branch.blocks[branch.blocks.length - 1];
+        if (blockData.previous_hash !== previous.hash || blockData.index !== previous.index + 1 || !isValidDifficulty(blockData.difficulty)) {
+          console.log(`Stopped syncing: received block ${blockData.index} (${blockData.hash}) does not extend the branch.`);
+          return;
+        }
+        if (branch.blocks.length >= maxSyncBranchLength()) {
+          console.log(`Stopped syncing: the branch ending in ${previous.hash} grew past ${maxSyncBranchLength()} blocks without overtaking our chain.`);
+          return;
+        }
+        branch.blocks.push(blockData);
+        branch.tip = { hash: blockData.hash, work: branch.tip.work + blockWork(blockData.difficulty) };
       }
-      
-      if (receivedChainLength > localChainLength) {
 
-        await blockchainInstance.replaceChain(receivedChain);
-        
-        
-
-        lastProcessedChainHash = receivedChainHash;
-        broadcastChain();
-      } else {
-        
+      if (compareChainTips(branch.tip, blockchainInstance.getChainTip()) > 0) {
+        if (!await blockchainInstance.replaceChain(branch.blocks)) {
+          return;
+        }
+        blockchainInstance.cancelMining(`received a better chain ending in ${branch.tip.hash}`);
+        branch = null;
       }
-    } catch (err) {
-      console.error("Error handling received chain:", err);
     }
-  }
+
+    const last = blocks[blocks.length - 1];
+    if (last && last.index < Number(response.tipHeight)) {
+      ws.syncBranch = branch;
+      requestBlocks(ws, branch ? [last.hash, ...blockchainInstance.getBlockLocator()] : undefined);
+    }
+}
 
 let lastProcessedBlockHash = null;
 
-async function handleReceivedBlock(receivedBlockData) {
+async function handleReceivedBlock(receivedBlockData, senderSocket = null) {
     const newBlock = Block.fromJSON(receivedBlockData);
   
     if (lastProcessedBlockHash === newBlock.hash) {
@@ -228,15 +277,16 @@ async function handleReceivedBlock(receivedBlockData) {
       
       
       // Prevent adding the same block to the in-memory chain multiple times
-      if (blockchainInstance.chain.some(block => block.hash === newBlock.hash)) {
+      if (blockchainInstance.hasBlock(newBlock.hash)) {
 
         lastProcessedBlockHash = newBlock.hash;
         return;
       }
   
-      // If not in the in-memory chain but exists in the database, load it
-      const loadedBlock = await Block.load(newBlock.hash);
-      blockchainInstance.chain.push(loadedBlock);
+      // If not in the in-memory chain but exists in the database and extends the tip, load it
+      if (blockExists.previousHash === blockchainInstance.getLatestHeader().hash) {
+        blockchainInstance.appendBlock(blockExists);
+      }
       
       
       lastProcessedBlockHash = newBlock.hash;
@@ -245,7 +295,7 @@ async function handleReceivedBlock(receivedBlockData) {
   
     const added = await blockchainInstance.addBlock(newBlock);
     if (added) {
-      
+      blockchainInstance.cancelMining(`received block ${newBlock.index} (${newBlock.hash})`);
   
       // Clear pending transactions that were included in the new block
       blockchainInstance.pendingTransactions = blockchainInstance.pendingTransactions.filter(tx => 
@@ -259,11 +309,17 @@ async function handleReceivedBlock(receivedBlockData) {
         blockchainInstance.transactionPool.delete(tx.hash);
       });
   
-      broadcastBlock(newBlock);
+      broadcastBlock(newBlock, senderSocket);
       lastProcessedBlockHash = newBlock.hash;
-    } else {
-      
-      broadcastChain(); // Request the full chain from peers
+    } else if (await blockchainInstance.acceptForkBlock(newBlock)) {
+      // The block won the fork choice against our tip; reorganize already updated pending transactions
+      blockchainInstance.cancelMining(`reorganized onto block ${newBlock.index} (${newBlock.hash})`);
+      broadcastBlock(newBlock, senderSocket);
+      lastProcessedBlockHash = newBlock.hash;
+    } else if (senderSocket && !blockchainInstance.hasBlock(newBlock.previousHash)) {
+      // We are missing the block's ancestors: catch up with the sender
+      senderSocket.syncBranch = null;
+      requestBlocks(senderSocket);
     }
 }
 
@@ -329,9 +385,9 @@ function heartbeat() {
     });
   }
 
-function initP2PServer() {
+function initP2PServer(options = nodeConfig.p2p) {
 
-  const server = new WebSocketServer({ port: P2P_PORT });
+  const server = new WebSocketServer({ port: options.port });
 
   server.on('connection', (ws) => {
     console.log('New peer connected');
@@ -339,10 +395,10 @@ function initP2PServer() {
   });
 
   // Initialize connections to existing peers
-  connectToPeers(peers);
+  connectToPeers(options.peers);
 
   // Heartbeat mechanism to keep connections alive
-  const interval = setInterval(heartbeat, 30000); // Every 30 seconds
+  const interval = setInterval(heartbeat, options.heartbeatIntervalSeconds * 1000);
 
   server.on('close', () => {
     clearInterval(interval);
@@ -352,7 +408,7 @@ function initP2PServer() {
 export {
   initP2PServer,  
   broadcastBlock,
-  broadcastChain,
+  broadcastChainTip,
   broadcastTransaction,
 };
 
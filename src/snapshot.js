@@ -0,0 +1,163 @@
+import fs from 'fs';
+import zlib from 'zlib';
+import readline from 'readline';
+import { Readable } from 'stream';
+import { pipeline } from 'stream/promises';
+import { Block } from './block.js';
+
+const SNAPSHOT_FORMAT = 'aibtcc-chain';
+const SNAPSHOT_VERSION = 1;
+
+/**
+ * Writes a range of the chain to an NDJSON file: one header line followed by
+ * one `Block.toJSON()` record per line. Paths ending in `.gz` are gzipped.
+ * @param {Blockchain} blockchain - Loaded blockchain
+ * @param {string} filePath - Destination file
+ * @param {Object} [options]
+ * @param {number} [options.fromHeight=0] - First block to export
+ * @param {number} [options.toHeight] - Last block to export, defaults to the tip
+ * @returns {Promise<Object>} - The header that was written
+ */
+async function exportChain(blockchain, filePath, { fromHeight = 0, toHeight } = {}) {
+  const tipHeight = blockchain.getHeight();
+  const lastHeight = toHeight === undefined ? tipHeight : toHeight;
+
+  if (!Number.isInteger(fromHeight) || !Number.isInteger(lastHeight) || fromHeight < 0 || lastHeight > tipHeight || fromHeight > lastHeight) {
+    throw new Error(`Invalid height range ${fromHeight}..${lastHeight} (chain tip is ${tipHeight})`);
+  }
+
+  const header = {
+    format: SNAPSHOT_FORMAT,
+    version: SNAPSHOT_VERSION,
+    genesisHash: blockchain.getHeader(0).hash,
+    fromHeight,
+    toHeight: lastHeight,
+    blockCount: lastHeight - fromHeight + 1,
+    createdAt: Date.now()
+  };
+
+  async function * lines() {
+    yield JSON.stringify(header) + '\n';
+    for await (const block of blockchain.iterateBlocks(fromHeight, lastHeight)) {
+      yield JSON.stringify(block.toJSON()) + '\n';
+    }
+  }
+
+  const stages = [Readable.from(lines())];
+  if (filePath.endsWith('.gz')) {
+    stages.push(zlib.createGzip());
+  }
+  stages.push(fs.createWriteStream(filePath));
+  await pipeline(...stages);
+
+  return header;
+}
+
+/**
+ * Reads a snapshot written by `exportChain` and appends its blocks to the
+ * local chain. Blocks the node already has must match; every new block goes
+ * through `Blockchain.addBlock` validation. Gzip is detected automatically.
+ * @param {Blockchain} blockchain - Loaded blockchain
+ * @param {string} filePath - Snapshot file
+ * @param {Object} [options]
+ * @param {Function} [options.onProgress] - Called with { height, imported, skipped } after each block
+ * @returns {Promise<{header: Object, imported: number, skipped: number}>}
+ */
+async function importChain(blockchain, filePath, { onProgress = () => {} } = {}) {
+  let input = fs.createReadStream(filePath);
+  if (await isGzipped(filePath)) {
+    input = input.pipe(zlib.createGunzip());
+  }
+
+  const lines = readline.createInterface({ input, crlfDelay: Infinity });
+  let header = null;
+  let expectedHeight = null;
+  let imported = 0;
+  let skipped = 0;
+
+  for await (const line of lines) {
+    if (line.trim() === '') continue;
+    const record = JSON.parse(line);
+
+    if (!header) {
+      header = checkHeader(record, blockchain);
+      expectedHeight = header.fromHeight;
+      continue;
+    }
+
+    const block = Block.fromJSON(record);
+    if (block.index !== expectedHeight) {
+      throw new Error(`Snapshot is out of order: expected block ${expectedHeight}, found ${block.index}`);
+    }
+    expectedHeight++;
+
+    if (block.index < blockchain.getChainLength()) {
+      if (blockchain.getHeader(block.index).hash !== block.hash) {
+        throw new Error(`Snapshot block ${block.index} conflicts with the local chain`);
+      }
+      skipped++;
+    } else if (block.index === 0) {
+      await importGenesisBlock(blockchain, block);
+      imported++;
+    } else {
+      if (block.index > blockchain.getChainLength()) {
+        throw new Error(`Snapshot starts at block ${header.fromHeight} but the local chain ends at ${blockchain.getHeight()}`);
+      }
+      let added;
+      try {
+        added = await blockchain.addBlock(block);
+      } catch (err) {
+        throw new Error(`Snapshot block ${block.index} (${block.hash}) failed validation: ${err.message}`);
+      }
+      if (!added) {
+        throw new Error(`Snapshot block ${block.index} (${block.hash}) failed validation`);
+      }
+      imported++;
+    }
+    onProgress({ height: block.index, imported, skipped });
+  }
+
+  if (!header) {
+    throw new Error('Snapshot file is empty');
+  }
+  if (expectedHeight !== header.toHeight + 1) {
+    throw new Error(`Snapshot is truncated: expected blocks up to ${header.toHeight}, got up to ${expectedHeight - 1}`);
+  }
+
+  return { header, imported, skipped };
+}
+
+function checkHeader(header, blockchain) {
+  if (header.format !== SNAPSHOT_FORMAT) {
+    throw new Error('Not a chain snapshot file');
+  }
+  if (header.version !== SNAPSHOT_VERSION) {
+    throw new Error(`Unsupported snapshot version ${header.version} (expected ${SNAPSHOT_VERSION})`);
+  }
+  if (blockchain.getChainLength() > 0 && blockchain.getHeader(0).hash !== header.genesisHash) {
+    throw new Error('Snapshot belongs to a chain with a different genesis block');
+  }
+  return header;
+}
+
+// Stores the snapshot's genesis block only if it is the one the node's genesis specification builds
+async function importGenesisBlock(blockchain, block) {
+  const expected = blockchain.loadGenesisBlock();
+  if (block.hash !== expected.hash || block.calculateHash() !== expected.hash) {
+    throw new Error(`Snapshot genesis block ${block.hash} does not match ${blockchain.config.chain.genesisFile} (${expected.hash})`);
+  }
+  await expected.save();
+  blockchain.appendBlock(expected);
+}
+
+async function isGzipped(filePath) {
+  const handle = await fs.promises.open(filePath, 'r');
+  try {
+    const { buffer, bytesRead } = await handle.read(Buffer.alloc(2), 0, 2, 0);
+    return bytesRead === 2 && buffer[0] === 0x1f && buffer[1] === 0x8b;
+  } finally {
+    await handle.close();
+  }
+}
+
+export { exportChain, importChain, SNAPSHOT_FORMAT, SNAPSHOT_VERSION };
@@ -0,0 +1,28 @@
+import { StorageAdapter } from './storageAdapter.js';
+
+/**
+ * Creates the storage backend selected in the configuration.
+ * Backends are imported lazily so a node only loads the driver it uses.
+ * @param {Object} options - { backend: 'mysql' | 'sqlite' | 'memory', ...backend options }
+ * @returns {Promise<StorageAdapter>}
+ */
+async function createStorage(options) {
+  switch (options.backend) {
+    case 'mysql': {
+      const { MySqlStorage } = await import('./mysqlStorage.js');
+      return new MySqlStorage(options);
+    }
+    case 'sqlite': {
+      const { SqliteStorage } = await import('./sqliteStorage.js');
+      return new SqliteStorage(options);
+    }
+    case 'memory': {
+      const { MemoryStorage } = await import('./memoryStorage.js');
+      return new MemoryStorage();
+    }
+    default:
+      throw new Error(`Unknown storage backend: ${options.backend}`);
+  }
+}
+
+export { createStorage, StorageAdapter };
@@ -0,0 +1,294 @@
+import Decimal from 'decimal.js';
+import { StorageAdapter, duplicateEntryError } from './storageAdapter.js';
+import { LATEST_SCHEMA_VERSION } from './migrations/index.js';
+
+/**
+ * Non-persistent backend that keeps every table in process memory.
+ * Intended for tests and throwaway dev nodes; all data is lost on exit.
+ * Transactions snapshot the tables and restore them if the callback fails.
+ */
+class MemoryStorage extends StorageAdapter {
+  constructor() {
+    super('memory');
+    this.inTransaction = false;
+    this.reset();
+  }
+
+  reset() {
+    this.blocks = new Map(); // hash -> row
+    this.blockHashesByIndex = new Map(); // index -> hash
+    this.transactions = new Map(); // hash -> row
+    this.pendingTransactions = new Map(); // hash -> row, in arrival order
+    this.merkleNodes = [];
+    this.merkleProofs = new Map(); // transaction hash -> row
+    this.balances = new Map(); // address -> balance string
+    this.balanceDeltas = new Map(); // `${address}:${block_index}` -> row
+  }
+
+  async withTransaction(fn) {
+    if (this.inTransaction) {
+      return fn(this);
+    }
+
+    return this.runExclusive(async () => {
+      const snapshot = this.snapshot();
+      this.inTransaction = true;
+      try {
+        return await fn(this);
+      } catch (err) {
+        this.restore(snapshot);
+        throw err;
+      } finally {
+        this.inTransaction = false;
+      }
+    });
+  }
+
+  snapshot() {
+    return {
+      blocks: new Map(this.blocks),
+      blockHashesByIndex: new Map(this.blockHashesByIndex),
+      transactions: new Map(this.transactions),
+      pendingTransactions: new Map(this.pendingTransactions),
+      merkleNodes: [...this.merkleNodes],
+      merkleProofs: new Map(this.merkleProofs),
+      balances: new Map(this.balances),
+      balanceDeltas: new Map(this.balanceDeltas)
+    };
+  }
+
+  restore(snapshot) {
+    Object.assign(this, snapshot);
+  }
+
+  // ---- Schema ----
+
+  // In-memory tables are always created with the current layout
+  async getSchemaVersion() {
+    return LATEST_SCHEMA_VERSION;
+  }
+
+  async applyMigration(migration) {}
+
+  // ---- Blocks ----
+
+  async saveBlock(row) {
+    if (this.blocks.has(row.hash)) {
+      throw duplicateEntryError('blocks', row.hash);
+    }
+    this.blocks.set(row.hash, { ...row });
+    this.blockHashesByIndex.set(row.index, row.hash);
+  }
+
+  async getBlockByHash(hash) {
+    return copy(this.blocks.get(hash));
+  }
+
+  async getBlockByIndex(index) {
+    const hash = this.blockHashesByIndex.get(index);
+    return hash ? copy(this.blocks.get(hash)) : null;
+  }
+
+  async getAllBlocks() {
+    return [...this.blocks.values()]
+      .sort((a, b) => a.index - b.index)
+      .map(copy);
+  }
+
+  async getBlocksByHeightRange(fromIndex, toIndex) {
+    const rows = [];
+    for (let index = fromIndex; index <= toIndex; index++) {
+      const hash = this.blockHashesByIndex.get(index);
+      if (hash) rows.push(copy(this.blocks.get(hash)));
+    }
+    return rows;
+  }
+
+  async deleteBlock(hash) {
+    const row = this.blocks.get(hash);
+    if (!row) return;
+    this.blocks.delete(hash);
+    if (this.blockHashesByIndex.get(row.index) === hash) {
+      this.blockHashesByIndex.delete(row.index);
+    }
+  }
+
+  // ---- Confirmed transactions ----
+
+  async saveTransaction(row) {
+    if (this.transactions.has(row.hash)) {
+      throw duplicateEntryError('transactions', row.hash);
+    }
+    this.transactions.set(row.hash, { ...row });
+  }
+
+  async getTransactionByHash(hash) {
+    return copy(this.transactions.get(hash));
+  }
+
+  async getTransactionsByBlockHash(blockHash) {
+    return [...this.transactions.values()]
+      .filter(row => row.block_hash === blockHash)
+      .sort((a, b) => a.index_in_block - b.index_in_block)
+      .map(copy);
+  }
+
+  async getTransactionsByBlockHashes(blockHashes) {
+    const wanted = new Set(blockHashes);
+    return [...this.transactions.values()]
+      .filter(row => wanted.has(row.block_hash))
+      .sort((a, b) => a.block_hash.localeCompare(b.block_hash) || a.index_in_block - b.index_in_block)
+      .map(copy);
+  }
+
+  async getAllTransactions() {
+    return [...this.transactions.values()].map(copy);
+  }
+
+  async deleteTransactionsByBlockHash(blockHash) {
+    for (const [hash, row] of this.transactions) {
+      if (row.block_hash === blockHash) this.transactions.delete(hash);
+    }
+  }
+
+  async getTransactionsByAddress(address, { fromHeight, toHeight, after, descending, limit }) {
+    const sign = descending ? -1 : 1;
+    const position = row => [row.block_index, row.index_in_block];
+    const compare = (a, b) => sign * (a[0] - b[0] || a[1] - b[1]);
+
+    return [...this.transactions.values()]
+      .filter(row => row.from_address === address || row.to_address === address)
+      .map(row => ({ ...row, block_index: this.blocks.get(row.block_hash).index }))
+      .filter(row => row.block_index >= fromHeight && row.block_index <= toHeight)
+      .filter(row => !after || compare(position(row), [after.blockIndex, after.indexInBlock]) > 0)
+      .sort((a, b) => compare(position(a), position(b)))
+      .slice(0, limit);
+  }
+
+  async getLatestTransactionFromAddress(address) {
+    let latest = null;
+    for (const row of this.transactions.values()) {
+      if (row.from_address === address && (!latest || row.timestamp > latest.timestamp)) {
+        latest = row;
+      }
+    }
+    return copy(latest);
+  }
+
+  // ---- Pending transactions ----
+
+  async savePendingTransaction(row) {
+    if (!this.pendingTransactions.has(row.hash)) {
+      this.pendingTransactions.set(row.hash, { ...row });
+    }
+  }
+
+  async getPendingTransactions() {
+    return [...this.pendingTransactions.values()].map(copy);
+  }
+
+  async getPendingTransactionByHash(hash) {
+    return copy(this.pendingTransactions.get(hash));
+  }
+
+  async getPendingTransactionsByAddress(address) {
+    return [...this.pendingTransactions.values()]
+      .filter(row => row.from_address === address || row.to_address === address)
+      .sort((a, b) => a.timestamp - b.timestamp)
+      .map(copy);
+  }
+
+  async countPendingTransactions() {
+    return this.pendingTransactions.size;
+  }
+
+  async deletePendingTransactions(hashes) {
+    hashes.forEach(hash => this.pendingTransactions.delete(hash));
+  }
+
+  async clearPendingTransactions() {
+    this.pendingTransactions.clear();
+  }
+
+  // ---- Merkle data ----
+
+  async saveMerkleNode(row) {
+    this.merkleNodes.push({ ...row });
+  }
+
+  async saveMerkleProof(row) {
+    this.merkleProofs.set(row.transaction_hash, { ...row });
+  }
+
+  async getMerkleProof(transactionHash) {
+    return copy(this.merkleProofs.get(transactionHash));
+  }
+
+  async deleteMerkleData(blockHash) {
+    this.merkleNodes = this.merkleNodes.filter(row => row.block_hash !== blockHash);
+    for (const [hash, row] of this.merkleProofs) {
+      if (row.block_hash === blockHash) this.merkleProofs.delete(hash);
+    }
+  }
+
+  // ---- Balances ----
+
+  async updateBalance(address, delta) {
+    const current = this.balances.get(address) || 0;
+    this.balances.set(address, new Decimal(current).plus(delta).toFixed(8));
+  }
+
+  async getBalance(address) {
+    return this.balances.has(address) ? this.balances.get(address) : null;
+  }
+
+  async getAllBalances() {
+    return [...this.balances].map(([address, balance]) => ({ address, balance }));
+  }
+
+  async saveBalanceDelta(row) {
+    const key = `${row.address}:${row.block_index}`;
+    if (this.balanceDeltas.has(key)) {
+      throw duplicateEntryError('balance_deltas', key);
+    }
+    this.balanceDeltas.set(key, copy(row));
+  }
+
+  async deleteBalanceDeltasByBlockHash(blockHash) {
+    for (const [key, row] of this.balanceDeltas) {
+      if (row.block_hash === blockHash) this.balanceDeltas.delete(key);
+    }
+  }
+
+  async getBalanceDeltaAt(address, blockIndex) {
+    const rows = await this.getBalanceDeltas(address);
+    const row = rows.filter(delta => delta.block_index <= blockIndex).pop();
+    return row || null;
+  }
+
+  async getBalanceDeltas(address) {
+    return [...this.balanceDeltas.values()]
+      .filter(row => row.address === address)
+      .sort((a, b) => a.block_index - b.block_index)
+      .map(copy);
+  }
+
+  // ---- Maintenance ----
+
+  async clearDerivedData() {
+    this.balances = new Map();
+    this.balanceDeltas = new Map();
+    this.merkleNodes = [];
+    this.merkleProofs = new Map();
+  }
+
+  async rebuildIndexes() {
+    this.blockHashesByIndex = new Map([...this.blocks.values()].map(row => [row.index, row.hash]));
+  }
+}
+
+function copy(row) {
+  return row ? { ...row } : null;
+}
+
+export { MemoryStorage };
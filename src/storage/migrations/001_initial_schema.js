@@ -0,0 +1,120 @@
+// Core chain tables: blocks, confirmed and pending transactions, merkle data and balances
+export default {
+  version: 1,
+  description: 'Create core chain tables',
+  mysql: [
+    `CREATE TABLE IF NOT EXISTS blocks (
+      hash CHAR(64) NOT NULL PRIMARY KEY,
+      previous_hash CHAR(64) NULL,
+      timestamp BIGINT NOT NULL,
+      nonce BIGINT NOT NULL DEFAULT 0,
+      difficulty BIGINT NOT NULL DEFAULT 0,
+      merkle_root CHAR(64) NOT NULL,
+      \`index\` INT UNSIGNED NOT NULL,
+      origin_transaction_hash CHAR(64) NULL,
+      UNIQUE KEY uq_blocks_index (\`index\`)
+    ) ENGINE=InnoDB`,
+    `CREATE TABLE IF NOT EXISTS transactions (
+      hash CHAR(64) NOT NULL PRIMARY KEY,
+      from_address VARCHAR(130) NULL,
+      to_address VARCHAR(130) NULL,
+      amount DECIMAL(30, 8) NOT NULL,
+      origin_transaction_hash CHAR(64) NULL,
+      timestamp BIGINT NOT NULL,
+      signature TEXT NULL,
+      block_hash CHAR(64) NOT NULL,
+      public_key VARCHAR(130) NULL,
+      index_in_block INT UNSIGNED NOT NULL,
+      KEY idx_transactions_block_hash (block_hash, index_in_block),
+      KEY idx_transactions_from_address (from_address),
+      KEY idx_transactions_to_address (to_address)
+    ) ENGINE=InnoDB`,
+    `CREATE TABLE IF NOT EXISTS pending_transactions (
+      hash CHAR(64) NOT NULL PRIMARY KEY,
+      from_address VARCHAR(130) NULL,
+      to_address VARCHAR(130) NULL,
+      amount DECIMAL(30, 8) NOT NULL,
+      timestamp BIGINT NOT NULL,
+      signature TEXT NULL,
+      origin_transaction_hash CHAR(64) NULL,
+      public_key VARCHAR(130) NULL,
+      KEY idx_pending_from_address (from_address),
+      KEY idx_pending_to_address (to_address)
+    ) ENGINE=InnoDB`,
+    `CREATE TABLE IF NOT EXISTS merkle_nodes (
+      block_hash CHAR(64) NOT NULL,
+      node_level INT UNSIGNED NOT NULL,
+      node_index INT UNSIGNED NOT NULL,
+      node_value CHAR(64) NOT NULL,
+      PRIMARY KEY (block_hash, node_level, node_index)
+    ) ENGINE=InnoDB`,
+    `CREATE TABLE IF NOT EXISTS merkle_proof_paths (
+      block_hash CHAR(64) NOT NULL,
+      transaction_hash CHAR(64) NOT NULL,
+      proof_path TEXT NOT NULL,
+      PRIMARY KEY (block_hash, transaction_hash),
+      KEY idx_merkle_proof_paths_transaction_hash (transaction_hash)
+    ) ENGINE=InnoDB`,
+    `CREATE TABLE IF NOT EXISTS address_balances (
+      address VARCHAR(130) NOT NULL PRIMARY KEY,
+      balance DECIMAL(30, 8) NOT NULL DEFAULT 0
+    ) ENGINE=InnoDB`
+  ],
+  sqlite: [
+    `CREATE TABLE blocks (
+      hash TEXT NOT NULL PRIMARY KEY,
+      previous_hash TEXT NULL,
+      timestamp INTEGER NOT NULL,
+      nonce INTEGER NOT NULL DEFAULT 0,
+      difficulty INTEGER NOT NULL DEFAULT 0,
+      merkle_root TEXT NOT NULL,
+      \`index\` INTEGER NOT NULL UNIQUE,
+      origin_transaction_hash TEXT NULL
+    )`,
+    `CREATE TABLE transactions (
+      hash TEXT NOT NULL PRIMARY KEY,
+      from_address TEXT NULL,
+      to_address TEXT NULL,
+      amount TEXT NOT NULL,
+      origin_transaction_hash TEXT NULL,
+      timestamp INTEGER NOT NULL,
+      signature TEXT NULL,
+      block_hash TEXT NOT NULL,
+      public_key TEXT NULL,
+      index_in_block INTEGER NOT NULL
+    )`,
+    "CREATE INDEX idx_transactions_block_hash ON transactions (block_hash, index_in_block)",
+    "CREATE INDEX idx_transactions_from_address ON transactions (from_address)",
+    "CREATE INDEX idx_transactions_to_address ON transactions (to_address)",
+    `CREATE TABLE pending_transactions (
+      hash TEXT NOT NULL PRIMARY KEY,
+      from_address TEXT NULL,
+      to_address TEXT NULL,
+      amount TEXT NOT NULL,
+      timestamp INTEGER NOT NULL,
+      signature TEXT NULL,
+      origin_transaction_hash TEXT NULL,
+      public_key TEXT NULL
+    )`,
+    "CREATE INDEX idx_pending_from_address ON pending_transactions (from_address)",
+    "CREATE INDEX idx_pending_to_address ON pending_transactions (to_address)",
+    `CREATE TABLE merkle_nodes (
+      block_hash TEXT NOT NULL,
+      node_level INTEGER NOT NULL,
+      node_index INTEGER NOT NULL,
+      node_value TEXT NOT NULL,
+      PRIMARY KEY (block_hash, node_level, node_index)
+    )`,
+    `CREATE TABLE merkle_proof_paths (
+      block_hash TEXT NOT NULL,
+      transaction_hash TEXT NOT NULL,
+      proof_path TEXT NOT NULL,
+      PRIMARY KEY (block_hash, transaction_hash)
+    )`,
+    "CREATE INDEX idx_merkle_proof_paths_transaction_hash ON merkle_proof_paths (transaction_hash)",
+    `CREATE TABLE address_balances (
+      address TEXT NOT NULL PRIMARY KEY,
+      balance TEXT NOT NULL DEFAULT '0'
+    )`
+  ]
+};
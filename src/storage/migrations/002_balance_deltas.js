@@ -0,0 +1,28 @@
+// Net balance change and resulting balance per address for every block, used for historical balance queries.
+// Existing chains fill the table by running "npm run reindex" after migrating.
+export default {
+  version: 2,
+  description: 'Create balance_deltas table',
+  mysql: [
+    `CREATE TABLE IF NOT EXISTS balance_deltas (
+      address VARCHAR(130) NOT NULL,
+      block_index INT UNSIGNED NOT NULL,
+      block_hash CHAR(64) NOT NULL,
+      delta DECIMAL(30, 8) NOT NULL,
+      balance DECIMAL(30, 8) NOT NULL,
+      PRIMARY KEY (address, block_index),
+      KEY idx_balance_deltas_block_hash (block_hash)
+    ) ENGINE=InnoDB`
+  ],
+  sqlite: [
+    `CREATE TABLE balance_deltas (
+      address TEXT NOT NULL,
+      block_index INTEGER NOT NULL,
+      block_hash TEXT NOT NULL,
+      delta TEXT NOT NULL,
+      balance TEXT NOT NULL,
+      PRIMARY KEY (address, block_index)
+    )`,
+    "CREATE INDEX idx_balance_deltas_block_hash ON balance_deltas (block_hash)"
+  ]
+};
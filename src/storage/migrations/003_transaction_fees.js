@@ -0,0 +1,15 @@
+import { addMysqlColumn } from './mysqlColumns.js';
+
+// Fee paid by the sender of each confirmed and pending transaction. Rows written before fees existed paid none.
+export default {
+  version: 3,
+  description: 'Add fee column to transactions and pending_transactions',
+  mysql: [
+    addMysqlColumn('transactions', 'fee', "DECIMAL(30, 8) NOT NULL DEFAULT 0"),
+    addMysqlColumn('pending_transactions', 'fee', "DECIMAL(30, 8) NOT NULL DEFAULT 0")
+  ],
+  sqlite: [
+    "ALTER TABLE transactions ADD COLUMN fee TEXT NOT NULL DEFAULT '0'",
+    "ALTER TABLE pending_transactions ADD COLUMN fee TEXT NOT NULL DEFAULT '0'"
+  ]
+};
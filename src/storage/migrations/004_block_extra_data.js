@@ -0,0 +1,13 @@
+import { addMysqlColumn } from './mysqlColumns.js';
+
+// Extra data committed to by a block's hash. Genesis blocks built from genesis.json carry the chain ID and spec extra data here.
+export default {
+  version: 4,
+  description: 'Add extra_data column to blocks',
+  mysql: [
+    addMysqlColumn('blocks', 'extra_data', "TEXT NULL")
+  ],
+  sqlite: [
+    "ALTER TABLE blocks ADD COLUMN extra_data TEXT NULL"
+  ]
+};
@@ -0,0 +1,18 @@
+import { addMysqlColumn } from './mysqlColumns.js';
+
+// Chain ID committed to by blocks and transactions, which keeps them from being replayed on another network.
+// Rows written before chain IDs existed have none.
+export default {
+  version: 5,
+  description: 'Add chain_id column to blocks, transactions and pending_transactions',
+  mysql: [
+    addMysqlColumn('blocks', 'chain_id', "VARCHAR(255) NULL"),
+    addMysqlColumn('transactions', 'chain_id', "VARCHAR(255) NULL"),
+    addMysqlColumn('pending_transactions', 'chain_id', "VARCHAR(255) NULL")
+  ],
+  sqlite: [
+    "ALTER TABLE blocks ADD COLUMN chain_id TEXT NULL",
+    "ALTER TABLE transactions ADD COLUMN chain_id TEXT NULL",
+    "ALTER TABLE pending_transactions ADD COLUMN chain_id TEXT NULL"
+  ]
+};
@@ -0,0 +1,18 @@
+import initialSchema from './001_initial_schema.js';
+import balanceDeltas from './002_balance_deltas.js';
+import transactionFees from './003_transaction_fees.js';
+import blockExtraData from './004_block_extra_data.js';
+import chainIds from './005_chain_ids.js';
+
+// Ordered list of schema migrations. Append new migrations with the next version number.
+const migrations = [
+  initialSchema,
+  balanceDeltas,
+  transactionFees,
+  blockExtraData,
+  chainIds
+];
+
+const LATEST_SCHEMA_VERSION = migrations[migrations.length - 1].version;
+
+export { migrations, LATEST_SCHEMA_VERSION };
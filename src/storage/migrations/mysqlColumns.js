@@ -0,0 +1,10 @@
+// MySQL commits DDL as soon as it runs, so a column added by a migration that failed later on is already there
+// when the migration is run again. The check skips the ALTER in that case.
+function addMysqlColumn(table, column, definition) {
+  return {
+    sql: `ALTER TABLE ${table} ADD COLUMN ${column} ${definition}`,
+    check: `SELECT 1 FROM information_schema.COLUMNS WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = '${table}' AND COLUMN_NAME = '${column}'`
+  };
+}
+
+export { addMysqlColumn };
@@ -0,0 +1,39 @@
+import { migrations, LATEST_SCHEMA_VERSION } from './migrations/index.js';
+
+/**
+ * Applies every migration newer than the database's current schema version.
+ * @param {StorageAdapter} storage - Connected storage backend
+ * @param {Function} [log=console.log] - Receives one progress line per migration
+ * @returns {Promise<{from: number, to: number, applied: number[]}>}
+ */
+async function migrate(storage, log = console.log) {
+  const current = await storage.getSchemaVersion();
+  const pending = migrations.filter(migration => migration.version > current);
+
+  for (const migration of pending) {
+    log(`Applying migration ${migration.version}: ${migration.description}`);
+    await storage.applyMigration(migration);
+  }
+
+  return {
+    from: current,
+    to: LATEST_SCHEMA_VERSION,
+    applied: pending.map(migration => migration.version)
+  };
+}
+
+/**
+ * Throws if the database schema is older than the code expects.
+ * @param {StorageAdapter} storage - Connected storage backend
+ */
+async function assertSchemaIsCurrent(storage) {
+  const current = await storage.getSchemaVersion();
+  if (current < LATEST_SCHEMA_VERSION) {
+    throw new Error(`Database schema is at version ${current} but version ${LATEST_SCHEMA_VERSION} is required. Run "npm run migrate" first.`);
+  }
+  if (current > LATEST_SCHEMA_VERSION) {
+    throw new Error(`Database schema version ${current} is newer than this node supports (${LATEST_SCHEMA_VERSION}).`);
+  }
+}
+
+export { migrate, assertSchemaIsCurrent, LATEST_SCHEMA_VERSION };
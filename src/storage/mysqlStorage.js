@@ -0,0 +1,96 @@
+import mysql from 'mysql2/promise';
+import { SqlStorage } from './sqlStorage.js';
+
+class MySqlStorage extends SqlStorage {
+  /**
+   * @param {Object} options - Connection options (host, port, user, password, database, connectionLimit)
+   */
+  constructor(options) {
+    super('mysql');
+    this.options = options;
+    this.pool = null;
+    this.executor = null; // The pool, or a single connection inside a transaction
+  }
+
+  async connect() {
+    this.pool = mysql.createPool({
+      host: this.options.host,
+      port: this.options.port,
+      user: this.options.user,
+      password: this.options.password,
+      database: this.options.database,
+      waitForConnections: true,
+      connectionLimit: this.options.connectionLimit || 10,
+      queueLimit: 0
+    });
+    this.executor = this.pool;
+
+    // Fail fast if the server is unreachable
+    const connection = await this.pool.getConnection();
+    connection.release();
+  }
+
+  async close() {
+    if (this.pool) {
+      await this.pool.end();
+      this.pool = null;
+      this.executor = null;
+    }
+  }
+
+  async withTransaction(fn) {
+    if (this.executor !== this.pool) {
+      return fn(this); // Already bound to a transaction's connection
+    }
+
+    const connection = await this.pool.getConnection();
+    const scoped = Object.create(this);
+    scoped.executor = connection;
+
+    try {
+      await connection.beginTransaction();
+      const result = await fn(scoped);
+      await connection.commit();
+      return result;
+    } catch (err) {
+      await connection.rollback();
+      throw err;
+    } finally {
+      connection.release();
+    }
+  }
+
+  async query(sql, params = []) {
+    const [rows] = await this.executor.query(sql, params);
+    return rows;
+  }
+
+  async execute(sql, params = []) {
+    const [result] = await this.executor.query(sql, params);
+    return result;
+  }
+
+  insertPendingSql() {
+    return `
+      INSERT INTO pending_transactions (hash, from_address, to_address, amount, timestamp, signature, origin_transaction_hash, public_key, fee, chain_id)
+      VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+      ON DUPLICATE KEY UPDATE hash = hash
+    `;
+  }
+
+  async updateBalance(address, delta) {
+    const query = `
+      INSERT INTO address_balances (address, balance)
+      VALUES (?, ?)
+      ON DUPLICATE KEY UPDATE balance = balance + ?
+    `;
+    await this.execute(query, [address, delta, delta]);
+  }
+
+  // OPTIMIZE TABLE recreates InnoDB tables together with their indexes
+  async rebuildIndexes() {
+    await this.query("OPTIMIZE TABLE blocks, transactions, pending_transactions, merkle_nodes, merkle_proof_paths, address_balances, balance_deltas");
+  }
+}
+
+export { MySqlStorage };
@@ -0,0 +1,313 @@
+import { StorageAdapter } from './storageAdapter.js';
+
+/**
+ * Shared implementation for the SQL backends. Subclasses provide `query`
+ * (returns an array of rows), `execute` (for writes) and the few statements
+ * whose syntax differs between dialects.
+ */
+class SqlStorage extends StorageAdapter {
+  async query(sql, params = []) { throw new Error(`${this.constructor.name} does not implement query()`); }
+
+  async execute(sql, params = []) { throw new Error(`${this.constructor.name} does not implement execute()`); }
+
+  async first(sql, params = []) {
+    const rows = await this.query(sql, params);
+    return rows.length > 0 ? rows[0] : null;
+  }
+
+  // ---- Schema ----
+
+  async getSchemaVersion() {
+    await this.execute(`
+      CREATE TABLE IF NOT EXISTS schema_version (
+        version INT NOT NULL PRIMARY KEY,
+        description VARCHAR(255) NOT NULL,
+        applied_at BIGINT NOT NULL
+      )
+    `);
+    await this.execute(`
+      CREATE TABLE IF NOT EXISTS schema_migration_steps (
+        version INT NOT NULL,
+        step INT NOT NULL,
+        PRIMARY KEY (version, step)
+      )
+    `);
+    const row = await this.first("SELECT MAX(version) AS version FROM schema_version");
+    return row && row.version !== null ? Number(row.version) : 0;
+  }
+
+  /**
+   * Runs a migration and records its version in one transaction. MySQL
+   * commits DDL immediately, so every statement is also recorded in
+   * schema_migration_steps as it completes: a migration that failed halfway
+   * resumes after its last completed statement. Statements may be given as
+   * { sql, check }, where `check` is a query returning a row when the
+   * statement's change is already in place.
+   */
+  async applyMigration(migration) {
+    const statements = migration[this.name];
+    if (!statements) {
+      throw new Error(`Migration ${migration.version} has no statements for ${this.name}`);
+    }
+
+    await this.withTransaction(async store => {
+      const completed = new Set(
+        (await store.query("SELECT step FROM schema_migration_steps WHERE version = ?", [migration.version])).map(row => Number(row.step))
+      );
+
+      for (const [step, statement] of statements.entries()) {
+        if (completed.has(step)) {
+          continue;
+        }
+        const { sql, check } = typeof statement === 'string' ? { sql: statement, check: null } : statement;
+        if (!check || !await store.first(check)) {
+          await store.execute(sql);
+        }
+        await store.execute("INSERT INTO schema_migration_steps (version, step) VALUES (?, ?)", [migration.version, step]);
+      }
+
+      await store.execute(
+        "INSERT INTO schema_version (version, description, applied_at) VALUES (?, ?, ?)",
+        [migration.version, migration.description, Date.now()]
+      );
+      await store.execute("DELETE FROM schema_migration_steps WHERE version = ?", [migration.version]);
+    });
+  }
+
+  // ---- Blocks ----
+
+  async saveBlock(row) {
+    const query = "INSERT INTO blocks (hash, previous_hash, timestamp, nonce, difficulty, merkle_root, `index`, origin_transaction_hash, extra_data, chain_id) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
+    await this.execute(query, [
+      row.hash,
+      row.previous_hash,
+      row.timestamp,
+      row.nonce,
+      row.difficulty,
+      row.merkle_root,
+      row.index,
+      row.origin_transaction_hash,
+      row.extra_data,
+      row.chain_id
+    ]);
+  }
+
+  async getBlockByHash(hash) {
+    return this.first("SELECT * FROM blocks WHERE hash = ?", [hash]);
+  }
+
+  async getBlockByIndex(index) {
+    return this.first("SELECT * FROM blocks WHERE `index` = ?", [index]);
+  }
+
+  async getAllBlocks() {
+    return this.query("SELECT * FROM blocks ORDER BY `index` ASC");
+  }
+
+  async getBlocksByHeightRange(fromIndex, toIndex) {
+    return this.query("SELECT * FROM blocks WHERE `index` BETWEEN ? AND ? ORDER BY `index` ASC", [fromIndex, toIndex]);
+  }
+
+  async deleteBlock(hash) {
+    await this.execute("DELETE FROM blocks WHERE hash = ?", [hash]);
+  }
+
+  // ---- Confirmed transactions ----
+
+  async saveTransaction(row) {
+    const query = "INSERT INTO transactions (hash, from_address, to_address, amount, origin_transaction_hash, timestamp, signature, block_hash, public_key, index_in_block, fee, chain_id) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
+    await this.execute(query, [
+      row.hash,
+      row.from_address,
+      row.to_address,
+      row.amount,
+      row.origin_transaction_hash,
+      row.timestamp,
+      row.signature,
+      row.block_hash,
+      row.public_key,
+      row.index_in_block,
+      row.fee,
+      row.chain_id
+    ]);
+  }
+
+  async getTransactionByHash(hash) {
+    return this.first("SELECT * FROM transactions WHERE hash = ?", [hash]);
+  }
+
+  async getTransactionsByBlockHash(blockHash) {
+    return this.query("SELECT * FROM transactions WHERE block_hash = ? ORDER BY index_in_block ASC", [blockHash]);
+  }
+
+  async getTransactionsByBlockHashes(blockHashes) {
+    if (blockHashes.length === 0) return [];
+    const placeholders = blockHashes.map(() => '?').join(', ');
+    return this.query(
+      `SELECT * FROM transactions WHERE block_hash IN (${placeholders}) ORDER BY block_hash, index_in_block ASC`,
+      blockHashes
+    );
+  }
+
+  async getAllTransactions() {
+    return this.query("SELECT * FROM transactions");
+  }
+
+  async deleteTransactionsByBlockHash(blockHash) {
+    await this.execute("DELETE FROM transactions WHERE block_hash = ?", [blockHash]);
+  }
+
+  // One indexed lookup per address column; UNION also folds transactions sent to oneself into one row.
+  // Each side is filtered, sorted and limited on its own, so a page reads at most 2 * limit rows.
+  async getTransactionsByAddress(address, { fromHeight, toHeight, after, descending, limit }) {
+    const order = descending ? 'DESC' : 'ASC';
+    let cursorCondition = '';
+    const cursorParams = [];
+
+    if (after) {
+      const comparison = descending ? '<' : '>';
+      cursorCondition = `AND (b.\`index\` ${comparison} ? OR (b.\`index\` = ? AND t.index_in_block ${comparison} ?))`;
+      cursorParams.push(after.blockIndex, after.blockIndex, after.indexInBlock);
+    }
+
+    const side = column => `
+      SELECT * FROM (
+        SELECT t.*, b.\`index\` AS block_index FROM transactions t JOIN blocks b ON b.hash = t.block_hash
+        WHERE t.${column} = ? AND b.\`index\` BETWEEN ? AND ? ${cursorCondition}
+        ORDER BY b.\`index\` ${order}, t.index_in_block ${order}
+        LIMIT ?
+      ) ${column === 'from_address' ? 'sent' : 'received'}`;
+    const sideParams = [address, fromHeight, toHeight, ...cursorParams, limit];
+
+    return this.query(`
+      SELECT * FROM (${side('from_address')} UNION ${side('to_address')}) history
+      ORDER BY block_index ${order}, index_in_block ${order}
+      LIMIT ?
+    `, [...sideParams, ...sideParams, limit]);
+  }
+
+  async getLatestTransactionFromAddress(address) {
+    return this.first("SELECT * FROM transactions WHERE from_address = ? ORDER BY timestamp DESC LIMIT 1", [address]);
+  }
+
+  // ---- Pending transactions ----
+
+  async savePendingTransaction(row) {
+    await this.execute(this.insertPendingSql(), [
+      row.hash,
+      row.from_address,
+      row.to_address,
+      row.amount,
+      row.timestamp,
+      row.signature,
+      row.origin_transaction_hash,
+      row.public_key,
+      row.fee,
+      row.chain_id
+    ]);
+  }
+
+  // Dialect hook: insert into pending_transactions, ignoring duplicates
+  insertPendingSql() {
+    throw new Error(`${this.constructor.name} does not implement insertPendingSql()`);
+  }
+
+  async getPendingTransactions() {
+    return this.query("SELECT * FROM pending_transactions");
+  }
+
+  async getPendingTransactionByHash(hash) {
+    return this.first("SELECT * FROM pending_transactions WHERE hash = ?", [hash]);
+  }
+
+  async getPendingTransactionsByAddress(address) {
+    return this.query(`
+      SELECT * FROM (
+        SELECT * FROM pending_transactions WHERE from_address = ?
+        UNION
+        SELECT * FROM pending_transactions WHERE to_address = ?
+      ) pending
+      ORDER BY timestamp ASC
+    `, [address, address]);
+  }
+
+  async countPendingTransactions() {
+    const row = await this.first("SELECT COUNT(*) AS count FROM pending_transactions");
+    return Number(row.count);
+  }
+
+  async deletePendingTransactions(hashes) {
+    if (hashes.length === 0) return;
+    const placeholders = hashes.map(() => '?').join(', ');
+    await this.execute(`DELETE FROM pending_transactions WHERE hash IN (${placeholders})`, hashes);
+  }
+
+  async clearPendingTransactions() {
+    await this.execute("DELETE FROM pending_transactions");
+  }
+
+  // ---- Merkle data ----
+
+  async saveMerkleNode(row) {
+    const query = "INSERT INTO merkle_nodes (block_hash, node_level, node_index, node_value) VALUES (?, ?, ?, ?)";
+    await this.execute(query, [row.block_hash, row.node_level, row.node_index, row.node_value]);
+  }
+
+  async saveMerkleProof(row) {
+    const query = "INSERT INTO merkle_proof_paths (block_hash, transaction_hash, proof_path) VALUES (?, ?, ?)";
+    await this.execute(query, [row.block_hash, row.transaction_hash, row.proof_path]);
+  }
+
+  async getMerkleProof(transactionHash) {
+    return this.first("SELECT * FROM merkle_proof_paths WHERE transaction_hash = ?", [transactionHash]);
+  }
+
+  async deleteMerkleData(blockHash) {
+    await this.execute("DELETE FROM merkle_nodes WHERE block_hash = ?", [blockHash]);
+    await this.execute("DELETE FROM merkle_proof_paths WHERE block_hash = ?", [blockHash]);
+  }
+
+  // ---- Balances ----
+
+  async getBalance(address) {
+    const row = await this.first("SELECT balance FROM address_balances WHERE address = ?", [address]);
+    return row ? String(row.balance) : null;
+  }
+
+  async getAllBalances() {
+    return this.query("SELECT address, balance FROM address_balances");
+  }
+
+  async saveBalanceDelta(row) {
+    await this.execute(
+      "INSERT INTO balance_deltas (address, block_index, block_hash, delta, balance) VALUES (?, ?, ?, ?, ?)",
+      [row.address, row.block_index, row.block_hash, row.delta, row.balance]
+    );
+  }
+
+  async deleteBalanceDeltasByBlockHash(blockHash) {
+    await this.execute("DELETE FROM balance_deltas WHERE block_hash = ?", [blockHash]);
+  }
+
+  async getBalanceDeltaAt(address, blockIndex) {
+    return this.first(
+      "SELECT * FROM balance_deltas WHERE address = ? AND block_index <= ? ORDER BY block_index DESC LIMIT 1",
+      [address, blockIndex]
+    );
+  }
+
+  async getBalanceDeltas(address) {
+    return this.query("SELECT * FROM balance_deltas WHERE address = ? ORDER BY block_index ASC", [address]);
+  }
+
+  // ---- Maintenance ----
+
+  async clearDerivedData() {
+    await this.execute("DELETE FROM address_balances");
+    await this.execute("DELETE FROM balance_deltas");
+    await this.execute("DELETE FROM merkle_nodes");
+    await this.execute("DELETE FROM merkle_proof_paths");
+  }
+}
+
+export { SqlStorage };
@@ -0,0 +1,107 @@
+import Database from 'better-sqlite3';
+import Decimal from 'decimal.js';
+import { SqlStorage } from './sqlStorage.js';
+import { duplicateEntryError } from './storageAdapter.js';
+
+/**
+ * Embedded backend for development nodes and tests. better-sqlite3 is
+ * synchronous, so every call completes before the returned promise resolves.
+ * There is a single connection, so transactions are serialized and writes
+ * made outside a transaction wait until the open one has finished.
+ */
+class SqliteStorage extends SqlStorage {
+  /**
+   * @param {Object} options - { filename } path of the database file, or ':memory:'
+   */
+  constructor(options) {
+    super('sqlite');
+    this.options = options;
+    this.db = null;
+    this.inTransaction = false;
+  }
+
+  async connect() {
+    this.db = new Database(this.options.filename);
+    this.db.pragma('journal_mode = WAL');
+    this.db.pragma('foreign_keys = ON');
+  }
+
+  async close() {
+    if (this.db) {
+      this.db.close();
+      this.db = null;
+    }
+  }
+
+  async withTransaction(fn) {
+    if (this.inTransaction) {
+      return fn(this);
+    }
+
+    return this.runExclusive(async () => {
+      const scoped = Object.create(this);
+      scoped.inTransaction = true;
+
+      this.db.exec('BEGIN IMMEDIATE');
+      try {
+        const result = await fn(scoped);
+        this.db.exec('COMMIT');
+        return result;
+      } catch (err) {
+        this.db.exec('ROLLBACK');
+        throw err;
+      }
+    });
+  }
+
+  async query(sql, params = []) {
+    return this.db.prepare(sql).all(params);
+  }
+
+  async execute(sql, params = []) {
+    if (!this.inTransaction) {
+      return this.runExclusive(() => this.run(sql, params));
+    }
+    return this.run(sql, params);
+  }
+
+  run(sql, params) {
+    try {
+      return this.db.prepare(sql).run(params);
+    } catch (err) {
+      if (err.code === 'SQLITE_CONSTRAINT_PRIMARYKEY' || err.code === 'SQLITE_CONSTRAINT_UNIQUE') {
+        throw duplicateEntryError(tableName(sql), params[0]);
+      }
+      throw err;
+    }
+  }
+
+  insertPendingSql() {
+    return `
+      INSERT OR IGNORE INTO pending_transactions (hash, from_address, to_address, amount, timestamp, signature, origin_transaction_hash, public_key, fee, chain_id)
+      VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+    `;
+  }
+
+  // Balances are kept as TEXT and summed with Decimal to avoid floating point drift
+  async updateBalance(address, delta) {
+    const current = await this.getBalance(address);
+    const balance = new Decimal(current || 0).plus(delta).toFixed(8);
+    await this.execute(
+      "INSERT INTO address_balances (address, balance) VALUES (?, ?) ON CONFLICT(address) DO UPDATE SET balance = excluded.balance",
+      [address, balance]
+    );
+  }
+
+  async rebuildIndexes() {
+    this.db.exec('REINDEX');
+    this.db.exec('ANALYZE');
+  }
+}
+
+function tableName(sql) {
+  const match = /INTO\s+(\w+)/i.exec(sql);
+  return match ? match[1] : 'table';
+}
+
+export { SqliteStorage };
@@ -0,0 +1,218 @@
+/**
+ * Base class for the node's persistence layer.
+ *
+ * Every backend (MySQL, SQLite, in-memory) implements the same set of
+ * operations. Rows are plain objects using the snake_case column names of the
+ * SQL schema, so `Block`, `Transaction` and `MerkleTree` can map them without
+ * knowing which backend produced them.
+ */
+class StorageAdapter {
+  constructor(name) {
+    this.name = name;
+  }
+
+  /**
+   * Opens the underlying connection(s). Called once at startup.
+   * @returns {Promise<void>}
+   */
+  async connect() {}
+
+  /**
+   * Releases the underlying connection(s).
+   * @returns {Promise<void>}
+   */
+  async close() {}
+
+  /**
+   * Runs `fn` with a storage handle whose writes are committed together.
+   * If `fn` throws, every write made through the handle is rolled back and
+   * the error is rethrown. Calling withTransaction on a handle that is already
+   * inside a transaction simply reuses it.
+   * @param {Function} fn - async (store) => result
+   * @returns {Promise<*>} - Whatever `fn` returns
+   */
+  async withTransaction(fn) { notImplemented(this, 'withTransaction'); }
+
+  /**
+   * Serializes `fn` with every other exclusive section of this adapter.
+   * Used by single-connection backends to keep transactions from interleaving.
+   */
+  async runExclusive(fn) {
+    const previous = this.exclusiveTail || Promise.resolve();
+    let release;
+    this.exclusiveTail = new Promise(resolve => { release = resolve; });
+    try {
+      await previous;
+      return await fn();
+    } finally {
+      release();
+    }
+  }
+
+  // ---- Schema ----
+
+  /**
+   * @returns {Promise<number>} - Highest applied migration version, 0 for an empty database
+   */
+  async getSchemaVersion() { notImplemented(this, 'getSchemaVersion'); }
+
+  /**
+   * Runs one migration and records its version in schema_version, as a whole or not at all.
+   * @param {Object} migration - { version, description, mysql: Statement[], sqlite: Statement[] },
+   *   each statement a SQL string or { sql, check } (see SqlStorage.applyMigration)
+   */
+  async applyMigration(migration) { notImplemented(this, 'applyMigration'); }
+
+  // ---- Blocks ----
+
+  /**
+   * @param {Object} row - Block row (hash, previous_hash, timestamp, nonce, difficulty, merkle_root, index, origin_transaction_hash, extra_data, chain_id)
+   */
+  async saveBlock(row) { notImplemented(this, 'saveBlock'); }
+
+  /** @returns {Promise<Object|null>} */
+  async getBlockByHash(hash) { notImplemented(this, 'getBlockByHash'); }
+
+  /** @returns {Promise<Object|null>} */
+  async getBlockByIndex(index) { notImplemented(this, 'getBlockByIndex'); }
+
+  /** @returns {Promise<Object[]>} - All block rows ordered by index */
+  async getAllBlocks() { notImplemented(this, 'getAllBlocks'); }
+
+  /** @returns {Promise<Object[]>} - Block rows with fromIndex <= index <= toIndex, ordered by index */
+  async getBlocksByHeightRange(fromIndex, toIndex) { notImplemented(this, 'getBlocksByHeightRange'); }
+
+  async deleteBlock(hash) { notImplemented(this, 'deleteBlock'); }
+
+  // ---- Confirmed transactions ----
+
+  /**
+   * @param {Object} row - Transaction row (hash, from_address, to_address, amount, origin_transaction_hash, timestamp, signature, block_hash, public_key, index_in_block, fee, chain_id)
+   */
+  async saveTransaction(row) { notImplemented(this, 'saveTransaction'); }
+
+  /** @returns {Promise<Object|null>} */
+  async getTransactionByHash(hash) { notImplemented(this, 'getTransactionByHash'); }
+
+  /** @returns {Promise<Object[]>} - Transaction rows of a block ordered by index_in_block */
+  async getTransactionsByBlockHash(blockHash) { notImplemented(this, 'getTransactionsByBlockHash'); }
+
+  /** @returns {Promise<Object[]>} - Transactions of all the given blocks, ordered by index_in_block within each block */
+  async getTransactionsByBlockHashes(blockHashes) { notImplemented(this, 'getTransactionsByBlockHashes'); }
+
+  /** @returns {Promise<Object[]>} */
+  async getAllTransactions() { notImplemented(this, 'getAllTransactions'); }
+
+  async deleteTransactionsByBlockHash(blockHash) { notImplemented(this, 'deleteTransactionsByBlockHash'); }
+
+  /**
+   * Confirmed transactions sent or received by `address`, each with the
+   * height of its block as `block_index`, ordered by (block_index, index_in_block).
+   * @param {string} address
+   * @param {Object} options
+   * @param {number} options.fromHeight - Lowest block height, inclusive
+   * @param {number} options.toHeight - Highest block height, inclusive
+   * @param {Object|null} options.after - Position ({ blockIndex, indexInBlock }) to continue after, in the requested order
+   * @param {boolean} options.descending - Newest first
+   * @param {number} options.limit - Maximum number of rows
+   * @returns {Promise<Object[]>}
+   */
+  async getTransactionsByAddress(address, options) { notImplemented(this, 'getTransactionsByAddress'); }
+
+  /** @returns {Promise<Object|null>} - Most recent transaction sent by the address */
+  async getLatestTransactionFromAddress(address) { notImplemented(this, 'getLatestTransactionFromAddress'); }
+
+  // ---- Pending transactions ----
+
+  /**
+   * Stores a pending transaction. Storing the same hash twice is a no-op.
+   * @param {Object} row - Pending transaction row
+   */
+  async savePendingTransaction(row) { notImplemented(this, 'savePendingTransaction'); }
+
+  /** @returns {Promise<Object[]>} */
+  async getPendingTransactions() { notImplemented(this, 'getPendingTransactions'); }
+
+  /** @returns {Promise<Object|null>} */
+  async getPendingTransactionByHash(hash) { notImplemented(this, 'getPendingTransactionByHash'); }
+
+  /** @returns {Promise<Object[]>} - Pending transactions sent or received by the address, oldest first */
+  async getPendingTransactionsByAddress(address) { notImplemented(this, 'getPendingTransactionsByAddress'); }
+
+  /** @returns {Promise<number>} */
+  async countPendingTransactions() { notImplemented(this, 'countPendingTransactions'); }
+
+  /** @param {string[]} hashes */
+  async deletePendingTransactions(hashes) { notImplemented(this, 'deletePendingTransactions'); }
+
+  async clearPendingTransactions() { notImplemented(this, 'clearPendingTransactions'); }
+
+  // ---- Merkle data ----
+
+  /**
+   * @param {Object} row - Merkle node row (block_hash, node_level, node_index, node_value)
+   */
+  async saveMerkleNode(row) { notImplemented(this, 'saveMerkleNode'); }
+
+  /**
+   * @param {Object} row - Proof row (block_hash, transaction_hash, proof_path as a JSON string)
+   */
+  async saveMerkleProof(row) { notImplemented(this, 'saveMerkleProof'); }
+
+  /** @returns {Promise<Object|null>} */
+  async getMerkleProof(transactionHash) { notImplemented(this, 'getMerkleProof'); }
+
+  /** Removes the Merkle nodes and proof paths stored for a block */
+  async deleteMerkleData(blockHash) { notImplemented(this, 'deleteMerkleData'); }
+
+  // ---- Balances ----
+
+  /**
+   * Adds `delta` (which may be negative) to the balance of `address`,
+   * creating the row if it does not exist yet.
+   */
+  async updateBalance(address, delta) { notImplemented(this, 'updateBalance'); }
+
+  /** @returns {Promise<string|null>} - Stored balance, or null for unknown addresses */
+  async getBalance(address) { notImplemented(this, 'getBalance'); }
+
+  /** @returns {Promise<Object[]>} - Every address_balances row (address, balance) */
+  async getAllBalances() { notImplemented(this, 'getAllBalances'); }
+
+  /**
+   * @param {Object} row - Balance change of one address in one block (address, block_index, block_hash, delta, balance after the block)
+   */
+  async saveBalanceDelta(row) { notImplemented(this, 'saveBalanceDelta'); }
+
+  async deleteBalanceDeltasByBlockHash(blockHash) { notImplemented(this, 'deleteBalanceDeltasByBlockHash'); }
+
+  /** @returns {Promise<Object|null>} - The address's last balance_deltas row at or below `blockIndex` */
+  async getBalanceDeltaAt(address, blockIndex) { notImplemented(this, 'getBalanceDeltaAt'); }
+
+  /** @returns {Promise<Object[]>} - Every balance_deltas row of the address ordered by block_index */
+  async getBalanceDeltas(address) { notImplemented(this, 'getBalanceDeltas'); }
+
+  // ---- Maintenance ----
+
+  /**
+   * Deletes everything that can be recomputed from blocks and transactions:
+   * address balances, balance deltas, Merkle nodes and Merkle proof paths.
+   */
+  async clearDerivedData() { notImplemented(this, 'clearDerivedData'); }
+
+  /** Rebuilds the backend's indexes after bulk changes */
+  async rebuildIndexes() { notImplemented(this, 'rebuildIndexes'); }
+}
+
+function notImplemented(adapter, method) {
+  throw new Error(`${adapter.constructor.name} does not implement ${method}()`);
+}
+
+// Mirrors the MySQL error code so callers can detect duplicates the same way on every backend
+function duplicateEntryError(table, key) {
+  const err = new Error(`Duplicate entry '${key}' in ${table}`);
+  err.code = 'ER_DUP_ENTRY';
+  return err;
+}
+
+export { StorageAdapter, duplicateEntryError };
@@ -3,18 +3,15 @@
 import crypto from 'crypto'; // Required for creating cryptographic hashes
 import elliptic from 'elliptic';
 const { ec: EC } = elliptic;
-import { db } from './db.js'; // Database module for interacting with the database
+import { storage } from './db.js'; // Storage backend for persisting transactions
 import {MerkleTree, MerkleProofPath} from './merkleTree.js'; // Importing MerkleTree and Node classes
 
-import { createNewWallet, loadWallet } from './wallet.js';
+import { createNewWallet, loadWallet, addressFromPublicKey } from './wallet.js';
 
-import util from 'util';
 import Decimal from'decimal.js';
 
 const ec = new EC("secp256k1"); // Initialize the elliptic curve for cryptography
 
-const queryAsync = util.promisify(db.query).bind(db);
-
 class Transaction {
   constructor(
     fromAddress,
@@ -25,7 +22,9 @@ class Transaction {
     blockHash = "",
     originTransactionHash = null,
     publicKey = "",
-    index_in_block = null
+    index_in_block = null,
+    fee = 0,
+    chainId = null
   ) {
     this.fromAddress = fromAddress; // Address sending the funds
     this.toAddress = toAddress; // Address receiving the funds
@@ -35,6 +34,8 @@ class Transaction {
     this.blockHash = blockHash; // Hash of the block this transaction is included in (if any)
     this.originTransactionHash = originTransactionHash;
     this.publicKey = publicKey; 
+    this.fee = fee; // Paid by the sender on top of the amount, collected by the miner
+    this.chainId = chainId; // Network the transaction was made for, so it cannot be replayed on another one
     this.hash = this.calculateHash(); // Calculate the transaction hash
     this.index_in_block = index_in_block;
   }
@@ -50,7 +51,9 @@ class Transaction {
       originTransactionHash: this.originTransactionHash,
       publicKey: this.publicKey,
       hash: this.hash,
-      index_in_block: this.index_in_block 
+      index_in_block: this.index_in_block,
+      fee: new Decimal(this.fee).toFixed(8),
+      chainId: this.chainId
     };
   }
   
@@ -64,28 +67,81 @@ class Transaction {
       data.blockHash,
       data.originTransactionHash,
       data.publicKey,
-      data.index_in_block
+      data.index_in_block,
+      data.fee !== undefined ? data.fee : 0,
+      data.chainId !== undefined ? data.chainId : null
     );
     tx.hash = data.hash;
     return tx;
   }
 
+  // Build a transaction from a storage row (snake_case column names)
+  static fromRow(row) {
+    const tx = new Transaction(
+      row.from_address,
+      row.to_address,
+      new Decimal(row.amount).toFixed(8),
+      Number(row.timestamp),
+      row.signature,
+      row.block_hash !== undefined ? row.block_hash : null, // pending rows have no block
+      row.origin_transaction_hash,
+      row.public_key,
+      row.index_in_block !== undefined ? row.index_in_block : null,
+      row.fee !== undefined && row.fee !== null ? new Decimal(row.fee).toFixed(8) : 0,
+      row.chain_id !== undefined ? row.chain_id : null
+    );
+    tx.hash = row.hash;
+    return tx;
+  }
+
+  // Storage row for the transactions table
+  toRow() {
+    return {
+      hash: this.hash,
+      from_address: this.fromAddress,
+      to_address: this.toAddress,
+      amount: this.amount,
+      origin_transaction_hash: this.originTransactionHash,
+      timestamp: this.timestamp,
+      signature: this.signature,
+      block_hash: this.blockHash,
+      public_key: this.publicKey,
+      index_in_block: this.index_in_block,
+      fee: new Decimal(this.fee).toFixed(8),
+      chain_id: this.chainId
+    };
+  }
+
   // Calculate the hash of the transaction
   calculateHash() {
     const amountStr = new Decimal(this.amount).toFixed(8); // Ensure consistent decimal formatting
     const originHashStr = this.originTransactionHash || ''; // Use empty string if null
+    const fee = new Decimal(this.fee || 0);
+
+    // Transactions without a fee or chain ID keep the concatenated preimage they were always hashed with.
+    // Every other one hashes a JSON array, whose delimiters keep adjacent fields (timestamp and fee) apart.
+    const preimage = fee.isZero() && !this.chainId
+      ? this.fromAddress + this.toAddress + amountStr + originHashStr + this.timestamp
+      : JSON.stringify([this.fromAddress, this.toAddress, amountStr, originHashStr, this.timestamp, fee.toFixed(8), this.chainId || null]);
+
     return crypto
       .createHash("sha256")
-      .update(
-        this.fromAddress +
-          this.toAddress +
-          amountStr +
-          originHashStr +
-          this.timestamp
-      )
+      .update(preimage)
       .digest("hex");
   }
 
+  // Serialized size in bytes, the unit of fee rates and block size limits.
+  // The block position fields are left out so the size does not change once the transaction is mined.
+  getSize() {
+    const { blockHash, index_in_block, ...content } = this.toJSON();
+    return Buffer.byteLength(JSON.stringify(content));
+  }
+
+  // Fee paid per byte of serialized transaction
+  getFeeRate() {
+    return new Decimal(this.fee || 0).dividedBy(this.getSize());
+  }
+
   async signWithAddress(address) {
     try {
       const wallet = loadWallet(address);
@@ -104,15 +160,28 @@ class Transaction {
     }
   }
   
-  // Validate the transaction
-  isValid() {
+  // Validate the transaction. When `chainId` is given, the transaction must also have been made for that chain.
+  isValid(chainId) {
     const hashToVerify = this.calculateHash();
+
+    if (chainId !== undefined && (this.chainId || null) !== chainId) {
+      throw new Error(`Transaction was made for chain ${this.chainId || '(none)'}, not ${chainId || '(none)'}!`);
+    }
   
     if (this.fromAddress === null) return true; // Mining rewards
+
+    if (!isValidFee(this.fee)) {
+      throw new Error("Transaction fee must be a non-negative amount with at most 8 decimals!");
+    }
   
     if (!this.signature || this.signature.length === 0) {
       throw new Error("Transaction signature is missing or invalid!");
     }
+
+    // The signing key must be the sender's, or anyone could sign for any address
+    if (this.publicKey && addressFromPublicKey(this.publicKey) !== this.fromAddress) {
+      throw new Error("Transaction public key does not belong to the sender address!");
+    }
   
     try {
       // Check if publicKey is present
@@ -128,27 +197,12 @@ class Transaction {
     }
   }
 
-  // Save the transaction to the database
-  async save() {
-    this.isValid();
-  
-    const query = "INSERT INTO transactions (hash, from_address, to_address, amount, origin_transaction_hash, timestamp, signature, block_hash, public_key, index_in_block) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
-    const values = [
-      this.hash,
-      this.fromAddress,
-      this.toAddress,
-      this.amount,
-      this.originTransactionHash,
-      this.timestamp,
-      this.signature,
-      this.blockHash,
-      this.publicKey,
-      this.index_in_block 
-    ];
+  // Save the transaction to the database. When `chainId` is given, the transaction must have been made for that chain.
+  async save(store = storage, chainId) {
+    this.isValid(chainId);
   
     try {
-      const [results] = await db.query(query, values);
-      return results;
+      await store.saveTransaction(this.toRow());
     } catch (err) {
       throw err;
     }
@@ -156,26 +210,13 @@ class Transaction {
 
   // Load a transaction from the database
   static async load(hash) {
-    const query = "SELECT * FROM transactions WHERE hash = ?";
     try {
-      const [results] = await db.query(query, [hash]);
-      if (results.length === 0) {
+      const row = await storage.getTransactionByHash(hash);
+      if (!row) {
         return null;
       }
   
-      const txData = results[0];
-      const tx = new Transaction(
-        txData.from_address,
-        txData.to_address,
-        new Decimal(txData.amount).toFixed(8),
-        txData.timestamp,
-        txData.signature,
-        txData.block_hash,
-        txData.origin_transaction_hash,
-        txData.public_key,
-        txData.index_in_block  
-      );
-      tx.hash = txData.hash;
+      const tx = Transaction.fromRow(row);
   
       // Validate the transaction
       if (!tx.isValid()) {
@@ -193,24 +234,21 @@ class Transaction {
   
 
   async savePending() {
-    const query = `
-      INSERT INTO pending_transactions (hash, from_address, to_address, amount, timestamp, signature, origin_transaction_hash) 
-      VALUES (?, ?, ?, ?, ?, ?, ?)
-      ON DUPLICATE KEY UPDATE hash = hash
-    `;
-    const values = [
-      this.calculateHash(),
-      this.fromAddress,
-      this.toAddress,
-      this.amount,
-      this.timestamp,
-      this.signature,
-      this.originTransactionHash,
-    ];
-  
+    const row = {
+      hash: this.calculateHash(),
+      from_address: this.fromAddress,
+      to_address: this.toAddress,
+      amount: this.amount,
+      timestamp: this.timestamp,
+      signature: this.signature,
+      origin_transaction_hash: this.originTransactionHash,
+      public_key: this.publicKey,
+      fee: new Decimal(this.fee).toFixed(8),
+      chain_id: this.chainId
+    };
   
     try {
-      await db.query(query, values);
+      await storage.savePendingTransaction(row);
 
     } catch (err) {
       console.error("Error saving transaction to pending_transactions:", err);
@@ -221,26 +259,9 @@ class Transaction {
 
   // Load all pending transactions
   static async loadPendingTransactions() {
-    const query = "SELECT * FROM pending_transactions";
-  
     try {
-      const [results] = await db.query(query);
-      const transactions = results.map((txData) => {
-        const tx = new Transaction(
-          txData.from_address,
-          txData.to_address,
-          txData.amount,
-          txData.timestamp,
-          txData.signature,
-          null, // block_hash is null for pending
-          txData.origin_transaction_hash,
-          txData.public_key
-        );
-        tx.hash = txData.hash;
-        return tx;
-      });
-  
-      return transactions;
+      const rows = await storage.getPendingTransactions();
+      return rows.map(row => Transaction.fromRow(row));
     } catch (err) {
       console.error("Error loading pending transactions:", err);
       throw err;
@@ -248,13 +269,12 @@ class Transaction {
   }
 
   static async loadPendingTransactionByHash(hash) {
-    const query = "SELECT * FROM pending_transactions WHERE hash = ?";
     try {
-      const [results] = await db.query(query, [hash]);
-      if (results.length === 0) {
+      const row = await storage.getPendingTransactionByHash(hash);
+      if (!row) {
         return null; // Transaction not found
       }
-      return Transaction.fromJSON(results[0]); // Return the found transaction
+      return Transaction.fromRow(row); // Return the found transaction
     } catch (err) {
       console.error("Error loading pending transaction by hash:", err);
       throw err;
@@ -263,39 +283,15 @@ class Transaction {
 
   // Get the latest transaction for a given address
   static async getLatestTransactionForAddress(address) {
-  
-    const query = `
-      SELECT * 
-      FROM transactions 
-      WHERE from_address = ? 
-      ORDER BY timestamp DESC 
-      LIMIT 1
-    `;
-  
-  
     try {
-      // Execute the query using await
-      const [results] = await db.query(query, [address]);
+      const row = await storage.getLatestTransactionFromAddress(address);
   
-      if (results.length === 0) {
+      if (!row) {
         console.log("No transactions found for address:", address);
         return null;
       }
   
-      const txData = results[0];
-      const tx = new Transaction(
-        txData.from_address,
-        txData.to_address,
-        txData.amount,
-        txData.timestamp,
-        txData.signature,
-        txData.block_hash,
-        txData.origin_transaction_hash,
-        txData.public_key
-      );
-      tx.hash = txData.hash;
-  
-      return tx;
+      return Transaction.fromRow(row);
     } catch (err) {
       console.error("Error fetching latest transaction:", err);
       throw err;
@@ -318,4 +314,13 @@ class Transaction {
   }
 }
 
+function isValidFee(fee) {
+  try {
+    const value = new Decimal(fee);
+    return value.isFinite() && !value.isNegative() && value.decimalPlaces() <= 8;
+  } catch (err) {
+    return false;
+  }
+}
+
 export {Transaction};
\ No newline at end of file
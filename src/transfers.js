@@ -0,0 +1,75 @@
+"use strict";
+
+import Decimal from 'decimal.js';
+
+/**
+ * Checks one transfer against what its sender holds: the amount must be
+ * positive and the sender must cover the amount plus the fee.
+ * @param {Transaction} tx - A transaction with a sender
+ * @param {Decimal} balance - What the sender holds before the transaction
+ * @returns {string|null} - Why the transfer is not allowed, or null if it is
+ */
+function checkTransfer(tx, balance) {
+  if (!new Decimal(tx.amount).greaterThan(0)) {
+    return `transfers a non-positive amount in transaction ${tx.hash}`;
+  }
+  const cost = new Decimal(tx.amount).plus(tx.fee || 0);
+  if (balance.lessThan(cost)) {
+    return `spends ${cost.toFixed(8)} from ${tx.fromAddress}, which only holds ${balance.toFixed(8)} (transaction ${tx.hash})`;
+  }
+  return null;
+}
+
+/**
+ * Applies a block's transactions in order: none may appear twice and every
+ * transfer must pass `checkTransfer` at its point in the block. Recipients
+ * are credited as the transactions are applied, so a transfer may spend
+ * coins received earlier in the same block.
+ * @param {Transaction[]} transactions - Block transactions in order, coinbase included
+ * @param {Map<string, Decimal>} balances - address -> balance before the block; updated in place
+ * @returns {string|null} - Why the block is not allowed, or null if it is
+ */
+function applyTransfers(transactions, balances) {
+  const seen = new Set();
+  for (const tx of transactions) {
+    if (seen.has(tx.hash)) {
+      return `contains transaction ${tx.hash} twice`;
+    }
+    seen.add(tx.hash);
+
+    if (tx.fromAddress) {
+      const balance = balances.get(tx.fromAddress) || new Decimal(0);
+      const error = checkTransfer(tx, balance);
+      if (error) {
+        return error;
+      }
+      balances.set(tx.fromAddress, balance.minus(tx.amount).minus(tx.fee || 0));
+    }
+    if (tx.toAddress) {
+      balances.set(tx.toAddress, (balances.get(tx.toAddress) || new Decimal(0)).plus(tx.amount));
+    }
+  }
+  return null;
+}
+
+/**
+ * Keeps the candidates their senders can pay for, taking each sender's
+ * transactions in timestamp order. Coins received from other candidates are
+ * not counted: the transaction paying them may not make it into the block.
+ * @param {Transaction[]} candidates - Pending transactions, unique by hash
+ * @param {Map<string, Decimal>} balances - sender -> confirmed balance; updated in place
+ * @returns {Transaction[]}
+ */
+function affordableTransactions(candidates, balances) {
+  const ordered = [...candidates].sort((a, b) => a.timestamp - b.timestamp || a.hash.localeCompare(b.hash));
+  return ordered.filter(tx => {
+    const balance = balances.get(tx.fromAddress) || new Decimal(0);
+    if (checkTransfer(tx, balance)) {
+      return false;
+    }
+    balances.set(tx.fromAddress, balance.minus(tx.amount).minus(tx.fee || 0));
+    return true;
+  });
+}
+
+export { checkTransfer, applyTransfers, affordableTransactions };
@@ -19,17 +19,19 @@ if (!fs.existsSync(WALLET_DIR)) {
   fs.mkdirSync(WALLET_DIR, { recursive: true });
 }
 
+// The address of a public key: the first 30 hex characters of the SHA-256 of the key
+function addressFromPublicKey(publicKey) {
+  const publicKeyBuffer = Buffer.from(publicKey, 'hex');
+  const hash = crypto.createHash('sha256').update(publicKeyBuffer).digest('hex');
+  return hash.slice(0, 30);
+}
+
 function createNewWallet() {
   const key = ec.genKeyPair();
   const publicKey = key.getPublic('hex');
   const privateKey = key.getPrivate('hex');
   
-  // Hash the public key
-  const publicKeyBuffer = Buffer.from(publicKey, 'hex');
-  const hash = crypto.createHash('sha256').update(publicKeyBuffer).digest('hex');
-  
-  // Truncate the hash to create a shorter address
-  const shortAddress = hash.slice(0, 30); // Adjust length as needed
+  const shortAddress = addressFromPublicKey(publicKey);
 
   // Save the wallet to a file with the address included
   const walletData = {
@@ -54,7 +56,7 @@ function loadWallet(address) {
   return walletData;
 }
 
-export { createNewWallet, loadWallet, ec };
+export { createNewWallet, loadWallet, addressFromPublicKey, ec };
 
 
 
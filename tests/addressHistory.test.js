@@ -0,0 +1,60 @@
+import assert from 'assert';
+import { Blockchain } from '../src/blockchain.js';
+import { GENESIS_ADDRESS, RECIPIENT, resetChain, payment, blockAfter } from './helpers.js';
+
+describe('Address history', function() {
+  this.timeout(10000);
+
+  const blockchain = new Blockchain();
+  const payments = [];
+
+  beforeEach(async function() {
+    payments.length = 0;
+    await resetChain();
+
+    // Blocks 1-3 each carry two payments to RECIPIENT plus a mining reward
+    for (let height = 1; height <= 3; height++) {
+      const previous = blockchain.getLatestHeader();
+      const transactions = [];
+      for (let i = 0; i < 2; i++) {
+        const tx = await payment(GENESIS_ADDRESS, height, { timestamp: previous.timestamp + height * 10 + i });
+        transactions.push(tx);
+        payments.push(tx.hash);
+      }
+      assert.ok(await blockchain.addBlock(blockAfter(previous, transactions)));
+    }
+  });
+
+  it('should page through confirmed entries newest first', async function() {
+    const first = await blockchain.getAddressHistory(RECIPIENT, { limit: 4 });
+    const second = await blockchain.getAddressHistory(RECIPIENT, { limit: 4, cursor: first.nextCursor });
+
+    assert.deepStrictEqual([...first.entries, ...second.entries].map(entry => entry.hash), [...payments].reverse());
+    assert.strictEqual(second.nextCursor, null);
+    assert.deepStrictEqual(first.entries.map(entry => entry.confirmations), [1, 1, 2, 2]);
+    assert.strictEqual(first.entries[0].blockHeight, 3);
+  });
+
+  it('should filter by height range in ascending order', async function() {
+    const page = await blockchain.getAddressHistory(RECIPIENT, { direction: 'asc', fromHeight: 2, toHeight: 2 });
+
+    assert.deepStrictEqual(page.entries.map(entry => entry.hash), payments.slice(2, 4));
+    assert.deepStrictEqual(page.entries.map(entry => entry.indexInBlock), [0, 1]);
+  });
+
+  it('should include pending transactions on the first page only', async function() {
+    const tx = await payment(GENESIS_ADDRESS, 1);
+    await blockchain.addPendingTransaction(tx);
+
+    const first = await blockchain.getAddressHistory(RECIPIENT, { limit: 2 });
+    const second = await blockchain.getAddressHistory(RECIPIENT, { limit: 2, cursor: first.nextCursor });
+
+    assert.deepStrictEqual(first.pending.map(entry => [entry.hash, entry.status, entry.confirmations]), [[tx.hash, 'pending', 0]]);
+    assert.deepStrictEqual(second.pending, []);
+  });
+
+  it('should reject invalid paging options', async function() {
+    await assert.rejects(blockchain.getAddressHistory(RECIPIENT, { limit: 0 }), /limit/);
+    await assert.rejects(blockchain.getAddressHistory(RECIPIENT, { cursor: 'abc' }), /cursor/);
+  });
+});
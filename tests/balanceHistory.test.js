@@ -0,0 +1,53 @@
+import assert from 'assert';
+import { storage } from '../src/db.js';
+import { Blockchain } from '../src/blockchain.js';
+import { GENESIS_ADDRESS, MINER, RECIPIENT, resetChain, payment, blockAfter } from './helpers.js';
+
+describe('Historical balances', function() {
+  this.timeout(10000);
+
+  const blockchain = new Blockchain();
+
+  async function addBlock(transactions) {
+    const block = blockAfter(blockchain.getLatestHeader(), transactions);
+    assert.ok(await blockchain.addBlock(block));
+    return block;
+  }
+
+  beforeEach(async function() {
+    const genesis = await resetChain();
+    await addBlock([]);
+    await addBlock([await payment(GENESIS_ADDRESS, 25, { timestamp: genesis.timestamp + 2 })]);
+  });
+
+  it('should return the balance as of each height', async function() {
+    assert.strictEqual(await blockchain.getBalanceAt(MINER, 0), '0.00000000');
+    assert.strictEqual(await blockchain.getBalanceAt(MINER, 1), '100.00000000');
+    assert.strictEqual(await blockchain.getBalanceAt(MINER, 2), '200.00000000');
+    assert.strictEqual(await blockchain.getBalanceAt(GENESIS_ADDRESS, 1), '1000.00000000');
+    assert.strictEqual(await blockchain.getBalanceAt(GENESIS_ADDRESS, 2), '975.00000000');
+    await assert.rejects(blockchain.getBalanceAt(MINER, 3), /outside the chain/);
+  });
+
+  it('should list the per-block balance changes of an address', async function() {
+    const history = await blockchain.getBalanceHistory(GENESIS_ADDRESS);
+
+    assert.deepStrictEqual(history.map(({ height, change, balance }) => ({ height, change, balance })), [
+      { height: 0, change: '1000.00000000', balance: '1000.00000000' },
+      { height: 2, change: '-25.00000000', balance: '975.00000000' }
+    ]);
+    assert.strictEqual(history[1].blockHash, blockchain.getHeader(2).hash);
+  });
+
+  it('should drop the history of disconnected blocks and rebuild it on reindex', async function() {
+    const tip = await blockchain.getLatestBlock();
+    await storage.withTransaction(store => tip.disconnect(store));
+    blockchain.truncateChain(1);
+
+    assert.deepStrictEqual((await blockchain.getBalanceHistory(RECIPIENT)), []);
+
+    await storage.clearDerivedData();
+    await blockchain.reindex();
+    assert.deepStrictEqual((await blockchain.getBalanceHistory(MINER)).map(entry => entry.balance), ['100.00000000']);
+  });
+});
@@ -0,0 +1,79 @@
+import assert from 'assert';
+import { Blockchain } from '../src/blockchain.js';
+import { blockSize, checkBlockLimits } from '../src/blockLimits.js';
+import { GENESIS_ADDRESS, MINER, resetChain, payment, blockAfter } from './helpers.js';
+
+describe('Block limits', function() {
+  this.timeout(20000);
+
+  const blockchain = new Blockchain();
+  const defaultConfig = blockchain.config;
+  let genesis;
+
+  function useLimits(limits) {
+    blockchain.config = { ...defaultConfig, chain: { ...defaultConfig.chain, ...limits } };
+  }
+
+  async function payments(count) {
+    const transactions = [];
+    for (let i = 0; i < count; i++) {
+      transactions.push(await payment(GENESIS_ADDRESS, 1, { timestamp: genesis.timestamp + 1 + i }));
+    }
+    return transactions;
+  }
+
+  beforeEach(async function() {
+    genesis = await resetChain();
+  });
+
+  afterEach(function() {
+    blockchain.config = defaultConfig;
+  });
+
+  it('should measure blocks by their transactions only', async function() {
+    const { transactions } = blockAfter(genesis, await payments(2));
+    assert.strictEqual(blockSize(transactions), transactions.reduce((total, tx) => total + tx.getSize(), 0));
+    assert.strictEqual(checkBlockLimits(transactions, { maxBlockBytes: blockSize(transactions), maxBlockTransactions: 3 }), null);
+    assert.match(checkBlockLimits(transactions, { maxBlockBytes: blockSize(transactions) - 1, maxBlockTransactions: 3 }), /bytes/);
+    assert.match(checkBlockLimits(transactions, { maxBlockBytes: 1000000, maxBlockTransactions: 2 }), /transactions/);
+  });
+
+  it('should keep the size of a transaction once it is mined', async function() {
+    const [tx] = await payments(1);
+    const size = tx.getSize();
+    tx.blockHash = genesis.hash;
+    tx.index_in_block = 0;
+    assert.strictEqual(tx.getSize(), size);
+  });
+
+  it('should reject blocks over the limits', async function() {
+    const block = blockAfter(genesis, await payments(3));
+
+    useLimits({ maxBlockTransactions: 3 });
+    assert.strictEqual(await blockchain.addBlock(block), false);
+    assert.strictEqual(await Blockchain.isValidChain([(await blockchain.getBlock(0)).toJSON(), block.toJSON()], blockchain.config.chain), false);
+
+    useLimits({ maxBlockBytes: blockSize(block.transactions) - 1 });
+    assert.strictEqual(await blockchain.addBlock(block), false);
+
+    useLimits({ maxBlockTransactions: 4, maxBlockBytes: blockSize(block.transactions) });
+    assert.strictEqual(await blockchain.addBlock(block), true);
+  });
+
+  it('should spill an oversized mempool into later blocks', async function() {
+    useLimits({ maxBlockTransactions: 3 });
+    for (const tx of await payments(5)) {
+      await blockchain.addPendingTransaction(tx);
+    }
+
+    await blockchain.minePendingTransactions(MINER);
+    assert.strictEqual((await blockchain.getLatestBlock()).transactions.length, 3);
+    assert.strictEqual(blockchain.pendingTransactions.length, 3);
+
+    await blockchain.minePendingTransactions(MINER);
+    await blockchain.minePendingTransactions(MINER);
+    assert.strictEqual(blockchain.getHeight(), 3);
+    assert.strictEqual(blockchain.pendingTransactions.length, 0);
+    assert.strictEqual(await blockchain.isChainValid(), true);
+  });
+});
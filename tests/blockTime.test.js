@@ -0,0 +1,78 @@
+import assert from 'assert';
+import { Blockchain } from '../src/blockchain.js';
+import { medianTimePast, checkBlockTime } from '../src/blockTime.js';
+import { NetworkTime, MAX_OFFSET_MS } from '../src/networkTime.js';
+import { resetChain, blockAfter } from './helpers.js';
+const PARAMS = { maxFutureDriftSeconds: 60 };
+
+describe('Block timestamps', function() {
+  describe('rules', function() {
+    const timestamps = [100, 500, 200, 400, 300, 900, 800, 700, 600, 1000, 1100, 1200];
+    const headerAt = height => ({ timestamp: timestamps[height] });
+
+    it('should use the median of the last 11 blocks', function() {
+      assert.strictEqual(medianTimePast(3, headerAt), 200);
+      assert.strictEqual(medianTimePast(12, headerAt), 700);
+    });
+
+    it('should reject timestamps at or before the median time past', function() {
+      assert.match(checkBlockTime(700, 12, headerAt, PARAMS, 2000), /median time/);
+      assert.strictEqual(checkBlockTime(701, 12, headerAt, PARAMS, 2000), null);
+    });
+
+    it('should reject timestamps beyond the allowed future drift', function() {
+      assert.match(checkBlockTime(62001, 12, headerAt, PARAMS, 2000), /future/);
+      assert.strictEqual(checkBlockTime(62000, 12, headerAt, PARAMS, 2000), null);
+    });
+
+    it('should exempt the genesis block', function() {
+      assert.strictEqual(checkBlockTime(1, 0, headerAt, PARAMS, 2000), null);
+    });
+  });
+
+  describe('NetworkTime', function() {
+    it('should apply the median peer offset once enough peers reported', function() {
+      const time = new NetworkTime(() => 10000);
+      time.addSample('a', 11000);
+      time.addSample('b', 9000);
+      assert.strictEqual(time.now(), 10000);
+
+      time.addSample('c', 10500);
+      assert.strictEqual(time.getOffset(), 500);
+      assert.strictEqual(time.now(), 10500);
+
+      time.removePeer('c');
+      assert.strictEqual(time.getOffset(), 0);
+    });
+
+    it('should cap the offset', function() {
+      const time = new NetworkTime(() => 0);
+      ['a', 'b', 'c'].forEach(peer => time.addSample(peer, 24 * 60 * 60 * 1000));
+      assert.strictEqual(time.getOffset(), MAX_OFFSET_MS);
+    });
+  });
+
+  describe('enforcement', function() {
+    const blockchain = new Blockchain();
+    let genesis;
+
+    function blockAt(timestamp) {
+      return blockAfter(genesis, [], { timestamp });
+    }
+
+    beforeEach(async function() {
+      genesis = await resetChain();
+    });
+
+    it('should reject blocks dated before their parent', async function() {
+      assert.strictEqual(await blockchain.addBlock(blockAt(genesis.timestamp - 1)), false);
+      assert.strictEqual(await Blockchain.isValidChain([(await blockchain.getBlock(0)).toJSON(), blockAt(genesis.timestamp).toJSON()]), false);
+    });
+
+    it('should reject blocks dated too far in the future', async function() {
+      const future = Date.now() + (blockchain.config.chain.maxFutureDriftSeconds + 60) * 1000;
+      assert.strictEqual(await blockchain.addBlock(blockAt(future)), false);
+      assert.strictEqual(await blockchain.addBlock(blockAt(genesis.timestamp + 1)), true);
+    });
+  });
+});
@@ -0,0 +1,57 @@
+import assert from 'assert';
+import { storage } from '../src/db.js';
+import { Blockchain } from '../src/blockchain.js';
+import { resetChain, branch } from './helpers.js';
+
+describe('Headers-only chain', function() {
+  const blockchain = new Blockchain();
+
+  beforeEach(async function() {
+    for (const block of branch(await resetChain(), 3)) {
+      assert.ok(await blockchain.addBlock(block));
+    }
+  });
+
+  it('should load only headers on startup and fetch bodies on demand', async function() {
+    await blockchain.loadChainFromDatabase();
+
+    assert.strictEqual(blockchain.getHeight(), 3);
+    assert.deepStrictEqual([...blockchain.blockCache.entries.keys()], [3]); // Only the tip body
+    assert.strictEqual(blockchain.getHeader(2).previousHash, blockchain.getHeader(1).hash);
+
+    const block = await blockchain.getBlock(2);
+    assert.strictEqual(block.hash, blockchain.getHeader(2).hash);
+    assert.strictEqual(block.transactions.length, 1);
+    assert.strictEqual(await blockchain.getBlock(2), block);
+    assert.strictEqual(await blockchain.getBlock(4), null);
+  });
+
+  it('should load block ranges with batched queries', async function() {
+    await blockchain.loadChainFromDatabase();
+    const calls = { single: 0, batch: 0 };
+    const original = { load: storage.getTransactionsByBlockHash, batch: storage.getTransactionsByBlockHashes };
+    storage.getTransactionsByBlockHash = async (...args) => { calls.single++; return original.load.apply(storage, args); };
+    storage.getTransactionsByBlockHashes = async (...args) => { calls.batch++; return original.batch.apply(storage, args); };
+
+    try {
+      const blocks = await blockchain.getBlocks(0, 2);
+      assert.deepStrictEqual(blocks.map(block => block.hash), blockchain.headers.slice(0, 3).map(header => header.hash));
+      assert.deepStrictEqual(calls, { single: 0, batch: 1 });
+    } finally {
+      storage.getTransactionsByBlockHash = original.load;
+      storage.getTransactionsByBlockHashes = original.batch;
+    }
+  });
+
+  it('should find confirmed transactions without scanning blocks', async function() {
+    await blockchain.loadChainFromDatabase();
+    const expected = (await blockchain.getBlock(3)).transactions[0];
+    blockchain.blockCache.clear();
+
+    const found = await blockchain.findTransaction(expected.hash);
+
+    assert.strictEqual(found.block.index, 3);
+    assert.strictEqual(found.transaction.hash, expected.hash);
+    assert.strictEqual(await blockchain.findTransaction('0'.repeat(64)), null);
+  });
+});
@@ -0,0 +1,90 @@
+import assert from 'assert';
+import { Blockchain, Block, Transaction } from '../src/blockchain.js';
+import { GENESIS_ADDRESS, MINER, RECIPIENT, CHAIN_ID, resetChain, payment, blockAfter } from './helpers.js';
+
+const OTHER_CHAIN_ID = 'aibtcc-devnet';
+
+describe('Chain IDs', function() {
+  this.timeout(10000);
+
+  const blockchain = new Blockchain();
+  let genesis;
+
+  const paymentFor = chainId => payment(GENESIS_ADDRESS, 10, { chainId, timestamp: genesis.timestamp + 1 });
+
+  beforeEach(async function() {
+    genesis = await resetChain();
+  });
+
+  it('should take the chain ID from the genesis block', function() {
+    assert.strictEqual(blockchain.getChainId(), CHAIN_ID);
+    assert.strictEqual(genesis.chainId, CHAIN_ID);
+  });
+
+  it('should cover the chain ID with the hash and signature', async function() {
+    const tx = await paymentFor(CHAIN_ID);
+    assert.notStrictEqual(tx.hash, new Transaction(GENESIS_ADDRESS, RECIPIENT, 10, tx.timestamp).hash);
+    assert.strictEqual(tx.isValid(CHAIN_ID), true);
+    assert.throws(() => tx.isValid(OTHER_CHAIN_ID), /made for chain aibtcc-test, not aibtcc-devnet/);
+
+    tx.chainId = OTHER_CHAIN_ID;
+    assert.throws(() => tx.verifyTransaction(), /hash does not match/);
+    assert.strictEqual(tx.isValid(OTHER_CHAIN_ID), false);
+  });
+
+  it('should survive JSON and storage round trips', async function() {
+    const tx = await paymentFor(CHAIN_ID);
+    assert.strictEqual(Transaction.fromJSON(tx.toJSON()).calculateHash(), tx.hash);
+    assert.strictEqual(Transaction.fromRow(tx.toRow()).calculateHash(), tx.hash);
+
+    const block = blockAfter(genesis, [tx]);
+    assert.strictEqual(Block.fromJSON(block.toJSON()).calculateHash(), block.hash);
+  });
+
+  it('should refuse transactions made for another chain in the mempool', async function() {
+    await assert.rejects(blockchain.addPendingTransaction(await paymentFor(OTHER_CHAIN_ID)), /aibtcc-devnet/);
+    await assert.rejects(blockchain.addPendingTransaction(await paymentFor(null)), /\(none\)/);
+
+    await blockchain.addPendingTransaction(await paymentFor(CHAIN_ID));
+    assert.strictEqual(blockchain.pendingTransactions.length, 1);
+  });
+
+  it('should neither return nor store transactions made for another chain', async function() {
+    const foreign = await paymentFor(OTHER_CHAIN_ID);
+    await blockchain.returnTransactionsToMempool([{ transactions: [foreign] }], new Set());
+    assert.strictEqual(blockchain.pendingTransactions.length, 0);
+
+    await assert.rejects(foreign.save(undefined, CHAIN_ID), /made for chain aibtcc-devnet/);
+  });
+
+  it('should refuse blocks and transactions made for another chain', async function() {
+    assert.strictEqual(await blockchain.addBlock(blockAfter(genesis, [await paymentFor(CHAIN_ID)], { chainId: OTHER_CHAIN_ID })), false);
+    assert.strictEqual(await blockchain.addBlock(blockAfter(genesis, [await paymentFor(OTHER_CHAIN_ID)])), false);
+    assert.strictEqual(blockchain.getHeight(), 0);
+
+    assert.strictEqual(await blockchain.addBlock(blockAfter(genesis, [await paymentFor(CHAIN_ID)])), true);
+  });
+
+  it('should report foreign blocks and transactions when validating a chain', async function() {
+    const chain = [(await blockchain.getBlock(0)).toJSON()];
+
+    const foreignBlock = blockAfter(genesis, [], { chainId: OTHER_CHAIN_ID });
+    const result = await Blockchain.validateChain([...chain, foreignBlock.toJSON()], blockchain.config.chain);
+    assert.strictEqual(result.error.height, 1);
+    assert.match(result.error.reason, /was mined for chain aibtcc-devnet/);
+
+    const foreignTransaction = blockAfter(genesis, [await paymentFor(OTHER_CHAIN_ID)]);
+    assert.match((await Blockchain.validateChain([...chain, foreignTransaction.toJSON()], blockchain.config.chain)).error.reason, /transaction/);
+  });
+
+  it('should mine blocks for its own chain', async function() {
+    await blockchain.addPendingTransaction(await paymentFor(CHAIN_ID));
+    await blockchain.minePendingTransactions(MINER);
+
+    const block = await blockchain.getLatestBlock();
+    assert.strictEqual(block.index, 1);
+    assert.strictEqual(block.chainId, CHAIN_ID);
+    assert.ok(block.transactions.every(tx => tx.chainId === CHAIN_ID));
+    assert.strictEqual(await blockchain.isChainValid(), true);
+  });
+});
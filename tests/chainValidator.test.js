@@ -0,0 +1,118 @@
+import assert from 'assert';
+import { Blockchain, Block, Transaction } from '../src/blockchain.js';
+import { GENESIS_ADDRESS, MINER, RECIPIENT, resetChain, payment, blockAfter } from './helpers.js';
+
+describe('Chain validation', function() {
+  this.timeout(10000);
+
+  const blockchain = new Blockchain();
+  let genesis;
+
+  async function localChain() {
+    return (await blockchain.getBlocks()).map(block => block.toJSON());
+  }
+
+  beforeEach(async function() {
+    genesis = await resetChain();
+
+    const first = blockAfter(genesis, [await payment(GENESIS_ADDRESS, 10, { timestamp: genesis.timestamp + 1 })]);
+    assert.ok(await blockchain.addBlock(first));
+    assert.ok(await blockchain.addBlock(blockAfter(first, [await payment(GENESIS_ADDRESS, 20, { timestamp: first.timestamp + 1 })])));
+  });
+
+  it('should replay a valid chain to the tip', async function() {
+    const result = await Blockchain.validateChain(await localChain());
+    assert.deepStrictEqual(result, { valid: true, checkedBlocks: 3, error: null });
+    assert.strictEqual(await blockchain.isChainValid(), true);
+  });
+
+  it('should validate a chain streamed one block at a time', async function() {
+    const chain = await localChain();
+    const stream = (async function* () {
+      yield* chain;
+    })();
+    assert.deepStrictEqual(await Blockchain.validateChain(stream), { valid: true, checkedBlocks: 3, error: null });
+    assert.strictEqual((await Blockchain.validateChain([])).valid, false);
+  });
+
+  it('should report the first failing block and the reason', async function() {
+    const chain = await localChain();
+    chain[2].merkle_root = chain[1].merkle_root;
+
+    const result = await Blockchain.validateChain(chain);
+    assert.strictEqual(result.valid, false);
+    assert.strictEqual(result.checkedBlocks, 2);
+    assert.strictEqual(result.error.height, 2);
+    assert.strictEqual(result.error.hash, chain[2].hash);
+    assert.match(result.error.reason, /Merkle root/);
+    assert.strictEqual(await Blockchain.isValidChain(chain), false);
+  });
+
+  it('should check blocks after genesis', async function() {
+    const chain = await localChain();
+    chain[1].previous_hash = chain[2].hash;
+
+    const result = await Blockchain.validateChain(chain);
+    assert.strictEqual(result.error.height, 1);
+    assert.match(result.error.reason, /link/);
+  });
+
+  it('should reject tampered transactions', async function() {
+    const chain = await localChain();
+    chain[1].transactions[0].signature = chain[2].transactions[0].signature;
+    chain[1].hash = Block.fromJSON(chain[1]).calculateHash(); // A block hash recomputed over the forged signature
+
+    const result = await Blockchain.validateChain(chain);
+    assert.strictEqual(result.error.height, 1);
+    assert.match(result.error.reason, /transaction/);
+  });
+
+  it('should reject spending more than the sender holds', async function() {
+    const chain = await localChain();
+    const tip = Block.fromJSON(chain[2]);
+    const overspend = blockAfter(tip, [await payment(GENESIS_ADDRESS, 971, { timestamp: tip.timestamp + 1 })]);
+
+    const result = await Blockchain.validateChain([...chain, overspend.toJSON()]);
+    assert.strictEqual(result.error.height, 3);
+    assert.match(result.error.reason, /only holds 970\.00000000/);
+
+    const affordable = blockAfter(tip, [await payment(GENESIS_ADDRESS, 970, { timestamp: tip.timestamp + 1 })]);
+    assert.strictEqual((await Blockchain.validateChain([...chain, affordable.toJSON()])).valid, true);
+  });
+
+  it('should reject transactions signed with a key that is not the sender\'s', async function() {
+    const chain = await localChain();
+    const tip = Block.fromJSON(chain[2]);
+    const forged = new Transaction(GENESIS_ADDRESS, RECIPIENT, 5, tip.timestamp + 1, null, '', null, '', null, 0, blockchain.getChainId());
+    await forged.signWithAddress(MINER); // A valid signature, by the wrong wallet
+    assert.throws(() => forged.isValid(), /does not belong to the sender/);
+
+    const result = await Blockchain.validateChain([...chain, blockAfter(tip, [forged]).toJSON()]);
+    assert.strictEqual(result.error.height, 3);
+    assert.match(result.error.reason, /does not belong to the sender/);
+  });
+
+  it('should reject a transaction included twice', async function() {
+    const chain = await localChain();
+    const tip = Block.fromJSON(chain[2]);
+    const replayed = Transaction.fromJSON(chain[1].transactions[0]);
+
+    const result = await Blockchain.validateChain([...chain, blockAfter(tip, [replayed]).toJSON()]);
+    assert.strictEqual(result.error.height, 3);
+    assert.match(result.error.reason, /repeats transaction/);
+
+    const transfer = Transaction.fromJSON(chain[2].transactions[0]);
+    const twice = await Blockchain.validateChain([...chain, blockAfter(tip, [transfer, transfer]).toJSON()]);
+    assert.match(twice.error.reason, /repeats transaction/);
+  });
+
+  it('should not let a block spend its own coinbase', async function() {
+    const chain = await localChain();
+    const tip = Block.fromJSON(chain[2]);
+    const block = blockAfter(tip, [await payment(MINER, 300, { timestamp: tip.timestamp + 1 })]);
+
+    const result = await Blockchain.validateChain([...chain, block.toJSON()]);
+    assert.strictEqual(result.error.height, 3);
+    assert.match(result.error.reason, /spends/);
+  });
+});
@@ -0,0 +1,69 @@
+import assert from 'assert';
+import { Blockchain } from '../src/blockchain.js';
+import { checkCoinbase } from '../src/coinbase.js';
+import { GENESIS_ADDRESS, MINER, resetChain, payment, coinbase, blockWith } from './helpers.js';
+
+describe('Coinbase rules', function() {
+  this.timeout(10000);
+
+  const blockchain = new Blockchain();
+  let genesis;
+  let transfer;
+
+  beforeEach(async function() {
+    genesis = await resetChain();
+    transfer = await payment(GENESIS_ADDRESS, 10, { timestamp: genesis.timestamp + 1 });
+  });
+
+  it('should accept a single coinbase in last position up to the subsidy', async function() {
+    assert.strictEqual(checkCoinbase([transfer, coinbase(100)], 100, 1), null);
+    assert.strictEqual(await blockchain.addBlock(blockWith(genesis, [transfer, coinbase(100)])), true);
+  });
+
+  it('should reject blocks without exactly one coinbase', async function() {
+    assert.match(checkCoinbase([transfer], 100, 1), /exactly one/);
+    assert.strictEqual(await blockchain.addBlock(blockWith(genesis, [transfer])), false);
+    assert.strictEqual(await blockchain.addBlock(blockWith(genesis, [coinbase(50), coinbase(50, { timestamp: genesis.timestamp + 2 })])), false);
+  });
+
+  it('should reject a coinbase that is not the last transaction', async function() {
+    assert.match(checkCoinbase([coinbase(100), transfer], 100, 1), /last/);
+    assert.strictEqual(await blockchain.addBlock(blockWith(genesis, [coinbase(100), transfer])), false);
+  });
+
+  it('should reject a coinbase paying more than the subsidy', async function() {
+    assert.strictEqual(await blockchain.addBlock(blockWith(genesis, [coinbase(100.00000001)])), false);
+    assert.strictEqual(await Blockchain.isValidChain([(await blockchain.getBlock(0)).toJSON(), blockWith(genesis, [coinbase(1000)]).toJSON()]), false);
+  });
+
+  it('should reject a coinbase that does not carry the block height', async function() {
+    const reused = coinbase(100, { height: 2 });
+    assert.match(checkCoinbase([reused], 100, 1), /block height 1/);
+    assert.strictEqual(await blockchain.addBlock(blockWith(genesis, [reused])), false);
+    assert.strictEqual(await Blockchain.isValidChain([(await blockchain.getBlock(0)).toJSON(), blockWith(genesis, [reused]).toJSON()]), false);
+  });
+
+  it('should accept blocks paying the same reward to the same miner in the same millisecond', async function() {
+    const timestamp = Date.now();
+    const first = blockWith(genesis, [coinbase(100, { timestamp })]);
+    assert.strictEqual(await blockchain.addBlock(first), true);
+    const second = blockWith(first, [coinbase(100, { timestamp })]);
+    assert.notStrictEqual(second.transactions[0].hash, first.transactions[0].hash);
+    assert.strictEqual(await blockchain.addBlock(second), true);
+  });
+
+  it('should keep coinbase transactions out of the mempool', async function() {
+    await assert.rejects(blockchain.addPendingTransaction(coinbase(100)), /Coinbase/);
+    assert.strictEqual(blockchain.pendingTransactions.length, 0);
+  });
+
+  it('should mine blocks with a valid coinbase', async function() {
+    await blockchain.addPendingTransaction(transfer);
+    await blockchain.minePendingTransactions(MINER);
+
+    const block = await blockchain.getLatestBlock();
+    assert.strictEqual(block.index, 1);
+    assert.strictEqual(block.transactions[block.transactions.length - 1].toAddress, MINER);
+    assert.strictEqual(await Blockchain.isValidChain((await blockchain.getBlocks()).map(b => b.toJSON())), true);
+  });
+});
@@ -0,0 +1,52 @@
+import assert from 'assert';
+import fs from 'fs';
+import os from 'os';
+import path from 'path';
+import { loadConfig } from '../src/config.js';
+import { ConfigError } from '../src/errors.js';
+
+describe('loadConfig', function() {
+  const argv = ['node', 'blockchain-cli.js'];
+
+  it('should use the defaults when nothing is overridden', function() {
+    const config = loadConfig({ argv, env: {} });
+    assert.strictEqual(config.database.backend, 'mysql');
+    assert.strictEqual(config.database.sqliteFile, 'blockchain.sqlite');
+    assert.strictEqual(config.p2p.port, 6001);
+    assert.strictEqual(config.chain.miningReward, 100);
+    assert.ok(Object.isFrozen(config.chain));
+  });
+
+  it('should apply file, environment and flags in increasing precedence', function() {
+    const file = path.join(fs.mkdtempSync(path.join(os.tmpdir(), 'aibtcc-')), 'config.json');
+    fs.writeFileSync(file, JSON.stringify({ p2p: { port: 7001 }, database: { name: 'fromfile', user: 'alice' } }));
+
+    const config = loadConfig({
+      argv: [...argv, '--config', file, '--p2p-port', '9001'],
+      env: { P2P_PORT: '8001', DATABASE_NAME: 'fromenv', PEERS: 'ws://a:1,ws://b:2' }
+    });
+
+    assert.strictEqual(config.p2p.port, 9001);
+    assert.strictEqual(config.database.name, 'fromenv');
+    assert.strictEqual(config.database.user, 'alice');
+    assert.deepStrictEqual(config.p2p.peers, ['ws://a:1', 'ws://b:2']);
+  });
+
+  it('should report every invalid setting', function() {
+    assert.throws(
+      () => loadConfig({ argv, env: { STORAGE_BACKEND: 'oracle', P2P_PORT: 'abc', MINER_ADDRESS: 'x' } }),
+      (err) => err instanceof ConfigError && err.problems.length === 3
+    );
+  });
+
+  it('should report settings the genesis specification replaced', function() {
+    const file = path.join(fs.mkdtempSync(path.join(os.tmpdir(), 'aibtcc-')), 'config.json');
+    fs.writeFileSync(file, JSON.stringify({ chain: { difficulty: 4, genesisReward: 1000000 } }));
+
+    assert.throws(
+      () => loadConfig({ argv: [...argv, '--config', file], env: {} }),
+      (err) => err instanceof ConfigError && err.problems.length === 2 &&
+        err.problems.every(problem => /^chain\.\w+ has been removed: the genesis specification/.test(problem))
+    );
+  });
+});
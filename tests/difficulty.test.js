@@ -0,0 +1,78 @@
+import assert from 'assert';
+import { Blockchain, Block, Transaction } from '../src/blockchain.js';
+import { MAX_TARGET, targetForDifficulty, meetsDifficulty, nextDifficulty } from '../src/difficulty.js';
+import { MINER, resetChain, blockAfter } from './helpers.js';
+
+const PARAMS = { retargetInterval: 4, targetBlockTimeSeconds: 10 };
+
+// Headers 0..count-1 spaced `spacingMs` apart at the given difficulty
+function headers(count, spacingMs, difficulty = 8) {
+  return Array.from({ length: count }, (_, index) => ({ index, timestamp: 1000000 + index * spacingMs, difficulty }));
+}
+
+describe('Difficulty', function() {
+  describe('targets', function() {
+    it('should scale the target inversely with difficulty', function() {
+      assert.strictEqual(targetForDifficulty(0), MAX_TARGET);
+      assert.strictEqual(targetForDifficulty(1), MAX_TARGET);
+      assert.strictEqual(targetForDifficulty(16), MAX_TARGET / 16n);
+    });
+
+    it('should compare hashes against the target rather than hex prefixes', function() {
+      assert.strictEqual(meetsDifficulty('0fff' + 'f'.repeat(60), 16), true);
+      assert.strictEqual(meetsDifficulty('1000' + '0'.repeat(60), 16), false);
+      assert.strictEqual(meetsDifficulty('1000' + '0'.repeat(60), 15), true);
+      assert.strictEqual(meetsDifficulty('not a hash', 0), false);
+    });
+  });
+
+  describe('nextDifficulty', function() {
+    it('should keep the difficulty between retarget heights', function() {
+      const chain = headers(6, 1);
+      assert.strictEqual(nextDifficulty(5, height => chain[height], PARAMS), 8);
+    });
+
+    it('should raise the difficulty when blocks come too fast', function() {
+      const chain = headers(8, 5000);
+      assert.strictEqual(nextDifficulty(8, height => chain[height], PARAMS), 16);
+    });
+
+    it('should lower the difficulty when blocks come too slowly, down to at least 1', function() {
+      const chain = headers(8, 20000);
+      assert.strictEqual(nextDifficulty(8, height => chain[height], PARAMS), 4);
+
+      const easy = headers(8, 20000, 1);
+      assert.strictEqual(nextDifficulty(8, height => easy[height], PARAMS), 1);
+    });
+
+    it('should limit a single adjustment to a factor of four', function() {
+      const chain = headers(8, 1);
+      assert.strictEqual(nextDifficulty(8, height => chain[height], PARAMS), 32);
+    });
+
+    it('should never retarget when the interval is 0', function() {
+      const chain = headers(8, 1);
+      assert.strictEqual(nextDifficulty(8, height => chain[height], { ...PARAMS, retargetInterval: 0 }), 8);
+    });
+  });
+
+  describe('enforcement', function() {
+    const blockchain = new Blockchain();
+
+    beforeEach(resetChain);
+
+    it('should reject blocks that claim a different difficulty', async function() {
+      const block = blockAfter(blockchain.getLatestHeader(), [], { difficulty: 16 });
+
+      assert.strictEqual(await blockchain.addBlock(block), false);
+      assert.strictEqual(await blockchain.isChainValid(), true);
+      assert.strictEqual(await Blockchain.isValidChain([(await blockchain.getBlock(0)).toJSON(), block.toJSON()]), false);
+    });
+
+    it('should mine blocks that meet the target', function() {
+      const block = new Block(1, '0'.repeat(64), 1, [new Transaction(null, MINER, 100, 1)], 64);
+      block.mineBlock(64);
+      assert.ok(BigInt('0x' + block.hash) <= MAX_TARGET / 64n);
+    });
+  });
+});
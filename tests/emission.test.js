@@ -0,0 +1,62 @@
+import assert from 'assert';
+import { Blockchain } from '../src/blockchain.js';
+import { blockSubsidy, scheduledSupplyAt, supplyLimit, nextHalvingHeight } from '../src/emission.js';
+import { GENESIS_BALANCE, resetChain, coinbase, blockWith } from './helpers.js';
+
+const SCHEDULE = { miningReward: 50, halvingInterval: 4, tailEmission: 0, maxSupply: 0, genesisSupply: 1000 };
+
+function subsidies(params, count) {
+  return Array.from({ length: count }, (_, height) => blockSubsidy(height, params).toFixed(8));
+}
+
+describe('Emission schedule', function() {
+  it('should halve the subsidy every interval and pay nothing for genesis', function() {
+    assert.deepStrictEqual(subsidies(SCHEDULE, 9).map(Number), [0, 50, 50, 50, 25, 25, 25, 25, 12.5]);
+    assert.strictEqual(nextHalvingHeight(5, SCHEDULE), 8);
+    assert.strictEqual(nextHalvingHeight(5, { ...SCHEDULE, halvingInterval: 0 }), null);
+  });
+
+  it('should match the summed subsidies in the scheduled supply', function() {
+    const summed = subsidies(SCHEDULE, 30).reduce((total, subsidy) => total + Number(subsidy), SCHEDULE.genesisSupply);
+    assert.strictEqual(Number(scheduledSupplyAt(29, SCHEDULE).toFixed(8)), summed);
+  });
+
+  it('should never go below the tail emission', function() {
+    const params = { ...SCHEDULE, tailEmission: 10 };
+    assert.deepStrictEqual(subsidies(params, 13).slice(8).map(Number), [12.5, 12.5, 12.5, 12.5, 10]);
+    assert.strictEqual(blockSubsidy(1000000, params).toFixed(8), '10.00000000');
+    assert.strictEqual(supplyLimit(params), null);
+  });
+
+  it('should stop issuing at the hard cap', function() {
+    const params = { ...SCHEDULE, maxSupply: 1120 };
+    assert.deepStrictEqual(subsidies(params, 6).map(Number), [0, 50, 50, 20, 0, 0]);
+    assert.strictEqual(scheduledSupplyAt(100, params).toFixed(8), '1120.00000000');
+    assert.strictEqual(supplyLimit(params).toFixed(8), '1120.00000000');
+  });
+
+  it('should converge when the subsidy halves to zero', function() {
+    const limit = supplyLimit(SCHEDULE);
+    assert.ok(limit.greaterThan(1349) && limit.lessThan(1350));
+  });
+
+  describe('Blockchain', function() {
+    const blockchain = new Blockchain();
+
+    beforeEach(resetChain);
+
+    it('should report supply computed from the chain', async function() {
+      const block = blockWith(blockchain.getLatestHeader(), [coinbase(60)]);
+      assert.ok(await blockchain.addBlock(block));
+
+      const supply = await blockchain.getSupplyInfo();
+      const { miningReward } = blockchain.config.chain;
+
+      assert.strictEqual(supply.height, 1);
+      assert.strictEqual(Number(supply.issued), GENESIS_BALANCE + 60);
+      assert.strictEqual(Number(supply.circulating), 60);
+      assert.strictEqual(Number(supply.scheduled), GENESIS_BALANCE + miningReward);
+      assert.strictEqual(Number(supply.nextSubsidy), miningReward);
+    });
+  });
+});
@@ -0,0 +1,68 @@
+import assert from 'assert';
+import Decimal from 'decimal.js';
+import { Blockchain } from '../src/blockchain.js';
+import { estimateFee } from '../src/feeEstimator.js';
+import { GENESIS_ADDRESS, MINER, resetChain, payment } from './helpers.js';
+
+// Stand-in exposing only what the estimator reads: 1000 bytes at `ratePerKb`
+function fakeTx(ratePerKb, fromAddress = GENESIS_ADDRESS) {
+  return {
+    fromAddress,
+    getSize: () => 1000,
+    getFeeRate: () => new Decimal(ratePerKb).dividedBy(1000)
+  };
+}
+
+function fakeBlock(...rates) {
+  return { transactions: [...rates.map(rate => fakeTx(rate)), fakeTx(0, null)] };
+}
+
+describe('Fee estimation', function() {
+  const recentBlocks = [fakeBlock(1, 2, 3), fakeBlock(4, 5, 6, 7, 8, 9)];
+  const estimate = (targetBlocks, pending = []) =>
+    estimateFee({ recentBlocks, pending, targetBlocks, maxBlockBytes: 3000, size: 500 });
+
+  it('should ask less for longer confirmation targets', function() {
+    assert.strictEqual(estimate(1).feeRatePerKb, '5.00000000');
+    assert.strictEqual(estimate(3).feeRatePerKb, '3.00000000');
+    assert.strictEqual(estimate(8).feeRatePerKb, '1.00000000');
+    assert.strictEqual(estimate(1).fee, '2.50000000');
+    assert.strictEqual(estimate(1).sampleSize, 9);
+  });
+
+  it('should outbid a mempool deeper than the target', function() {
+    const pending = [fakeTx(20), fakeTx(30), fakeTx(10), fakeTx(40)];
+
+    const result = estimate(1, pending);
+    assert.strictEqual(result.feeRatePerKb, '20.00000001');
+    assert.strictEqual(result.mempoolBlocks, 2);
+    assert.strictEqual(estimate(2, pending).feeRatePerKb, estimate(2).feeRatePerKb);
+  });
+
+  it('should ask nothing without history or competition', function() {
+    const result = estimateFee({ recentBlocks: [], pending: [], targetBlocks: 1, maxBlockBytes: 3000, size: 500 });
+    assert.strictEqual(result.fee, '0.00000000');
+  });
+
+  it('should reject invalid targets', function() {
+    assert.throws(() => estimate(0), /confirmation target/);
+  });
+
+  describe('Blockchain', function() {
+    this.timeout(10000);
+
+    const blockchain = new Blockchain();
+
+    beforeEach(resetChain);
+
+    it('should estimate from mined fees', async function() {
+      const tx = await payment(GENESIS_ADDRESS, 10, { fee: 0.5 });
+      await blockchain.addPendingTransaction(tx);
+      await blockchain.minePendingTransactions(MINER);
+
+      const result = await blockchain.estimateFee(1, tx.getSize());
+      assert.strictEqual(result.sampleSize, 1);
+      assert.strictEqual(result.fee, '0.50000000');
+    });
+  });
+});
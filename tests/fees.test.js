@@ -0,0 +1,97 @@
+import assert from 'assert';
+import { Blockchain, Transaction } from '../src/blockchain.js';
+import { selectTransactions } from '../src/blockAssembler.js';
+import { blockSubsidy } from '../src/emission.js';
+import { GENESIS_ADDRESS, MINER, RECIPIENT, OTHER, resetChain, payment, coinbase, blockWith } from './helpers.js';
+
+describe('Transaction fees', function() {
+  this.timeout(10000);
+
+  const blockchain = new Blockchain();
+  let genesis;
+
+  // Pays OTHER so that no sender below is also a recipient
+  const transfer = (from, amount, fee, timestamp = genesis.timestamp + 1) => payment(from, amount, { to: OTHER, fee, timestamp });
+
+  beforeEach(async function() {
+    genesis = await resetChain();
+  });
+
+  it('should cover the fee with the hash and signature', async function() {
+    const tx = await transfer(GENESIS_ADDRESS, 10, 0.5);
+    assert.notStrictEqual(tx.hash, new Transaction(GENESIS_ADDRESS, OTHER, 10, tx.timestamp).hash);
+    assert.strictEqual(new Transaction(GENESIS_ADDRESS, OTHER, 10, tx.timestamp, null, '', null, '', null, 0).hash,
+      new Transaction(GENESIS_ADDRESS, OTHER, 10, tx.timestamp).hash);
+
+    tx.fee = 0.01;
+    assert.throws(() => tx.verifyTransaction(), /hash does not match/);
+    assert.strictEqual(tx.isValid(), false);
+  });
+
+  it('should keep the timestamp and fee apart in the hash', function() {
+    const first = new Transaction(GENESIS_ADDRESS, OTHER, 10, 1727740800123, null, '', null, '', null, 0.1);
+    const second = new Transaction(GENESIS_ADDRESS, OTHER, 10, 172774080012, null, '', null, '', null, 30.1);
+    assert.notStrictEqual(first.hash, second.hash);
+  });
+
+  it('should reject negative fees', async function() {
+    const tx = await transfer(GENESIS_ADDRESS, 10, -1);
+    assert.throws(() => tx.isValid(), /fee/);
+  });
+
+  it('should survive JSON and storage round trips', async function() {
+    const tx = await transfer(GENESIS_ADDRESS, 10, 0.25);
+    assert.strictEqual(Transaction.fromJSON(tx.toJSON()).fee, '0.25000000');
+    assert.strictEqual(Transaction.fromRow(tx.toRow()).calculateHash(), tx.hash);
+  });
+
+  it('should charge the sender and pay the fees to the miner', async function() {
+    await blockchain.addPendingTransaction(await transfer(GENESIS_ADDRESS, 10, 0.5));
+    await blockchain.addPendingTransaction(await transfer(GENESIS_ADDRESS, 5, 0.25, genesis.timestamp + 2));
+    await blockchain.minePendingTransactions(MINER);
+
+    const block = await blockchain.getLatestBlock();
+    assert.strictEqual(block.index, 1);
+    assert.strictEqual(block.getTotalFees().toFixed(8), '0.75000000');
+    assert.strictEqual(await blockchain.getBalanceOfAddress(GENESIS_ADDRESS), '984.25000000');
+    assert.strictEqual(await blockchain.getBalanceOfAddress(OTHER), '15.00000000');
+    assert.strictEqual(await blockchain.getBalanceOfAddress(MINER), blockSubsidy(1, blockchain.getChainParams()).plus(0.75).toFixed(8));
+    assert.strictEqual(await blockchain.isChainValid(), true);
+  });
+
+  it('should let the coinbase claim the subsidy plus fees but no more', async function() {
+    const subsidy = blockSubsidy(1, blockchain.getChainParams());
+    const tx = await transfer(GENESIS_ADDRESS, 10, 1);
+    const blockPaying = amount => blockWith(genesis, [tx, coinbase(amount.toFixed(8))]);
+
+    assert.strictEqual(await blockchain.addBlock(blockPaying(subsidy.plus(1.00000001))), false);
+    assert.strictEqual(await blockchain.addBlock(blockPaying(subsidy.plus(1))), true);
+  });
+
+  describe('Block assembly', function() {
+    it('should order transactions by fee rate', async function() {
+      const low = await transfer(GENESIS_ADDRESS, 1, 0.1);
+      const high = await transfer(MINER, 1, 0.9);
+      const none = await transfer(RECIPIENT, 1, 0);
+
+      assert.deepStrictEqual(selectTransactions([none, low, high], { maxBytes: 100000 }), [high, low, none]);
+    });
+
+    it('should keep each sender\'s transactions in timestamp order', async function() {
+      const first = await transfer(GENESIS_ADDRESS, 1, 0, genesis.timestamp + 1);
+      const second = await transfer(GENESIS_ADDRESS, 1, 5, genesis.timestamp + 2);
+      const other = await transfer(MINER, 1, 1);
+
+      assert.deepStrictEqual(selectTransactions([second, other, first], { maxBytes: 100000 }), [other, first, second]);
+    });
+
+    it('should stop at the size limit and leave the rest for later blocks', async function() {
+      const high = await transfer(MINER, 1, 0.9);
+      const low = await transfer(GENESIS_ADDRESS, 1, 0.1);
+      const followUp = await transfer(MINER, 1000000, 0.9, genesis.timestamp + 2); // Larger than `low`
+
+      const selected = selectTransactions([low, followUp, high], { maxBytes: high.getSize() + low.getSize() });
+      assert.deepStrictEqual(selected, [high, low]);
+    });
+  });
+});
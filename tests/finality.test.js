@@ -0,0 +1,81 @@
+import assert from 'assert';
+import { Blockchain } from '../src/blockchain.js';
+import { checkCheckpoint, checkReorgDepth } from '../src/finality.js';
+import { loadConfig } from '../src/config.js';
+import { MINER as MINER_A, OTHER as MINER_B, resetChain, branch } from './helpers.js';
+
+describe('Checkpoints and reorganization depth', function() {
+  this.timeout(10000);
+
+  const blockchain = new Blockchain();
+  const defaultConfig = blockchain.config;
+  let genesis;
+
+  function useChainParams(params) {
+    blockchain.config = { ...defaultConfig, chain: { ...defaultConfig.chain, ...params } };
+  }
+
+  beforeEach(async function() {
+    genesis = await resetChain();
+
+    for (const block of branch(genesis, 3, { miner: MINER_A })) {
+      assert.ok(await blockchain.addBlock(block));
+    }
+  });
+
+  afterEach(function() {
+    blockchain.config = defaultConfig;
+  });
+
+  it('should compare blocks with the checkpoint at their height', function() {
+    const checkpoints = { 2: 'a'.repeat(64) };
+    assert.strictEqual(checkCheckpoint(2, 'a'.repeat(64), checkpoints), null);
+    assert.strictEqual(checkCheckpoint(3, 'b'.repeat(64), checkpoints), null);
+    assert.match(checkCheckpoint(2, 'b'.repeat(64), checkpoints), /checkpoint at height 2/);
+  });
+
+  it('should limit how many blocks a reorganization disconnects', function() {
+    assert.strictEqual(checkReorgDepth(10, 8, 2), null);
+    assert.match(checkReorgDepth(10, 7, 2), /disconnect 3 blocks/);
+    assert.strictEqual(checkReorgDepth(10, 0, 0), null);
+  });
+
+  it('should reject chains that contradict a checkpoint', async function() {
+    const competing = branch(genesis, 4, { miner: MINER_B, start: genesis.timestamp + 10 });
+    const competingData = [genesis, ...competing].map(block => block.toJSON());
+    useChainParams({ checkpoints: { 2: blockchain.getHeader(2).hash } });
+
+    assert.strictEqual((await Blockchain.validateChain(competingData, blockchain.config.chain)).error.height, 2);
+    assert.strictEqual(await blockchain.replaceChain(competingData), false);
+    assert.strictEqual(blockchain.getHeight(), 3);
+  });
+
+  it('should reject blocks that contradict a checkpoint', async function() {
+    const [next] = branch(blockchain.getLatestHeader(), 1, { miner: MINER_A });
+    useChainParams({ checkpoints: { 4: 'f'.repeat(64) } });
+
+    assert.strictEqual(await blockchain.addBlock(next), false);
+  });
+
+  it('should refuse branches forking deeper than the maximum depth', async function() {
+    const competing = branch(genesis, 4, { miner: MINER_B, start: genesis.timestamp + 10 });
+    const competingData = [genesis, ...competing].map(block => block.toJSON());
+
+    useChainParams({ maxReorgDepth: 2 });
+    assert.strictEqual(await blockchain.replaceChain(competingData), false);
+    assert.strictEqual(blockchain.getHeader(1).hash, (await blockchain.getBlock(1)).hash);
+    assert.strictEqual(await blockchain.getBalanceOfAddress(MINER_B), '0.00000000');
+
+    useChainParams({ maxReorgDepth: 3 });
+    assert.strictEqual(await blockchain.replaceChain(competingData), true);
+    assert.strictEqual(blockchain.getLatestHeader().hash, competing[3].hash);
+  });
+
+  it('should read checkpoints from the environment', function() {
+    const hash = 'a'.repeat(64);
+    const config = loadConfig({ argv: ['node', 'cli'], env: { CHECKPOINTS: `10:${hash}`, MAX_REORG_DEPTH: '6' } });
+    assert.deepStrictEqual({ ...config.chain.checkpoints }, { 10: hash });
+    assert.strictEqual(config.chain.maxReorgDepth, 6);
+    assert.throws(() => loadConfig({ argv: ['node', 'cli'], env: { CHECKPOINTS: '10:nothex' } }), /checkpoints/);
+  });
+});
@@ -0,0 +1,80 @@
+import assert from 'assert';
+import { Blockchain } from '../src/blockchain.js';
+import { isValidDifficulty, blockWork, chainWork, compareChainTips } from '../src/difficulty.js';
+import { MINER as MINER_A, OTHER as MINER_B, resetChain, blockAfter } from './helpers.js';
+
+describe('Fork choice', function() {
+  describe('chain work', function() {
+    it('should count expected hashes per block', function() {
+      assert.strictEqual(blockWork(0), 1n);
+      assert.strictEqual(blockWork(1), 1n);
+      assert.strictEqual(blockWork(16), 16n);
+      assert.strictEqual(chainWork([{ difficulty: 16 }, { difficulty: 4 }]), 20n);
+    });
+
+    it('should only accept non-negative integer difficulties', function() {
+      assert.ok(isValidDifficulty(0) && isValidDifficulty(16));
+      [1.5, -1, NaN, Infinity, 2 ** 53, '16', null].forEach(difficulty => assert.strictEqual(isValidDifficulty(difficulty), false));
+    });
+
+    it('should prefer more work, then the lower tip hash', function() {
+      assert.ok(compareChainTips({ work: 5n, hash: 'ff' }, { work: 4n, hash: '00' }) > 0);
+      assert.ok(compareChainTips({ work: 5n, hash: '0a' }, { work: 5n, hash: '0b' }) > 0);
+      assert.ok(compareChainTips({ work: 5n, hash: '0b' }, { work: 5n, hash: '0a' }) < 0);
+      assert.strictEqual(compareChainTips({ work: 5n, hash: '0a' }, { work: 5n, hash: '0a' }), 0);
+    });
+  });
+
+  describe('Blockchain', function() {
+    const blockchain = new Blockchain();
+    let genesis;
+    let tip;
+
+    // A competing block at height 1 whose hash is lower (or higher) than the local tip's
+    function competitor(lower) {
+      for (let offset = 2; ; offset++) {
+        const block = blockAfter(genesis, [], { miner: MINER_B, timestamp: genesis.timestamp + offset });
+        if ((block.hash < tip.hash) === lower) return block;
+      }
+    }
+
+    beforeEach(async function() {
+      genesis = await resetChain();
+      tip = blockAfter(genesis, [], { miner: MINER_A });
+      assert.ok(await blockchain.addBlock(tip));
+    });
+
+    it('should switch to an equal-work block with a lower hash', async function() {
+      const block = competitor(true);
+
+      assert.strictEqual(await blockchain.acceptForkBlock(block), true);
+      assert.strictEqual(blockchain.getLatestHeader().hash, block.hash);
+      assert.strictEqual(await blockchain.getBalanceOfAddress(MINER_A), '0.00000000');
+      assert.strictEqual(await blockchain.getBalanceOfAddress(MINER_B), '100.00000000');
+    });
+
+    it('should keep its tip against an equal-work block with a higher hash', async function() {
+      assert.strictEqual(await blockchain.acceptForkBlock(competitor(false)), false);
+      assert.strictEqual(blockchain.getLatestHeader().hash, tip.hash);
+    });
+
+    it('should reject branches with a malformed difficulty before weighing them', async function() {
+      const block = competitor(true);
+      block.difficulty = 1.5;
+      assert.strictEqual(await blockchain.acceptForkBlock(block), false);
+      assert.strictEqual(await blockchain.replaceChain([genesis.toJSON(), { ...block.toJSON(), difficulty: NaN }]), false);
+      assert.strictEqual(blockchain.getLatestHeader().hash, tip.hash);
+    });
+
+    it('should apply the same rule to full chains regardless of arrival order', async function() {
+      const loser = competitor(false);
+      assert.strictEqual(await blockchain.replaceChain([genesis, loser].map(block => block.toJSON())), false);
+
+      const winner = competitor(true);
+      assert.strictEqual(await blockchain.replaceChain([genesis, winner].map(block => block.toJSON())), true);
+      assert.strictEqual(await blockchain.replaceChain([genesis, tip].map(block => block.toJSON())), false);
+      assert.strictEqual(blockchain.getLatestHeader().hash, winner.hash);
+      assert.strictEqual(blockchain.getChainTip().work, 2n);
+    });
+  });
+});
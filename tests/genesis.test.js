@@ -0,0 +1,110 @@
+import assert from 'assert';
+import fs from 'fs';
+import os from 'os';
+import path from 'path';
+import { storage } from '../src/db.js';
+import { Blockchain } from '../src/blockchain.js';
+import { loadGenesisSpec, validateGenesisSpec, buildGenesisBlock } from '../src/genesis.js';
+
+const SPEC = {
+  chainId: 'aibtcc-test',
+  timestamp: 1727740800000,
+  difficulty: 0,
+  extraData: 'test genesis',
+  allocations: [
+    { address: '6c7f05cca415fd2073de8ea8853834', amount: '600000' },
+    { address: 'c785e6e5b281c050dd9f18b28a8ce2', amount: '400000' }
+  ]
+};
+
+describe('Genesis specification', function() {
+  const blockchain = new Blockchain();
+  const defaultConfig = blockchain.config;
+  const specFile = path.join(os.tmpdir(), `genesis-test-${process.pid}.json`);
+
+  function useGenesisFile(spec) {
+    fs.writeFileSync(specFile, JSON.stringify(spec));
+    blockchain.config = { ...defaultConfig, chain: { ...defaultConfig.chain, genesisFile: specFile } };
+  }
+
+  beforeEach(function() {
+    storage.reset();
+    blockchain.clearChain();
+    blockchain.config = defaultConfig;
+  });
+
+  afterEach(function() {
+    blockchain.config = defaultConfig;
+    fs.rmSync(specFile, { force: true });
+  });
+
+  it('should build a byte-identical block from the same specification', function() {
+    const first = buildGenesisBlock(SPEC);
+    const second = buildGenesisBlock(structuredClone(SPEC));
+    assert.strictEqual(JSON.stringify(first.toJSON()), JSON.stringify(second.toJSON()));
+    assert.strictEqual(first.transactions.length, 2);
+    assert.strictEqual(first.timestamp, SPEC.timestamp);
+  });
+
+  it('should commit to the chain ID and extra data', function() {
+    const hash = buildGenesisBlock(SPEC).hash;
+    assert.notStrictEqual(buildGenesisBlock({ ...SPEC, chainId: 'aibtcc-other' }).hash, hash);
+    assert.notStrictEqual(buildGenesisBlock({ ...SPEC, extraData: 'other' }).hash, hash);
+  });
+
+  it('should report invalid specifications', function() {
+    assert.deepStrictEqual(validateGenesisSpec(SPEC), []);
+    const problems = validateGenesisSpec({
+      ...SPEC,
+      timestamp: 'now',
+      allocations: [SPEC.allocations[0], SPEC.allocations[0], { address: 'short', amount: '-1' }]
+    });
+    assert.strictEqual(problems.length, 4);
+    assert.throws(() => loadGenesisSpec(path.join(os.tmpdir(), 'missing-genesis.json')), /Cannot read genesis/);
+  });
+
+  it('should ship a valid genesis.json and use it by default', async function() {
+    const spec = loadGenesisSpec('genesis.json');
+    await blockchain.initializeGenesisBlock();
+    assert.strictEqual(blockchain.getHeader(0).hash, buildGenesisBlock(spec).hash);
+    assert.strictEqual(await blockchain.getBalanceOfAddress(spec.allocations[0].address), '1000000.00000000');
+    assert.strictEqual(blockchain.genesisSupply.toFixed(8), '1000000.00000000');
+  });
+
+  it('should store every allocation and pass chain validation', async function() {
+    useGenesisFile(SPEC);
+    await blockchain.initializeGenesisBlock();
+
+    assert.strictEqual(await blockchain.getBalanceOfAddress(SPEC.allocations[0].address), '600000.00000000');
+    assert.strictEqual(await blockchain.getBalanceOfAddress(SPEC.allocations[1].address), '400000.00000000');
+    assert.strictEqual(await blockchain.isChainValid(), true);
+  });
+
+  it('should leave every unmoved genesis allocation out of the circulating supply', async function() {
+    useGenesisFile(SPEC);
+    await blockchain.initializeGenesisBlock();
+
+    const supply = await blockchain.getSupplyInfo();
+    assert.strictEqual(supply.issued, '1000000.00000000');
+    assert.strictEqual(supply.circulating, '0.00000000');
+  });
+
+  it('should refuse a stored genesis block that does not match the specification', async function() {
+    useGenesisFile(SPEC);
+    await blockchain.initializeGenesisBlock();
+    blockchain.clearChain();
+
+    useGenesisFile({ ...SPEC, chainId: 'aibtcc-other' });
+    await assert.rejects(blockchain.initializeGenesisBlock(), /does not match/);
+  });
+
+  it('should take the genesis supply from the allocations, up to chain.maxSupply', async function() {
+    useGenesisFile(SPEC);
+    blockchain.config = { ...blockchain.config, chain: { ...blockchain.config.chain, maxSupply: 999999 } };
+    await assert.rejects(blockchain.initializeGenesisBlock(), /more than chain\.maxSupply/);
+
+    useGenesisFile({ ...SPEC, allocations: [SPEC.allocations[0]] });
+    await blockchain.initializeGenesisBlock();
+    assert.strictEqual((await blockchain.getSupplyInfo()).scheduled, '600000.00000000');
+  });
+});
@@ -1,49 +1,135 @@
-const { Transaction, Blockchain } = require('../src/blockchain');
-const EC = require('elliptic').ec;
-
-const ec = new EC('secp256k1');
-
-const keyPair = ec.genKeyPair();
-const publicKey = keyPair.getPublic('hex');
-
-function createSignedTx(amount = 10, toAddress = 'b2') {
-  if (amount <= 0) throw new Error('Amount must be positive');
-
-  const tx = new Transaction(publicKey, toAddress, amount);
-  tx.timestamp = Date.now();
-  tx.sign(keyPair);
+import fs from 'fs';
+import os from 'os';
+import path from 'path';
+import { storage } from '../src/db.js';
+import { blockchainInstance, Block, Transaction } from '../src/blockchain.js';
+import { blockSubsidy } from '../src/emission.js';
+
+// Wallets with key files in src/wallets, so tests can sign for them
+const GENESIS_ADDRESS = '6c7f05cca415fd2073de8ea8853834'; // Receives the genesis allocation
+const MINER = '59a8277a36bffda17f9a997e5f7c23';
+const RECIPIENT = 'c785e6e5b281c050dd9f18b28a8ce2';
+const OTHER = '9920a36cdafd9bc0b43d6e222b49a3';
+
+const CHAIN_ID = 'aibtcc-test';
+const GENESIS_BALANCE = 1000;
+
+// Genesis specification every test chain starts from
+const GENESIS_SPEC = {
+  chainId: CHAIN_ID,
+  timestamp: 1727740800000,
+  difficulty: 0,
+  allocations: [{ address: GENESIS_ADDRESS, amount: String(GENESIS_BALANCE) }]
+};
+const genesisFile = path.join(os.tmpdir(), `test-genesis-${process.pid}.json`);
+
+/**
+ * Empties the storage, the chain and the mempool, then points the node at
+ * GENESIS_SPEC and stores its genesis block, which allocates GENESIS_BALANCE
+ * to GENESIS_ADDRESS. Meant for `beforeEach`.
+ * @returns {Promise<Block>} - The genesis block
+ */
+async function resetChain() {
+  storage.reset();
+  blockchainInstance.clearChain();
+  blockchainInstance.pendingTransactions = [];
+  blockchainInstance.transactionPool.clear();
+
+  fs.writeFileSync(genesisFile, JSON.stringify(GENESIS_SPEC));
+  const { config } = blockchainInstance;
+  blockchainInstance.config = { ...config, chain: { ...config.chain, genesisFile } };
+  await blockchainInstance.initializeGenesisBlock();
+  return blockchainInstance.getBlock(0);
+}
 
+/**
+ * A transfer signed by `from`, made for the chain the node is on.
+ * @param {string} from - One of the wallets above
+ * @param {number|string} amount
+ * @param {Object} [options] - `to`, `fee`, `timestamp` and `chainId` of the transaction
+ * @returns {Promise<Transaction>}
+ */
+async function payment(from, amount, { to = RECIPIENT, fee = 0, timestamp = Date.now(), chainId = blockchainInstance.getChainId() } = {}) {
+  const tx = new Transaction(from, to, amount, timestamp, null, "", null, "", null, fee, chainId);
+  await tx.signWithAddress(from);
   return tx;
 }
 
-
-async function createBlockchainWithTx() {
-  const blockchain = new Blockchain();
-  const tx = createSignedTx(50);
-  blockchain.addTransaction(tx);
-  await blockchain.minePendingTransactions('miner-address');
-  return blockchain;
+/**
+ * A coinbase paying `amount` to the miner, for the block at `height`, by
+ * default the next one of the chain.
+ * @param {number|string} amount
+ * @param {Object} [options] - `miner`, `height`, `timestamp` and `chainId` of the transaction
+ * @returns {Transaction}
+ */
+function coinbase(amount, { miner = MINER, height = blockchainInstance.getChainLength(), timestamp = Date.now(), chainId = blockchainInstance.getChainId() } = {}) {
+  return new Transaction(null, miner, amount, timestamp, null, "", String(height), "", null, 0, chainId);
 }
 
-async function createBCWithMined() {
-  const blockchain = new Blockchain();
-  await blockchain.minePendingTransactions('miner-address');
-  return blockchain;
+/**
+ * A mined block on top of `previous` holding exactly `transactions`. Unless
+ * given, the difficulty is the one the chain expects next when `previous` is
+ * its tip, and the difficulty of `previous` otherwise.
+ * @param {Block|Object} previous - A block or header
+ * @param {Transaction[]} transactions - Coinbase included
+ * @param {Object} [options] - `timestamp`, `difficulty` and `chainId` of the block
+ * @returns {Block}
+ */
+function blockWith(previous, transactions, {
+  timestamp = previous.timestamp + 1,
+  difficulty = blockchainInstance.getLatestHeader().hash === previous.hash
+    ? blockchainInstance.getNextDifficulty()
+    : Number(previous.difficulty),
+  chainId = blockchainInstance.getChainId()
+} = {}) {
+  const block = new Block(previous.index + 1, previous.hash, timestamp, transactions, difficulty, chainId);
+  block.mineBlock(difficulty);
+  return block;
 }
 
-module.exports = {
-  createSignedTx,
-  createBlockchainWithTx,
-  createBCWithMined,
-  signingKey: keyPair
-};
-
-
-
-
-
-
-
-
+/**
+ * A block on top of `previous` holding `transactions`, followed by a coinbase
+ * claiming the subsidy and their fees.
+ * @param {Block|Object} previous - A block or header
+ * @param {Transaction[]} [transactions]
+ * @param {Object} [options] - `miner` of the block, and the options of `blockWith`
+ * @returns {Block}
+ */
+function blockAfter(previous, transactions = [], { miner = MINER, timestamp = previous.timestamp + 1, ...options } = {}) {
+  const reward = transactions.reduce((total, tx) => total.plus(tx.fee || 0), blockSubsidy(previous.index + 1, blockchainInstance.getChainParams()));
+  const coinbaseTx = coinbase(reward.toFixed(8), { miner, height: previous.index + 1, timestamp, chainId: options.chainId });
+  return blockWith(previous, [...transactions, coinbaseTx], { timestamp, ...options });
+}
 
+/**
+ * `length` empty blocks on top of `from`, one millisecond apart.
+ * @param {Block|Object} from - Block or header the branch starts from
+ * @param {number} length
+ * @param {Object} [options] - `miner` of the blocks and `start`, the first timestamp
+ * @returns {Block[]}
+ */
+function branch(from, length, { miner = MINER, start = from.timestamp + 1 } = {}) {
+  const blocks = [];
+  let previous = from;
+  for (let i = 0; i < length; i++) {
+    previous = blockAfter(previous, [], { miner, timestamp: start + i });
+    blocks.push(previous);
+  }
+  return blocks;
+}
 
+export {
+  GENESIS_ADDRESS,
+  MINER,
+  RECIPIENT,
+  OTHER,
+  CHAIN_ID,
+  GENESIS_BALANCE,
+  GENESIS_SPEC,
+  resetChain,
+  payment,
+  coinbase,
+  blockWith,
+  blockAfter,
+  branch,
+};
@@ -0,0 +1,21 @@
+import assert from 'assert';
+import { LRUCache } from '../src/lruCache.js';
+
+describe('LRUCache', function() {
+  it('should evict the least recently used entry', function() {
+    const cache = new LRUCache(2);
+    cache.set('a', 1);
+    cache.set('b', 2);
+    cache.get('a');
+    cache.set('c', 3);
+
+    assert.strictEqual(cache.size, 2);
+    assert.strictEqual(cache.has('b'), false);
+    assert.strictEqual(cache.get('a'), 1);
+    assert.strictEqual(cache.get('c'), 3);
+  });
+
+  it('should reject a non-positive capacity', function() {
+    assert.throws(() => new LRUCache(0), /positive integer/);
+  });
+});
@@ -0,0 +1,98 @@
+import assert from 'assert';
+import crypto from 'crypto';
+import { Blockchain, Block, Transaction } from '../src/blockchain.js';
+import { Miner } from '../src/miner.js';
+import { meetsDifficulty } from '../src/difficulty.js';
+import { GENESIS_ADDRESS, MINER, OTHER, resetChain, payment, blockAfter } from './helpers.js';
+const UNREACHABLE_DIFFICULTY = 1e15;
+
+describe('Worker thread mining', function() {
+  this.timeout(20000);
+
+  function candidateBlock() {
+    return new Block(1, 'a'.repeat(64), Date.now(), [new Transaction(null, MINER, 100)], 0);
+  }
+
+  it('should split the block hash around the nonce', function() {
+    const block = candidateBlock();
+    block.nonce = 12345;
+    const { prefix, suffix } = block.hashParts();
+    assert.strictEqual(crypto.createHash('sha256').update(prefix + 12345 + suffix).digest('hex'), block.calculateHash());
+  });
+
+  it('should find a nonce meeting the difficulty across several workers', async function() {
+    const block = candidateBlock();
+    const miner = new Miner({ threads: 2 });
+
+    assert.strictEqual(await miner.mine(block, 5000), true);
+    assert.ok(meetsDifficulty(block.hash, 5000));
+    assert.strictEqual(block.hash, block.calculateHash());
+    assert.strictEqual(miner.isMining(), false);
+  });
+
+  it('should report progress and stop when cancelled', async function() {
+    const miner = new Miner({ threads: 2, progressIntervalMs: 20 });
+    const reports = [];
+    const mining = miner.mine(candidateBlock(), UNREACHABLE_DIFFICULTY, { onProgress: report => reports.push(report) });
+
+    await new Promise(resolve => setTimeout(resolve, 300));
+    assert.strictEqual(miner.isMining(), true);
+    assert.strictEqual(miner.cancel(), true);
+    assert.strictEqual(await mining, false);
+    assert.strictEqual(miner.cancel(), false);
+
+    assert.ok(reports.length > 0);
+    assert.ok(reports[reports.length - 1].hashes > 0 && reports[reports.length - 1].hashrate > 0);
+  });
+
+  describe('Blockchain', function() {
+    const blockchain = new Blockchain();
+
+    beforeEach(resetChain);
+
+    afterEach(function() {
+      delete blockchain.getNextDifficulty; // Back to the prototype's
+    });
+
+    it('should keep the chain and mempool when mining is cancelled', async function() {
+      const tx = await payment(GENESIS_ADDRESS, 10);
+      await blockchain.addPendingTransaction(tx);
+      blockchain.getNextDifficulty = () => UNREACHABLE_DIFFICULTY; // Too hard to find before the cancellation
+
+      const mining = blockchain.minePendingTransactions(MINER);
+      await new Promise(resolve => setTimeout(resolve, 200));
+      blockchain.cancelMining('test');
+      await mining;
+
+      assert.strictEqual(blockchain.getHeight(), 0);
+      assert.deepStrictEqual(blockchain.pendingTransactions.map(pending => pending.hash), [tx.hash]);
+    });
+
+    it('should not let a received block take the height of a mined block being saved', async function() {
+      const tx = await payment(GENESIS_ADDRESS, 10);
+      await blockchain.addPendingTransaction(tx);
+      const received = blockAfter(blockchain.getLatestHeader(), [], { miner: OTHER });
+
+      // The received block arrives while the mined block is being saved
+      const save = Block.prototype.save;
+      let adding;
+      Block.prototype.save = async function(...args) {
+        if (!adding && this.transactions.some(mined => mined.hash === tx.hash)) {
+          adding = blockchain.addBlock(received, { broadcast: false });
+          await new Promise(resolve => setTimeout(resolve, 50));
+        }
+        return save.apply(this, args);
+      };
+      try {
+        assert.ok(await blockchain.minePendingTransactions(MINER));
+      } finally {
+        Block.prototype.save = save;
+      }
+
+      assert.strictEqual(await adding, false);
+      assert.strictEqual(blockchain.getHeight(), 1);
+      assert.strictEqual(blockchain.getLatestHeader().hash, (await blockchain.getLatestBlock()).hash);
+      assert.notStrictEqual(blockchain.getLatestHeader().hash, received.hash);
+    });
+  });
+});
@@ -0,0 +1,107 @@
+import assert from 'assert';
+import { Blockchain } from '../src/blockchain.js';
+import { MiningScheduler } from '../src/miningScheduler.js';
+import { loadConfig } from '../src/config.js';
+import { GENESIS_ADDRESS, MINER, resetChain, payment } from './helpers.js';
+
+describe('Mining scheduler', function() {
+  this.timeout(10000);
+
+  const blockchain = new Blockchain();
+  const defaults = blockchain.config.mining;
+  let scheduler;
+
+  function schedulerWith(settings) {
+    scheduler = new MiningScheduler(blockchain, { ...defaults, rewardAddress: MINER, ...settings });
+    return scheduler;
+  }
+
+  async function submitPayment(amount = 10, timestamp = Date.now()) {
+    const tx = await payment(GENESIS_ADDRESS, amount, { timestamp });
+    await blockchain.addPendingTransaction(tx);
+    return tx;
+  }
+
+  beforeEach(resetChain);
+
+  afterEach(function() {
+    if (scheduler) {
+      scheduler.stop();
+      scheduler = null;
+    }
+  });
+
+  it('should decide when to mine according to the policy', async function() {
+    const now = Date.now();
+    assert.strictEqual(schedulerWith({ policy: 'interval' }).shouldMine(now), false); // Nothing pending
+    await submitPayment(10, now - 30000);
+
+    assert.strictEqual(schedulerWith({ policy: 'interval' }).shouldMine(now), true);
+    assert.strictEqual(schedulerWith({ policy: 'threshold', minPendingTransactions: 2 }).shouldMine(now), false);
+    assert.strictEqual(schedulerWith({ policy: 'latency', maxLatencySeconds: 60 }).shouldMine(now), false);
+    assert.strictEqual(schedulerWith({ policy: 'latency', maxLatencySeconds: 20 }).shouldMine(now), true);
+    assert.strictEqual(schedulerWith({ policy: 'onDemand' }).shouldMine(now), false);
+
+    await submitPayment(5, now - 10000);
+    assert.strictEqual(schedulerWith({ policy: 'threshold', minPendingTransactions: 2 }).shouldMine(now), true);
+  });
+
+  it('should mine on demand, one attempt at a time', async function() {
+    await submitPayment();
+    schedulerWith({ policy: 'onDemand' });
+    assert.strictEqual(scheduler.start(), false);
+
+    const [first, second] = await Promise.all([scheduler.mineNow(), scheduler.mineNow()]);
+    assert.strictEqual(first.index, 1);
+    assert.strictEqual(second, first);
+    assert.strictEqual(await blockchain.getBalanceOfAddress(MINER), '100.00000000');
+
+    const status = scheduler.status();
+    assert.strictEqual(status.blocksMined, 1);
+    assert.strictEqual(status.pendingTransactions, 0);
+    assert.strictEqual(await scheduler.mineNow(), null); // Nothing left to mine
+  });
+
+  it('should mine on its own schedule until stopped', async function() {
+    schedulerWith({ policy: 'threshold', minPendingTransactions: 1, pendingCheckIntervalSeconds: 0.02 });
+    assert.strictEqual(scheduler.start(), true);
+    assert.strictEqual(scheduler.status().active, true);
+
+    await submitPayment();
+    for (let i = 0; i < 100 && blockchain.getHeight() === 0; i++) {
+      await new Promise(resolve => setTimeout(resolve, 20));
+    }
+    assert.strictEqual(blockchain.getHeight(), 1);
+
+    scheduler.stop();
+    assert.strictEqual(scheduler.status().active, false);
+    await submitPayment(5);
+    await new Promise(resolve => setTimeout(resolve, 100));
+    assert.strictEqual(blockchain.getHeight(), 1);
+  });
+
+  it('should not mine without a reward address or when disabled', async function() {
+    await submitPayment();
+    schedulerWith({ rewardAddress: null });
+    assert.strictEqual(scheduler.start(), false);
+    await assert.rejects(scheduler.mineNow(), /reward address/);
+    assert.throws(() => scheduler.setRewardAddress('short'), /24 to 30/);
+    scheduler.setRewardAddress(MINER);
+    assert.strictEqual(scheduler.status().rewardAddress, MINER);
+
+    assert.strictEqual(schedulerWith({ enabled: false }).status().policy, 'disabled');
+    assert.strictEqual(scheduler.start(), false);
+    await assert.rejects(scheduler.mineNow(), /disabled/);
+    assert.strictEqual(blockchain.getHeight(), 0);
+  });
+
+  it('should read the policy and reward address from the configuration', function() {
+    const argv = ['node', 'cli'];
+    assert.strictEqual(loadConfig({ argv, env: {} }).mining.rewardAddress, null);
+
+    const config = loadConfig({ argv, env: { MINING_POLICY: 'latency', MINER_ADDRESS: MINER } });
+    assert.strictEqual(config.mining.policy, 'latency');
+    assert.strictEqual(config.mining.rewardAddress, MINER);
+    assert.throws(() => loadConfig({ argv, env: { MINING_POLICY: 'sometimes' } }), /mining\.policy/);
+  });
+});
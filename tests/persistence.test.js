@@ -0,0 +1,50 @@
+import assert from 'assert';
+import { storage } from '../src/db.js';
+import { Block } from '../src/block.js';
+import { Transaction } from '../src/transaction.js';
+
+// Runs against the in-memory backend (STORAGE_BACKEND=memory in the test script)
+describe('Block persistence', function() {
+  function createBlock(index, reward) {
+    const rewardTx = new Transaction(null, '59a8277a36bffda17f9a997e5f7c23', reward, 1700000000000 + index);
+    return new Block(index, index === 0 ? null : 'parent', 1700000000000 + index, [rewardTx], 0);
+  }
+
+  beforeEach(function() {
+    storage.reset();
+  });
+
+  it('should store the block, its transactions, balances and merkle data', async function() {
+    const block = createBlock(0, 100);
+    await block.save();
+
+    assert.ok(await storage.getBlockByHash(block.hash));
+    assert.strictEqual((await storage.getTransactionsByBlockHash(block.hash)).length, 1);
+    assert.ok(await storage.getMerkleProof(block.transactions[0].hash));
+    assert.strictEqual(await storage.getBalance('59a8277a36bffda17f9a997e5f7c23'), '100.00000000');
+  });
+
+  it('should not apply balances twice when a stored block is saved again', async function() {
+    const block = createBlock(0, 100);
+    assert.strictEqual(await block.save(), true);
+    assert.strictEqual(await block.save(), false);
+
+    assert.strictEqual(await storage.getBalance('59a8277a36bffda17f9a997e5f7c23'), '100.00000000');
+  });
+
+  it('should leave nothing behind when a write fails midway', async function() {
+    const block = createBlock(0, 100);
+    const saveMerkleProof = storage.saveMerkleProof;
+    storage.saveMerkleProof = async () => { throw new Error('disk full'); };
+
+    try {
+      await assert.rejects(block.save(), /disk full/);
+    } finally {
+      storage.saveMerkleProof = saveMerkleProof;
+    }
+
+    assert.strictEqual(await storage.getBlockByHash(block.hash), null);
+    assert.strictEqual(await storage.getTransactionByHash(block.transactions[0].hash), null);
+    assert.strictEqual(await storage.getBalance('59a8277a36bffda17f9a997e5f7c23'), null);
+  });
+});
@@ -0,0 +1,42 @@
+import assert from 'assert';
+import { storage } from '../src/db.js';
+import { Blockchain } from '../src/blockchain.js';
+import { GENESIS_ADDRESS, MINER, resetChain, blockAfter } from './helpers.js';
+
+describe('Blockchain.reindex', function() {
+  const blockchain = new Blockchain();
+
+  beforeEach(async function() {
+    const block = blockAfter(await resetChain());
+    await block.save();
+    blockchain.appendBlock(block);
+  });
+
+  it('should report balance differences without writing in dry-run mode', async function() {
+    await storage.updateBalance(MINER, 5);
+    await storage.deleteMerkleData(blockchain.getHeader(1).hash);
+
+    const report = await blockchain.reindex({ dryRun: true });
+
+    assert.strictEqual(report.blocks, 2);
+    assert.deepStrictEqual(report.balanceDifferences, [{ address: MINER, stored: '105.00000000', expected: '100.00000000' }]);
+    assert.deepStrictEqual(report.blocksWithMissingMerkleData, [blockchain.getHeader(1).hash]);
+    assert.strictEqual(await storage.getBalance(MINER), '105.00000000');
+  });
+
+  it('should rebuild balances and Merkle data from the blocks', async function() {
+    await storage.updateBalance(MINER, 5);
+    await storage.updateBalance('ffffffffffffffffffffffffffffff', 1);
+    await storage.deleteMerkleData(blockchain.getHeader(1).hash);
+    const phases = new Set();
+
+    await blockchain.reindex({ onProgress: ({ phase }) => phases.add(phase) });
+
+    assert.strictEqual(await storage.getBalance(MINER), '100.00000000');
+    assert.strictEqual(await storage.getBalance(GENESIS_ADDRESS), '1000.00000000');
+    assert.strictEqual(await storage.getBalance('ffffffffffffffffffffffffffffff'), null);
+    assert.ok(await storage.getMerkleProof((await blockchain.getBlock(1)).transactions[0].hash));
+    assert.deepStrictEqual([...phases], ['scan', 'rebuild']);
+    assert.strictEqual(await blockchain.validateDatabaseState(), true);
+  });
+});
@@ -0,0 +1,72 @@
+import assert from 'assert';
+import { storage } from '../src/db.js';
+import { Blockchain, Block, Transaction } from '../src/blockchain.js';
+import { GENESIS_ADDRESS, MINER as MINER_A, OTHER as MINER_B, RECIPIENT, resetChain, payment, blockAfter } from './helpers.js';
+
+describe('Chain reorganization', function() {
+  this.timeout(10000);
+
+  const blockchain = new Blockchain();
+
+  beforeEach(resetChain);
+
+  it('should undo abandoned blocks and return their transactions to pending', async function() {
+    const genesis = await blockchain.getBlock(0);
+
+    const tx = await payment(GENESIS_ADDRESS, 10);
+    await blockchain.addPendingTransaction(tx);
+    await blockchain.minePendingTransactions(MINER_A);
+
+    assert.strictEqual(blockchain.getChainLength(), 2);
+    assert.strictEqual(await blockchain.getBalanceOfAddress(RECIPIENT), '10.00000000');
+    assert.strictEqual(blockchain.pendingTransactions.length, 0);
+
+    const b1 = blockAfter(genesis, [], { miner: MINER_B, timestamp: genesis.timestamp + 1000 });
+    const b2 = blockAfter(b1, [], { miner: MINER_B, timestamp: genesis.timestamp + 2000 });
+    const replaced = await blockchain.replaceChain([genesis, b1, b2].map(block => block.toJSON()));
+
+    assert.strictEqual(replaced, true);
+    assert.deepStrictEqual(blockchain.headers.map(header => header.hash), [genesis.hash, b1.hash, b2.hash]);
+    assert.strictEqual(await blockchain.getBalanceOfAddress(MINER_A), '0.00000000');
+    assert.strictEqual(await blockchain.getBalanceOfAddress(MINER_B), '200.00000000');
+    assert.strictEqual(await blockchain.getBalanceOfAddress(RECIPIENT), '0.00000000');
+    assert.strictEqual(await blockchain.getBalanceOfAddress(GENESIS_ADDRESS), '1000.00000000');
+    assert.strictEqual(await storage.getTransactionByHash(tx.hash), null);
+
+    assert.deepStrictEqual(blockchain.pendingTransactions.map(pending => pending.hash), [tx.hash]);
+    assert.ok(await storage.getPendingTransactionByHash(tx.hash));
+  });
+
+  it('should switch to a branch sent without the blocks both chains share', async function() {
+    const genesis = await blockchain.getBlock(0);
+    const a1 = blockAfter(genesis, [], { miner: MINER_A, timestamp: genesis.timestamp + 1000 });
+    assert.ok(await blockchain.addBlock(a1));
+
+    const b1 = blockAfter(genesis, [], { miner: MINER_B, timestamp: genesis.timestamp + 1000 });
+    const b2 = blockAfter(b1, [], { miner: MINER_B, timestamp: genesis.timestamp + 2000 });
+    assert.strictEqual(await blockchain.replaceChain([b2.toJSON()]), false); // Its parent is unknown
+    assert.strictEqual(await blockchain.replaceChain([b1, b2].map(block => block.toJSON())), true);
+    assert.deepStrictEqual(blockchain.headers.map(header => header.hash), [genesis.hash, b1.hash, b2.hash]);
+  });
+
+  it('should describe the main chain to peers with a block locator', async function() {
+    let previous = await blockchain.getBlock(0);
+    for (let i = 1; i <= 14; i++) {
+      previous = blockAfter(previous, [], { timestamp: previous.timestamp + 1000 }); // Difficulty is retargeted at height 10
+      assert.ok(await blockchain.addBlock(previous));
+    }
+
+    const heights = blockchain.getBlockLocator().map(hash => blockchain.getHeaderByHash(hash).index);
+    assert.deepStrictEqual(heights, [14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 3, 0]);
+    assert.strictEqual(blockchain.findLocatorFork(['f'.repeat(64), blockchain.getHeader(7).hash, blockchain.getHeader(3).hash]), 7);
+    assert.strictEqual(blockchain.findLocatorFork(['f'.repeat(64)]), -1);
+  });
+
+  it('should reject a chain with a different genesis block', async function() {
+    const otherGenesis = new Block(0, null, 1, [new Transaction(null, MINER_B, 5, 1)], 0);
+    const b1 = blockAfter(otherGenesis, [], { miner: MINER_B, timestamp: 2 });
+
+    assert.strictEqual(await blockchain.replaceChain([otherGenesis, b1].map(block => block.toJSON())), false);
+    assert.strictEqual(blockchain.getChainLength(), 1);
+  });
+});
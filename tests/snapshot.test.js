@@ -0,0 +1,80 @@
+import assert from 'assert';
+import fs from 'fs';
+import os from 'os';
+import path from 'path';
+import { storage } from '../src/db.js';
+import { Blockchain } from '../src/blockchain.js';
+import { exportChain, importChain } from '../src/snapshot.js';
+import { GENESIS_ADDRESS, MINER, GENESIS_SPEC, resetChain, branch } from './helpers.js';
+
+describe('Chain snapshots', function() {
+  const blockchain = new Blockchain();
+  const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'aibtcc-snapshot-'));
+  let hashes;
+
+  beforeEach(async function() {
+    for (const block of branch(await resetChain(), 3)) {
+      assert.ok(await blockchain.addBlock(block));
+    }
+    hashes = blockchain.headers.map(header => header.hash);
+  });
+
+  for (const fileName of ['chain.ndjson', 'chain.ndjson.gz']) {
+    it(`should restore the chain on an empty node from ${fileName}`, async function() {
+      const file = path.join(dir, fileName);
+      const header = await exportChain(blockchain, file);
+      assert.strictEqual(header.blockCount, 4);
+
+      storage.reset();
+      blockchain.clearChain();
+      const result = await importChain(blockchain, file);
+
+      assert.strictEqual(result.imported, 4);
+      assert.deepStrictEqual(blockchain.headers.map(header => header.hash), hashes);
+      assert.strictEqual(await storage.getBalance(MINER), '300.00000000');
+    });
+  }
+
+  it('should skip blocks the node already has and append the rest', async function() {
+    const file = path.join(dir, 'range.ndjson');
+    await exportChain(blockchain, file, { fromHeight: 2 });
+
+    const tip = await blockchain.getLatestBlock();
+    blockchain.truncateChain(tip.index - 1);
+    await storage.withTransaction(store => tip.disconnect(store));
+    const result = await importChain(blockchain, file);
+
+    assert.deepStrictEqual([result.skipped, result.imported], [1, 1]);
+    assert.strictEqual(blockchain.getLatestHeader().hash, hashes[3]);
+  });
+
+  it('should reject a block that fails validation', async function() {
+    const file = path.join(dir, 'tampered.ndjson');
+    await exportChain(blockchain, file);
+    const lines = fs.readFileSync(file, 'utf8').trim().split('\n');
+    const record = JSON.parse(lines[4]);
+    record.transactions[0].toAddress = GENESIS_ADDRESS;
+    lines[4] = JSON.stringify(record);
+    fs.writeFileSync(file, lines.join('\n'));
+
+    storage.reset();
+    blockchain.clearChain();
+    await assert.rejects(importChain(blockchain, file), /block 3/);
+    assert.strictEqual(blockchain.getChainLength(), 3);
+  });
+
+  it('should refuse a genesis block other than the one of the genesis specification', async function() {
+    const file = path.join(dir, 'other-genesis.ndjson');
+    await exportChain(blockchain, file);
+
+    const specFile = path.join(dir, 'other-genesis.json');
+    fs.writeFileSync(specFile, JSON.stringify({ ...GENESIS_SPEC, chainId: 'aibtcc-other' }));
+    blockchain.config = { ...blockchain.config, chain: { ...blockchain.config.chain, genesisFile: specFile } };
+    storage.reset();
+    blockchain.clearChain();
+
+    await assert.rejects(importChain(blockchain, file), /does not match/);
+    assert.strictEqual(blockchain.getChainLength(), 0);
+    assert.strictEqual(await storage.getBlockByIndex(0), null);
+  });
+});
@@ -0,0 +1,216 @@
+import assert from 'assert';
+import { MemoryStorage } from '../src/storage/memoryStorage.js';
+import { SqlStorage } from '../src/storage/sqlStorage.js';
+import { migrate, assertSchemaIsCurrent, LATEST_SCHEMA_VERSION } from '../src/storage/migrator.js';
+
+describe('MemoryStorage', function() {
+  let storage;
+
+  beforeEach(function() {
+    storage = new MemoryStorage();
+  });
+
+  describe('blocks', function() {
+    it('should return saved blocks by hash and index', async function() {
+      await storage.saveBlock({ hash: 'h1', previous_hash: 'h0', index: 1, timestamp: 2 });
+      await storage.saveBlock({ hash: 'h0', previous_hash: null, index: 0, timestamp: 1 });
+
+      assert.strictEqual((await storage.getBlockByHash('h1')).index, 1);
+      assert.strictEqual((await storage.getBlockByIndex(0)).hash, 'h0');
+      assert.deepStrictEqual((await storage.getAllBlocks()).map(b => b.hash), ['h0', 'h1']);
+    });
+
+    it('should return blocks within a height range', async function() {
+      for (let index = 0; index < 4; index++) {
+        await storage.saveBlock({ hash: `h${index}`, index });
+      }
+
+      assert.deepStrictEqual((await storage.getBlocksByHeightRange(1, 2)).map(b => b.hash), ['h1', 'h2']);
+      assert.deepStrictEqual((await storage.getBlocksByHeightRange(3, 9)).map(b => b.hash), ['h3']);
+    });
+
+    it('should reject duplicate blocks with ER_DUP_ENTRY', async function() {
+      await storage.saveBlock({ hash: 'h0', index: 0 });
+      await assert.rejects(storage.saveBlock({ hash: 'h0', index: 0 }), { code: 'ER_DUP_ENTRY' });
+    });
+  });
+
+  describe('transactions', function() {
+    it('should return block transactions ordered by index_in_block', async function() {
+      await storage.saveTransaction({ hash: 't2', block_hash: 'b', index_in_block: 1 });
+      await storage.saveTransaction({ hash: 't1', block_hash: 'b', index_in_block: 0 });
+      await storage.saveTransaction({ hash: 't3', block_hash: 'c', index_in_block: 0 });
+
+      const rows = await storage.getTransactionsByBlockHash('b');
+      assert.deepStrictEqual(rows.map(r => r.hash), ['t1', 't2']);
+
+      const batch = await storage.getTransactionsByBlockHashes(['c', 'b']);
+      assert.deepStrictEqual(batch.map(r => r.hash), ['t1', 't2', 't3']);
+      assert.deepStrictEqual(await storage.getTransactionsByBlockHashes([]), []);
+    });
+
+    it('should ignore duplicate pending transactions', async function() {
+      await storage.savePendingTransaction({ hash: 'p1', amount: 1 });
+      await storage.savePendingTransaction({ hash: 'p1', amount: 2 });
+
+      assert.strictEqual(await storage.countPendingTransactions(), 1);
+      assert.strictEqual((await storage.getPendingTransactionByHash('p1')).amount, 1);
+
+      await storage.deletePendingTransactions(['p1']);
+      assert.strictEqual(await storage.countPendingTransactions(), 0);
+    });
+  });
+
+  describe('balances', function() {
+    it('should accumulate balance deltas', async function() {
+      assert.strictEqual(await storage.getBalance('a'), null);
+
+      await storage.updateBalance('a', 100);
+      await storage.updateBalance('a', -0.1);
+
+      assert.strictEqual(await storage.getBalance('a'), '99.90000000');
+    });
+  });
+});
+
+describe('migrate', function() {
+  class RecordingStorage {
+    constructor(version) {
+      this.name = 'sqlite';
+      this.version = version;
+      this.applied = [];
+    }
+
+    async getSchemaVersion() {
+      return this.version;
+    }
+
+    async applyMigration(migration) {
+      this.applied.push(migration.version);
+      this.version = migration.version;
+    }
+  }
+
+  it('should apply every migration to an empty database', async function() {
+    const storage = new RecordingStorage(0);
+    const result = await migrate(storage, () => {});
+
+    assert.strictEqual(result.from, 0);
+    assert.strictEqual(result.to, LATEST_SCHEMA_VERSION);
+    assert.strictEqual(storage.applied.length, LATEST_SCHEMA_VERSION);
+    await assertSchemaIsCurrent(storage);
+  });
+
+  it('should not reapply migrations on an up to date database', async function() {
+    const storage = new RecordingStorage(LATEST_SCHEMA_VERSION);
+    const result = await migrate(storage, () => {});
+
+    assert.deepStrictEqual(result.applied, []);
+  });
+
+  it('should refuse to start on an outdated schema', async function() {
+    await assert.rejects(assertSchemaIsCurrent(new RecordingStorage(0)), /npm run migrate/);
+  });
+});
+
+describe('SqlStorage.applyMigration', function() {
+  // Behaves like MySQL: DDL commits at once, so nothing is rolled back when a migration fails
+  class AutocommitStorage extends SqlStorage {
+    constructor() {
+      super('mysql');
+      this.executed = [];
+      this.steps = [];
+      this.versions = [];
+      this.failOn = null;
+    }
+
+    async withTransaction(fn) {
+      return fn(this);
+    }
+
+    async query(sql, params = []) {
+      if (sql.startsWith('SELECT step')) {
+        return this.steps.filter(([version]) => version === params[0]).map(([, step]) => ({ step }));
+      }
+      return this.executed.includes(sql.replace('CHECK ', '')) ? [{ 1: 1 }] : []; // `check` queries
+    }
+
+    async execute(sql, params = []) {
+      if (sql.startsWith('INSERT INTO schema_migration_steps')) {
+        this.steps.push(params);
+      } else if (sql.startsWith('DELETE FROM schema_migration_steps')) {
+        this.steps = this.steps.filter(([version]) => version !== params[0]);
+      } else if (sql.startsWith('INSERT INTO schema_version')) {
+        this.versions.push(params[0]);
+      } else if (sql === this.failOn) {
+        throw new Error(`cannot run ${sql}`);
+      } else {
+        this.executed.push(sql);
+      }
+    }
+  }
+
+  const migration = { version: 9, description: 'test', mysql: ['A', { sql: 'B', check: 'CHECK B' }, 'C'] };
+
+  it('should resume a failed migration after its last completed statement', async function() {
+    const storage = new AutocommitStorage();
+    storage.failOn = 'C';
+    await assert.rejects(storage.applyMigration(migration), /cannot run C/);
+    assert.deepStrictEqual(storage.versions, []);
+
+    storage.failOn = null;
+    await storage.applyMigration(migration);
+    assert.deepStrictEqual(storage.executed, ['A', 'B', 'C']);
+    assert.deepStrictEqual(storage.versions, [9]);
+    assert.deepStrictEqual(storage.steps, []);
+  });
+
+  it('should skip statements whose change is already in place', async function() {
+    const storage = new AutocommitStorage();
+    storage.executed = ['A', 'B']; // B ran, but the process died before its step was recorded
+    storage.steps = [[9, 0]];
+
+    await storage.applyMigration(migration);
+    assert.deepStrictEqual(storage.executed, ['A', 'B', 'C']);
+  });
+});
+
+describe('MemoryStorage transactions', function() {
+  let storage;
+
+  beforeEach(function() {
+    storage = new MemoryStorage();
+  });
+
+  it('should keep writes when the callback succeeds', async function() {
+    await storage.withTransaction(async (store) => {
+      await store.saveBlock({ hash: 'h0', index: 0 });
+      await store.updateBalance('a', 5);
+    });
+
+    assert.ok(await storage.getBlockByHash('h0'));
+    assert.strictEqual(await storage.getBalance('a'), '5.00000000');
+  });
+
+  it('should roll back every write when the callback throws', async function() {
+    await storage.updateBalance('a', 1);
+
+    await assert.rejects(storage.withTransaction(async (store) => {
+      await store.saveBlock({ hash: 'h0', index: 0 });
+      await store.updateBalance('a', 5);
+      throw new Error('boom');
+    }), /boom/);
+
+    assert.strictEqual(await storage.getBlockByHash('h0'), null);
+    assert.strictEqual(await storage.getBalance('a'), '1.00000000');
+  });
+
+  it('should reuse the open transaction when nested', async function() {
+    await assert.rejects(storage.withTransaction(async (store) => {
+      await store.withTransaction(inner => inner.saveBlock({ hash: 'h0', index: 0 }));
+      throw new Error('outer failure');
+    }));
+
+    assert.strictEqual(await storage.getBlockByHash('h0'), null);
+  });
+});
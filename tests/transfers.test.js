@@ -0,0 +1,59 @@
+import assert from 'assert';
+import { Blockchain } from '../src/blockchain.js';
+import { GENESIS_ADDRESS, MINER, resetChain, payment, blockAfter } from './helpers.js';
+
+describe('Transfer rules', function() {
+  this.timeout(10000);
+
+  const blockchain = new Blockchain();
+  let genesis;
+
+  beforeEach(async function() {
+    genesis = await resetChain();
+  });
+
+  it('should reject a received block whose sender overspends', async function() {
+    const overspend = blockAfter(genesis, [await payment(GENESIS_ADDRESS, 1001, { timestamp: genesis.timestamp + 1 })]);
+    assert.strictEqual(await blockchain.addBlock(overspend), false);
+
+    const twice = blockAfter(genesis, [
+      await payment(GENESIS_ADDRESS, 600, { timestamp: genesis.timestamp + 1 }),
+      await payment(GENESIS_ADDRESS, 600, { timestamp: genesis.timestamp + 2 })
+    ]);
+    assert.strictEqual(await blockchain.addBlock(twice), false);
+    assert.strictEqual(blockchain.getHeight(), 0);
+  });
+
+  it('should reject a received block with a wrong Merkle root or a repeated transaction', async function() {
+    const tx = await payment(GENESIS_ADDRESS, 10, { timestamp: genesis.timestamp + 1 });
+    const forged = blockAfter(genesis, [tx]);
+    forged.merkleRoot = 'f'.repeat(64);
+    forged.hash = forged.calculateHash();
+    assert.strictEqual(await blockchain.addBlock(forged), false);
+
+    assert.strictEqual(await blockchain.addBlock(blockAfter(genesis, [tx, tx])), false);
+
+    const first = blockAfter(genesis, [tx]);
+    assert.strictEqual(await blockchain.addBlock(first), true);
+    assert.strictEqual(await blockchain.addBlock(blockAfter(first, [tx])), false);
+    assert.strictEqual(blockchain.getHeight(), 1);
+  });
+
+  it('should only admit transactions the sender can pay on top of its pending ones', async function() {
+    await blockchain.addPendingTransaction(await payment(GENESIS_ADDRESS, 600));
+    await assert.rejects(blockchain.addPendingTransaction(await payment(GENESIS_ADDRESS, 401)), /only holds 400\.00000000/);
+    await assert.rejects(blockchain.addPendingTransaction(await payment(GENESIS_ADDRESS, 0)), /non-positive amount/);
+    await blockchain.addPendingTransaction(await payment(GENESIS_ADDRESS, 400));
+    assert.strictEqual(blockchain.pendingTransactions.length, 2);
+  });
+
+  it('should leave unaffordable pending transactions out of mined blocks', async function() {
+    const affordable = await payment(GENESIS_ADDRESS, 600, { timestamp: Date.now() - 2000 });
+    const unaffordable = await payment(GENESIS_ADDRESS, 600, { timestamp: Date.now() - 1000 });
+    blockchain.pendingTransactions.push(affordable, unaffordable); // E.g. admitted before a reorganization
+
+    const block = await blockchain.minePendingTransactions(MINER);
+    assert.deepStrictEqual(block.transactions.map(tx => tx.hash), [affordable.hash, block.transactions[1].hash]);
+    assert.strictEqual(await blockchain.getBalanceOfAddress(GENESIS_ADDRESS), '400.00000000');
+  });
+});